@@ -15,6 +15,6 @@ void mainImage(out vec4 fragColor, in vec2 fragCoord)
     vec4 texColor = texture(tex1, c);// + texture(tex2, c);
     // texColor = mix(bgColor, texColor, texColor.a); // <-- PSST, IT'S THIS
     float mouse_dis = distance(iMouse.xy, fragCoord);
-    vec3 actual_color = mix(vec3(1), vec3(1, 0, 0), step(mouse_dis, 50 + iMouse.z * 50));
+    vec3 actual_color = mix(vec3(1), vec3(1, 0, 0), step(mouse_dis, 50 + float(iMouse.z > 0.0) * 50));
     fragColor = vec4(actual_color * texColor.rgb, 1.0);
 }
\ No newline at end of file
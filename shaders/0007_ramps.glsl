@@ -0,0 +1,13 @@
+#pragma @palette {widget:"gradient"}
+uniform sampler2D palette;
+#pragma @falloff {widget:"curve"}
+uniform sampler2D falloff;
+#pragma @speed {range:[0, 4]}
+uniform float speed = 1;
+
+void mainImage(out vec4 out_color, in vec2 fragCoord) {
+    vec2 p = (2.0 * fragCoord - iResolution.xy) / iResolution.y;
+    float d = fract(length(p) * 0.5 - iTime * speed * 0.1);
+    float shade = texture(falloff, vec2(d, 0.5)).r;
+    out_color = vec4(texture(palette, vec2(shade, 0.5)).rgb, 1.0);
+}
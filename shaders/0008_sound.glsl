@@ -0,0 +1,17 @@
+#pragma @pitch {range:[110, 880]}
+uniform float pitch = 220;
+
+vec2 mainSound(int samp, float time) {
+    float beat = fract(time * 2.0);
+    float env = exp(-6.0 * beat);
+    float tone = sin(6.2831 * pitch * time) * env;
+    float tick = sin(6.2831 * pitch * 4.0 * time) * exp(-40.0 * beat);
+    return vec2(tone + 0.3 * tick, tone - 0.3 * tick) * 0.5;
+}
+
+void mainImage(out vec4 out_color, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy;
+    float t = iTime + uv.x * 0.01;
+    float y = mainSound(0, t).x * 0.5 + 0.5;
+    out_color = vec4(vec3(smoothstep(0.01, 0.0, abs(uv.y - y))), 1.0);
+}
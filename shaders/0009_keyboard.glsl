@@ -0,0 +1,15 @@
+#pragma @keys {source:"keyboard"}
+uniform sampler2D keys;
+
+float key(int code, int row) {
+    return texelFetch(keys, ivec2(code, row), 0).x;
+}
+
+void mainImage(out vec4 out_color, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy;
+    // Click the viewport to give it focus, then use the arrow keys; space toggles the background.
+    vec2 dir = vec2(key(39, 0) - key(37, 0), key(38, 0) - key(40, 0));
+    float d = length(uv - 0.5 - dir * 0.25);
+    vec3 bg = mix(vec3(0.1), vec3(0.2, 0.1, 0.3), key(32, 2));
+    out_color = vec4(mix(vec3(1.0, 0.8, 0.2), bg, smoothstep(0.05, 0.06, d)), 1.0);
+}
@@ -0,0 +1,37 @@
+// Enable "Camera" in the uniforms panel to orbit (drag/scroll) or fly (WASD/QE) around.
+
+float map(vec3 p) {
+    float ground = p.y + 1.0;
+    vec3 q = p;
+    q.xz = mod(q.xz + 2.0, 4.0) - 2.0;
+    float sphere = length(q) - 0.8;
+    return min(ground, sphere);
+}
+
+vec3 normal(vec3 p) {
+    vec2 e = vec2(0.001, 0.0);
+    return normalize(vec3(
+        map(p + e.xyy) - map(p - e.xyy),
+        map(p + e.yxy) - map(p - e.yxy),
+        map(p + e.yyx) - map(p - e.yyx)
+    ));
+}
+
+void mainImage(out vec4 out_color, in vec2 fragCoord) {
+    vec2 p = (2.0 * fragCoord - iResolution.xy) / iResolution.y;
+    vec3 ro = iCameraPos;
+    vec3 rd = normalize(iCameraMatrix * vec3(p, 1.0 / tan(iFov * 0.5)));
+    float t = 0.0;
+    for (int i = 0; i < 128 && t < 100.0; i++) {
+        float d = map(ro + rd * t);
+        if (d < 0.001) break;
+        t += d;
+    }
+    vec3 col = vec3(0.6, 0.7, 0.9) - rd.y * 0.3;
+    if (t < 100.0) {
+        vec3 n = normal(ro + rd * t);
+        col = vec3(0.8, 0.7, 0.6) * (0.2 + 0.8 * max(dot(n, normalize(vec3(0.5, 0.8, 0.3))), 0.0));
+        col = mix(col, vec3(0.6, 0.7, 0.9), 1.0 - exp(-0.02 * t));
+    }
+    out_color = vec4(col, 1.0);
+}
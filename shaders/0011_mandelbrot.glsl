@@ -0,0 +1,24 @@
+#pragma @center {widget:"viewport", zoom:"zoom"}
+uniform vec2 center = vec2(-0.5, 0.0);
+uniform vec2 center_lo;
+uniform float zoom = 0.8;
+#pragma @iterations {range:[16, 1024]}
+uniform int iterations = 256;
+
+void mainImage(out vec4 out_color, in vec2 fragCoord) {
+    vec2 p = (2.0 * fragCoord - iResolution.xy) / iResolution.y;
+    // Offset from the center first so the low-order part isn't lost at deep zooms.
+    vec2 c = center + (center_lo + p / zoom);
+    vec2 z = vec2(0.0);
+    int i = 0;
+    for (; i < iterations; i++) {
+        z = vec2(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c;
+        if (dot(z, z) > 256.0) break;
+    }
+    if (i == iterations) {
+        out_color = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+    float t = float(i) - log2(log2(dot(z, z))) + 4.0;
+    out_color = vec4(0.5 + 0.5 * cos(3.0 + t * 0.15 + vec3(0.0, 0.6, 1.0)), 1.0);
+}
@@ -0,0 +1,22 @@
+#pragma @a {widget:"point"}
+uniform vec2 a = vec2(0.3, 0.4);
+#pragma @b {widget:"point"}
+uniform vec2 b = vec2(0.7, 0.6);
+#pragma @light {widget:"point", coords:"pixels"}
+uniform vec2 light = vec2(100.0, 100.0);
+
+float segment(vec2 p, vec2 a, vec2 b) {
+    vec2 pa = p - a, ba = b - a;
+    float h = clamp(dot(pa, ba) / dot(ba, ba), 0.0, 1.0);
+    return length(pa - ba * h);
+}
+
+void mainImage(out vec4 out_color, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy;
+    vec2 aspect = vec2(iResolution.x / iResolution.y, 1.0);
+    float d = segment(uv * aspect, a * aspect, b * aspect);
+    float glow = 40.0 / (1.0 + length(fragCoord - light));
+    vec3 col = vec3(0.1) + vec3(0.9, 0.7, 0.3) * glow;
+    col = mix(vec3(1.0), col, smoothstep(0.004, 0.008, d));
+    out_color = vec4(col, 1.0);
+}
@@ -1,11 +1,39 @@
+use crate::audio::{AUDIO_SUFFIXES, AudioLoadResponse, AudioTrack, is_audio_file};
+use crate::camera::camera_controls;
+use crate::config::Config;
+use crate::defines::{DefineInfo, DefineValues, inject_defines, parse_defines};
+use crate::editor::{Editor, error_line};
+use crate::file_browser::file_browser;
 use crate::file_change::{FileChangeState, has_changed};
 use crate::file_collection::FileCollection;
+use crate::file_watcher::FileWatcher;
 use crate::frame_history::FrameHistory;
+use crate::gallery::Gallery;
+use crate::isf;
 use crate::label_strip::label_strip;
-use crate::shader_frame::{Custom3d, ShaderCompileResponse};
-use crate::textures::{Textures, WrappedTexture};
+use crate::log_console::{self, LogConsole, shader_scope};
+use crate::new_shader::NewShaderWindow;
+use crate::playback::AudioPlayer;
+use crate::playlist::{AdvanceUnit, Playlist, PreparedEntry};
+use crate::point_gizmos::point_targets;
+use crate::prelude::PreludeMode;
+use crate::ramps::bake_ramp_images;
+use crate::screenshot::save_screenshot;
+use crate::session::{
+    ClockSession, PanelsSession, Session, ShaderSession, TextureSlot, WindowSession,
+};
+use crate::shader_frame::{CROSSFADE_TRANSITION, Custom3d, ShaderCompileResponse, Transition};
+use crate::shader_parser::{PreparseResult, UniformSource};
+use crate::shadertoy_export;
+use crate::shortcuts::{Action, Shortcuts};
+use crate::sound::{SoundRenderRequest, SoundRenderResponse, SoundRenderUpdate};
+use crate::sound_window::SoundWindow;
+use crate::textures::{NamedDataImages, Textures, WrappedTexture};
+use crate::time_controls::{beat_controls, time_controls};
 use crate::uniforms_box;
 use crate::uniforms_values::UniformsValues;
+use crate::viewport_nav::viewport_targets;
+use crate::warp::Warp;
 use clap::Parser;
 use eframe::{Frame, glow};
 use egui::{
@@ -13,10 +41,13 @@ use egui::{
     RichText, TextureHandle, TextureOptions,
 };
 use image::{DynamicImage, ImageError};
-use std::path::PathBuf;
-use std::sync::mpsc;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, mpsc};
 use std::time::Duration;
 
+const IMAGE_SUFFIXES: [&str; 5] = [".jpg", ".jpeg", ".png", ".webp", ".gif"];
+
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 pub struct Options {
@@ -24,14 +55,25 @@ pub struct Options {
     shader: Option<PathBuf>,
     #[arg(short, long, env = "VARJOSTIN_VSYNC", default_value_t = true)]
     pub vsync: bool,
-    #[arg(long, env = "VARJOSTIN_IMAGES_DIR", default_value = "./images")]
-    images_dir: PathBuf,
-    #[arg(long, env = "VARJOSTIN_SHADERS_DIR", default_value = "./shaders")]
-    shaders_dir: PathBuf,
+    /// Image directory; may be given several times, or comma-separated [default: ./images]
+    #[arg(long, env = "VARJOSTIN_IMAGES_DIR", value_delimiter = ',')]
+    images_dir: Vec<PathBuf>,
+    /// Shader directory; may be given several times, or comma-separated [default: ./shaders]
+    #[arg(long, env = "VARJOSTIN_SHADERS_DIR", value_delimiter = ',')]
+    shaders_dir: Vec<PathBuf>,
+    /// Where output warp settings are loaded from and saved to
+    #[arg(
+        long,
+        env = "VARJOSTIN_WARP_CONFIG",
+        default_value = "./varjostin-warp.json5"
+    )]
+    warp_config: PathBuf,
+    /// Shader paths to cycle through, one per line, instead of the shader collection
+    #[arg(long, env = "VARJOSTIN_PLAYLIST")]
+    playlist: Option<PathBuf>,
 }
 
 pub struct VarjostinApp {
-    #[allow(dead_code)]
     options: Options,
     custom3d: Custom3d,
     continuous: bool,
@@ -40,14 +82,75 @@ pub struct VarjostinApp {
     shader_compile_result_outbox: mpsc::Sender<ShaderCompileResponse>,
     last_shader_compile_result: Option<ShaderCompileResponse>,
     shader_path: Option<PathBuf>,
+    /// The shader file as last read, before defines are injected.
+    shader_source: Option<String>,
+    defines: Vec<DefineInfo>,
+    /// Define values the current program was compiled with.
+    compiled_define_values: DefineValues,
     edit_shader_path: String,
     shader_change_state: Option<FileChangeState>,
+    /// Read and compile the shader file on the next frame.
+    reload_shader: bool,
+    /// `None` if watching isn't available, in which case the shader file is polled.
+    file_watcher: Option<FileWatcher>,
     uniforms_values: UniformsValues,
     textures: Textures,
+    audio_load_inbox: mpsc::Receiver<AudioLoadResponse>,
+    audio_load_outbox: mpsc::Sender<AudioLoadResponse>,
+    audio_player: AudioPlayer,
     collections_initialized: bool,
     texture_collection: FileCollection,
     shader_collection: FileCollection,
+    shader_search: String,
+    texture_search: String,
     default_texture: TextureHandle,
+    sound_window: SoundWindow,
+    /// Hide the panels and fill the main window with the shader.
+    presentation: bool,
+    /// Show the shader in a separate native window instead of the main one.
+    output_window: bool,
+    output_fullscreen: bool,
+    warp: Warp,
+    playlist: Playlist,
+    gallery: Gallery,
+    /// Uniform values and textures of shaders switched away from.
+    shader_states: HashMap<PathBuf, (UniformsValues, Textures)>,
+    /// Main window geometry as of the last frame outside fullscreen.
+    window: Option<WindowSession>,
+    shortcuts: Shortcuts,
+    /// Hide the panels without going fullscreen.
+    panels_hidden: bool,
+    screenshot_dir: PathBuf,
+    /// Save the next screenshot of the shader's viewport.
+    screenshot_pending: bool,
+    new_shader: NewShaderWindow,
+    editor: Editor,
+    log_console: LogConsole,
+    /// Where the log records of the current shader's latest compile start.
+    compile_log_sequence: u64,
+}
+
+/// Write `text` to `path`, refusing to replace an existing file.
+fn write_new_file(path: &Path, text: &str) -> eyre::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| eyre::eyre!("Can't create {}: {}", path.display(), e))?;
+    std::io::Write::write_all(&mut file, text.as_bytes())?;
+    Ok(())
+}
+
+fn texture_slot(texture: &WrappedTexture) -> TextureSlot {
+    if texture.keyboard {
+        TextureSlot::Keyboard
+    } else if let Some(path) = &texture.path {
+        TextureSlot::File(path.clone())
+    } else if texture.handle.is_some() {
+        TextureSlot::Default
+    } else {
+        TextureSlot::Empty
+    }
 }
 
 fn get_fonts() -> FontDefinitions {
@@ -79,37 +182,84 @@ fn to_color_image(image: DynamicImage) -> Result<ColorImage, ImageError> {
 
 impl VarjostinApp {
     /// Called once before the first frame.
-    pub fn new(cc: &eframe::CreationContext<'_>, options: Options) -> Self {
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        mut options: Options,
+        config: Config,
+        session: Session,
+    ) -> Self {
         let ctx = &cc.egui_ctx;
         egui_extras::install_image_loaders(ctx);
         ctx.set_theme(egui::Theme::Dark);
         ctx.set_fonts(get_fonts());
         let (scr_sender, scr_receiver) = mpsc::channel();
+        let (audio_load_outbox, audio_load_inbox) = mpsc::channel();
         let mut custom3d = Custom3d::new(cc).unwrap();
+        custom3d.render_scale = config.render_scale.unwrap_or(1.0);
+        custom3d.prelude_mode = config.prelude_mode;
+        if options.images_dir.is_empty() {
+            options.images_dir =
+                (config.images_dirs.clone()).unwrap_or_else(|| vec![PathBuf::from("./images")]);
+        }
+        if options.shaders_dir.is_empty() {
+            options.shaders_dir =
+                (config.shaders_dirs.clone()).unwrap_or_else(|| vec![PathBuf::from("./shaders")]);
+        }
         custom3d.request_shader_compile(
             include_str!("test_fragment.glsl").to_owned(),
+            None,
             scr_sender.clone(),
         );
         let shader_path = options.shader.clone();
+        let warp = Warp::load(&options.warp_config);
+        let playlist = Playlist::new(options.playlist.as_deref());
         let edit_shader_path = shader_path
             .as_ref()
             .map(|p| p.to_string_lossy().to_string())
             .unwrap_or_default();
 
-        let image = load_image_from_memory(include_bytes!("./texture_05.png")).unwrap();
-        let texture = ctx.load_texture("texture_05", image, TextureOptions::LINEAR);
-        let texture_collection =
-            FileCollection::new(&options.images_dir, &[".jpg", ".jpeg", ".png"]);
-        let shader_collection = FileCollection::new(&options.shaders_dir, &[".glsl"]);
+        let texture = match &config.default_texture {
+            Some(path) => image::open(path)
+                .map_err(eyre::Error::from)
+                .and_then(|image| Ok(to_color_image(image)?))
+                .map(|image| {
+                    ctx.load_texture(path.to_string_lossy(), image, TextureOptions::LINEAR)
+                })
+                .inspect_err(|e| log::error!("Error loading default texture {:?}: {:?}", path, e))
+                .ok(),
+            None => None,
+        };
+        let texture = texture.unwrap_or_else(|| {
+            let image = load_image_from_memory(include_bytes!("./texture_05.png")).unwrap();
+            ctx.load_texture("texture_05", image, TextureOptions::LINEAR)
+        });
+        let texture_collection = FileCollection::new(
+            &options.images_dir,
+            &[&IMAGE_SUFFIXES[..], &AUDIO_SUFFIXES[..]].concat(),
+        );
+        let shader_collection = FileCollection::new(&options.shaders_dir, &[".glsl", ".fs"]);
+        let file_watcher = match FileWatcher::new(ctx) {
+            Ok(mut watcher) => {
+                for root in options.images_dir.iter().chain(&options.shaders_dir) {
+                    watcher.watch_tree(root);
+                }
+                Some(watcher)
+            }
+            Err(e) => {
+                log::warn!("Error setting up file watching, polling instead: {:?}", e);
+                None
+            }
+        };
         let textures = [
             WrappedTexture {
                 handle: Some(texture.clone()),
+                ..Default::default()
             },
             WrappedTexture::default(),
             WrappedTexture::default(),
             WrappedTexture::default(),
         ];
-        Self {
+        let mut app = Self {
             continuous: true,
             collections_initialized: false,
             custom3d,
@@ -118,72 +268,259 @@ impl VarjostinApp {
             last_shader_compile_result: None,
             options,
             shader_change_state: None,
+            reload_shader: true,
+            file_watcher,
             shader_collection,
+            shader_search: String::new(),
+            texture_search: String::new(),
             shader_compile_result_inbox: scr_receiver,
             shader_compile_result_outbox: scr_sender,
             shader_path,
+            shader_source: None,
+            defines: Vec::new(),
+            compiled_define_values: DefineValues::default(),
             texture_collection,
             default_texture: texture,
+            sound_window: SoundWindow::new(&config.recording),
+            presentation: false,
+            output_window: false,
+            output_fullscreen: false,
+            warp,
+            playlist,
+            gallery: Gallery::default(),
+            shader_states: HashMap::default(),
             textures,
+            audio_load_inbox,
+            audio_load_outbox,
+            audio_player: AudioPlayer::default(),
             uniforms_values: UniformsValues::default(),
+            window: session.window,
+            shortcuts: Shortcuts::new(&config.keys),
+            panels_hidden: false,
+            screenshot_dir: config
+                .recording
+                .screenshot_dir
+                .unwrap_or_else(|| PathBuf::from(".")),
+            screenshot_pending: false,
+            new_shader: NewShaderWindow::default(),
+            editor: Editor::new(config.editor),
+            log_console: LogConsole::default(),
+            compile_log_sequence: 0,
+        };
+        app.restore_session(ctx, session);
+        app
+    }
+
+    /// Pick up where the last run left off; with `--shader`, that shader keeps its stored values
+    /// but the clocks aren't restored.
+    fn restore_session(&mut self, ctx: &Context, session: Session) {
+        let restore_shader = self.options.shader.is_none();
+        self.gallery.open = session.panels.gallery;
+        self.playlist.open = session.panels.playlist;
+        self.warp.open = session.panels.warp;
+        self.sound_window.open = session.panels.sound;
+        self.output_window = session.panels.output_window;
+        self.log_console.open = session.panels.log;
+        for (path, shader) in session.shaders {
+            let textures = self.restore_textures(ctx, &path, &shader.textures);
+            if self.shader_path.as_ref() == Some(&path) {
+                self.uniforms_values = shader.uniforms_values;
+                self.textures = textures;
+            } else {
+                self.shader_states
+                    .insert(path, (shader.uniforms_values, textures));
+            }
+        }
+        if restore_shader {
+            if let Some(clock) = session.clock {
+                clock.apply(&mut self.custom3d.clock, &mut self.custom3d.beat_clock);
+            }
+            if let Some(path) = session.shader_path {
+                self.open_shader(path);
+            }
+        }
+    }
+
+    fn restore_textures(
+        &mut self,
+        ctx: &Context,
+        shader_path: &Path,
+        slots: &[TextureSlot; 4],
+    ) -> Textures {
+        let current = std::mem::take(&mut self.textures);
+        for (index, slot) in slots.iter().enumerate() {
+            match slot {
+                TextureSlot::Empty => {}
+                TextureSlot::Default => self.load_image_at_index(index, ctx, None),
+                TextureSlot::Keyboard => self.textures[index].keyboard = true,
+                TextureSlot::File(path) if is_audio_file(path) => {
+                    self.load_audio(ctx, Some(shader_path.to_path_buf()), index, path);
+                }
+                TextureSlot::File(path) => self.load_image_at_index(index, ctx, Some(path)),
+            }
+        }
+        std::mem::replace(&mut self.textures, current)
+    }
+
+    fn session(&self) -> Session {
+        let shader_session =
+            |uniforms_values: &UniformsValues, textures: &Textures| ShaderSession {
+                uniforms_values: uniforms_values.clone(),
+                textures: textures.each_ref().map(texture_slot),
+            };
+        let mut shaders: HashMap<PathBuf, ShaderSession> = self
+            .shader_states
+            .iter()
+            .map(|(path, (uniforms_values, textures))| {
+                (path.clone(), shader_session(uniforms_values, textures))
+            })
+            .collect();
+        if let Some(path) = &self.shader_path {
+            shaders.insert(
+                path.clone(),
+                shader_session(&self.uniforms_values, &self.textures),
+            );
+        }
+        Session {
+            shader_path: self.shader_path.clone(),
+            shaders,
+            clock: Some(ClockSession::from_clocks(
+                &self.custom3d.clock,
+                &self.custom3d.beat_clock,
+            )),
+            panels: PanelsSession {
+                gallery: self.gallery.open,
+                playlist: self.playlist.open,
+                warp: self.warp.open,
+                sound: self.sound_window.open,
+                output_window: self.output_window,
+                log: self.log_console.open,
+            },
+            window: self.window,
         }
     }
 
     fn update_collections(&mut self) {
         match self.texture_collection.collect_files() {
             Ok(n) => {
-                eprintln!("Collected {} images", n);
+                log::info!("Collected {} images", n);
             }
             Err(e) => {
-                eprintln!("Error collecting images: {:?}", e);
+                log::error!("Error collecting images: {:?}", e);
             }
         }
         match self.shader_collection.collect_files() {
             Ok(n) => {
-                eprintln!("Collected {} shaders", n);
+                log::info!("Collected {} shaders", n);
             }
             Err(e) => {
-                eprintln!("Error collecting shaders: {:?}", e);
+                log::error!("Error collecting shaders: {:?}", e);
             }
         }
     }
 
-    fn check_shader_state(&mut self) {
+    fn check_shader_state(&mut self, ctx: &Context) {
         if !self.collections_initialized {
             self.collections_initialized = true;
             self.update_collections();
         }
-        let change_res = if self.shader_path.is_some() {
-            has_changed(
-                self.shader_path.as_ref().unwrap(),
-                &self.shader_change_state,
-                Duration::from_millis(200),
-            )
-        } else {
-            Ok(None)
+        match &mut self.file_watcher {
+            Some(watcher) => {
+                let files = self
+                    .shader_path
+                    .iter()
+                    .chain(
+                        self.textures
+                            .iter()
+                            .filter_map(|texture| texture.path.as_ref()),
+                    )
+                    .cloned()
+                    .collect();
+                watcher.watch_files(files);
+                let events = watcher.poll();
+                if events.trees {
+                    self.update_collections();
+                }
+                for path in events.files {
+                    if self.shader_path.as_ref() == Some(&path) {
+                        log::info!("Shader changed: {:?}", path);
+                        self.reload_shader = true;
+                    }
+                    self.reload_textures(ctx, &path);
+                }
+            }
+            None => self.poll_shader_change(),
+        }
+        if self.reload_shader
+            && let Some(shader_path) = &self.shader_path
+        {
+            self.reload_shader = false;
+            match std::fs::read_to_string(shader_path) {
+                Ok(source) => {
+                    self.defines = parse_defines(&source);
+                    self.shader_source = Some(source);
+                    self.compile_shader();
+                }
+                Err(e) => {
+                    log::error!("Error reading shader {:?}: {}", shader_path, e);
+                    self.shader_source = None;
+                    self.last_shader_compile_result = Some(ShaderCompileResponse {
+                        duration: Duration::default(),
+                        preparse_result: None,
+                        error: Some(eyre::eyre!(e)),
+                    });
+                }
+            }
+        }
+        if self.shader_source.is_some()
+            && self.uniforms_values.define_values != self.compiled_define_values
+        {
+            self.compile_shader();
+        }
+        if let Ok(result) = self.shader_compile_result_inbox.try_recv() {
+            if let Some(e) = &result.error {
+                log::error!("{}", e);
+            }
+            self.last_shader_compile_result = Some(result);
+        }
+    }
+
+    /// Compile the shader source with the current define values.
+    fn compile_shader(&mut self) {
+        let Some(source) = &self.shader_source else {
+            return;
+        };
+        let define_values = &self.uniforms_values.define_values;
+        self.compile_log_sequence = log_console::next_sequence();
+        self.custom3d.request_shader_compile(
+            inject_defines(source, &self.defines, define_values),
+            self.shader_path.clone(),
+            self.shader_compile_result_outbox.clone(),
+        );
+        self.compiled_define_values = define_values.clone();
+    }
+
+    /// Fallback for when the file watcher can't be set up.
+    fn poll_shader_change(&mut self) {
+        let Some(shader_path) = &self.shader_path else {
+            return;
         };
-        match change_res {
+        match has_changed(
+            shader_path,
+            &self.shader_change_state,
+            Duration::from_millis(200),
+        ) {
             Ok(Some(new_state)) => {
-                eprintln!("Shader changed: {:?}", new_state);
+                log::info!("Shader changed: {:?}", new_state);
                 self.shader_change_state = Some(new_state);
-                match std::fs::read_to_string(self.shader_path.as_ref().unwrap()) {
-                    Ok(fragment_source) => {
-                        self.custom3d.request_shader_compile(
-                            fragment_source,
-                            self.shader_compile_result_outbox.clone(),
-                        );
-                    }
-                    Err(e) => {
-                        self.shader_change_state = None;
-                        self.last_shader_compile_result = Some(ShaderCompileResponse {
-                            duration: Duration::default(),
-                            preparse_result: None,
-                            error: Some(eyre::eyre!(e)),
-                        });
-                    }
-                }
+                self.reload_shader = true;
             }
             Ok(None) => {}
+            // Likely mid-way through an atomic save; it'll be back.
+            Err(e)
+                if e.downcast_ref::<std::io::Error>()
+                    .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound)
+                    && self.shader_change_state.is_some() => {}
             Err(e) => {
                 self.shader_change_state = None;
                 self.last_shader_compile_result = Some(ShaderCompileResponse {
@@ -193,12 +530,20 @@ impl VarjostinApp {
                 });
             }
         }
-        if let Ok(result) = self.shader_compile_result_inbox.try_recv() {
-            self.last_shader_compile_result = Some(result);
+    }
+
+    /// Reload the texture slots fed from `path`.
+    fn reload_textures(&mut self, ctx: &Context, path: &PathBuf) {
+        for index in 0..self.textures.len() {
+            if self.textures[index].path.as_ref() == Some(path) {
+                log::info!("Texture changed: {:?}", path);
+                self.load_image_at_index(index, ctx, Some(path));
+            }
         }
     }
 
     fn top_bar(&mut self, ctx: &Context) {
+        let mut open_path = None;
         egui::TopBottomPanel::top("menu").show(ctx, |ui| {
             ui.with_layout(
                 egui::Layout::left_to_right(Align::Min).with_cross_justify(true),
@@ -207,39 +552,113 @@ impl VarjostinApp {
                         .selected_text("Shaders...")
                         .close_behavior(PopupCloseBehavior::CloseOnClickOutside)
                         .show_ui(ui, |ui| {
-                            for (label, path_buf) in self.shader_collection.files.iter() {
-                                if ui.selectable_label(false, label).clicked() {
-                                    self.shader_path = Some(path_buf.clone());
-                                    self.edit_shader_path =
-                                        path_buf.to_string_lossy().to_string().clone();
-                                    self.shader_change_state = None;
+                            if let Some(path) =
+                                file_browser(ui, &self.shader_collection, &mut self.shader_search)
+                            {
+                                open_path = Some(path);
+                            }
+                        });
+                    ui.toggle_value(&mut self.new_shader.open, "New")
+                        .on_hover_text("Create a shader from a template");
+                    if ui
+                        .add_enabled(
+                            self.shader_path.is_some() && self.editor.is_available(),
+                            egui::Button::new("Edit"),
+                        )
+                        .on_hover_text("Open the shader in the editor")
+                        .on_disabled_hover_text(
+                            "Set `editor` in the config, $VISUAL or $EDITOR to edit shaders",
+                        )
+                        .clicked()
+                    {
+                        self.edit_shader(1);
+                    }
+                    ui.add_enabled_ui(self.shader_path.is_some(), |ui| {
+                        ui.menu_button("Export", |ui| {
+                            if ui
+                                .button("ISF")
+                                .on_hover_text("Write the shader as an ISF .fs file next to it, if there isn't one")
+                                .clicked()
+                            {
+                                self.export_isf();
+                                ui.close_menu();
+                            }
+                            if ui
+                                .button("Shadertoy to clipboard")
+                                .on_hover_text(
+                                    "Copy the shader for Shadertoy with the current uniform values",
+                                )
+                                .clicked()
+                            {
+                                if let Some(code) = self.shadertoy_code() {
+                                    ui.ctx().copy_text(code);
+                                    log::info!("Copied the shader for Shadertoy");
                                 }
+                                ui.close_menu();
+                            }
+                            if ui
+                                .button("Shadertoy file")
+                                .on_hover_text(
+                                    "Write the shader for Shadertoy with the current uniform values next to it",
+                                )
+                                .clicked()
+                            {
+                                self.export_shadertoy();
+                                ui.close_menu();
                             }
                         });
+                    });
+                    ui.toggle_value(&mut self.gallery.open, "Gallery")
+                        .on_hover_text("Browse shaders by thumbnail");
                     if ui.button("R").on_hover_text("Refresh").clicked() {
                         self.update_collections();
                     }
+                    if ui.button("♪").on_hover_text("Render sound").clicked() {
+                        self.sound_window.open = !self.sound_window.open;
+                    }
+                    if ui
+                        .button("Present")
+                        .on_hover_text(format!(
+                            "Hide the panels and go fullscreen ({}, Esc to leave)",
+                            self.shortcuts.format(ctx, Action::Presentation)
+                        ))
+                        .clicked()
+                    {
+                        self.set_presentation(ctx, true);
+                    }
+                    ui.toggle_value(&mut self.playlist.open, "Playlist")
+                        .on_hover_text("Cycle through shaders automatically");
+                    ui.toggle_value(&mut self.log_console.open, "Log")
+                        .on_hover_text("Warnings, errors and other messages");
+                    ui.toggle_value(&mut self.warp.open, "Warp")
+                        .on_hover_text("Keystone/mesh warp the output for projection mapping");
+                    ui.toggle_value(&mut self.output_window, "Window")
+                        .on_hover_text(format!(
+                            "Show the output in a separate window ({} there for fullscreen)",
+                            self.shortcuts.format(ctx, Action::Presentation)
+                        ));
                     if ui
                         .text_edit_singleline(&mut self.edit_shader_path)
                         .lost_focus()
                     {
-                        self.shader_path = Some(PathBuf::from(&self.edit_shader_path));
-                        self.shader_change_state = None;
-                    }
-                    if ui.button("Reset time").clicked() {
-                        self.custom3d.reset();
+                        open_path = Some(PathBuf::from(&self.edit_shader_path));
                     }
                     label_strip(
                         ui,
                         vec![
                             format!("Time: {:.2}", self.custom3d.curr_time()),
-                            format!("Frame: {}", self.custom3d.frame),
+                            format!("Frame: {}", self.custom3d.clock.frame()),
                             format!(
                                 "Mouse: {}x{}{}",
-                                self.custom3d.mouse_x as i32,
-                                self.custom3d.mouse_y as i32,
-                                if self.custom3d.mouse_down {
-                                    format!(" ({:.2})", self.custom3d.mouse_down_seconds)
+                                self.custom3d.mouse.position[0] as i32,
+                                self.custom3d.mouse.position[1] as i32,
+                                if self.custom3d.mouse.down {
+                                    format!(
+                                        " ({:.2})",
+                                        self.custom3d
+                                            .mouse
+                                            .down_seconds(self.custom3d.clock.time())
+                                    )
                                 } else {
                                     "".to_string()
                                 }
@@ -249,6 +668,230 @@ impl VarjostinApp {
                 },
             );
         });
+        if let Some(path) = open_path {
+            self.open_shader(path);
+        }
+    }
+
+    /// Switch to another shader, keeping each shader's uniform values and textures.
+    fn open_shader(&mut self, path: PathBuf) {
+        if let Some(old_path) = self.shader_path.take() {
+            self.shader_states.insert(
+                old_path,
+                (self.uniforms_values.clone(), self.textures.clone()),
+            );
+        }
+        match self.shader_states.remove(&path) {
+            Some((uniforms_values, textures)) => {
+                self.uniforms_values = uniforms_values;
+                self.textures = textures;
+            }
+            None => self.uniforms_values = UniformsValues::default(),
+        }
+        self.edit_shader_path = path.to_string_lossy().to_string();
+        self.shader_source = None;
+        self.shader_change_state = has_changed(&path, &None, Duration::ZERO).ok().flatten();
+        self.shader_path = Some(path);
+        self.reload_shader = true;
+    }
+
+    fn edit_shader(&self, line: usize) {
+        let Some(path) = &self.shader_path else {
+            return;
+        };
+        if let Err(e) = self.editor.open(path, line) {
+            log::error!("Error opening {:?} in the editor: {:?}", path, e);
+        }
+    }
+
+    /// Write the shader and its pragmas as `<name>.fs` next to it, unless there's one already.
+    fn export_isf(&self) {
+        let (Some(path), Some(source)) = (&self.shader_path, &self.shader_source) else {
+            return;
+        };
+        let _scope = shader_scope(Some(path));
+        let Some(Ok(ppr)) = self
+            .last_shader_compile_result
+            .as_ref()
+            .and_then(|r| r.preparse_result.as_ref())
+        else {
+            log::error!("The shader has to compile to be exported");
+            return;
+        };
+        let mode = PreludeMode::detect(source, self.custom3d.prelude_mode);
+        let export_path = path.with_extension("fs");
+        let result =
+            isf::export(source, ppr, mode).and_then(|text| write_new_file(&export_path, &text));
+        match result {
+            Ok(()) => log::info!("Exported {}", export_path.display()),
+            Err(e) => log::error!("Error exporting to ISF: {}", e),
+        }
+    }
+
+    /// The shader as Shadertoy code with the current uniform and define values baked in.
+    fn shadertoy_code(&self) -> Option<String> {
+        let (Some(path), Some(source)) = (&self.shader_path, &self.shader_source) else {
+            return None;
+        };
+        let _scope = shader_scope(Some(path));
+        let Some(Ok(ppr)) = self
+            .last_shader_compile_result
+            .as_ref()
+            .and_then(|r| r.preparse_result.as_ref())
+        else {
+            log::error!("The shader has to compile to be exported");
+            return None;
+        };
+        let mode = PreludeMode::detect(source, self.custom3d.prelude_mode);
+        let source = inject_defines(source, &self.defines, &self.uniforms_values.define_values);
+        shadertoy_export::export(
+            &source,
+            ppr,
+            &self.uniforms_values,
+            &self.custom3d.beat_clock,
+            mode,
+        )
+        .inspect_err(|e| log::error!("Error exporting to Shadertoy: {}", e))
+        .ok()
+    }
+
+    /// Write the shader as Shadertoy code to `<name>.shadertoy.frag` next to it, unless there's
+    /// one already.
+    fn export_shadertoy(&self) {
+        let (Some(path), Some(code)) = (&self.shader_path, self.shadertoy_code()) else {
+            return;
+        };
+        let export_path = path.with_extension("shadertoy.frag");
+        match write_new_file(&export_path, &code) {
+            Ok(()) => log::info!("Exported {}", export_path.display()),
+            Err(e) => log::error!("Error exporting to Shadertoy: {}", e),
+        }
+    }
+
+    /// Advance the playlist: compile the next entry ahead of time and switch to it when due.
+    fn autopilot(&mut self) {
+        if !self.playlist.enabled {
+            return;
+        }
+        let entries = self.playlist.entries(&self.shader_collection.files);
+        if entries.is_empty() {
+            return;
+        }
+        let time = self.custom3d.clock.time();
+        let position = match self.playlist.unit {
+            AdvanceUnit::Seconds => time,
+            AdvanceUnit::Beats => self.custom3d.beat_clock.beat(time),
+        };
+        let slot_start = match self.playlist.slot_start {
+            // The clock looped or was reset.
+            Some(start) if start <= position => start,
+            _ => {
+                self.playlist.slot_start = Some(position);
+                position
+            }
+        };
+        let Some(prepared) = &mut self.playlist.prepared else {
+            let index = match self.playlist.next.take() {
+                Some(index) if index < entries.len() => index,
+                _ => (self.playlist.index + 1) % entries.len(),
+            };
+            let path = entries[index].clone();
+            match std::fs::read_to_string(&path) {
+                Ok(source) => {
+                    let log_sequence = log_console::next_sequence();
+                    let define_values = self
+                        .shader_states
+                        .get(&path)
+                        .map(|(uniforms_values, _)| uniforms_values.define_values.clone())
+                        .unwrap_or_default();
+                    self.custom3d.request_shader_prepare(
+                        inject_defines(&source, &parse_defines(&source), &define_values),
+                        Some(path.clone()),
+                        self.playlist.outbox.clone(),
+                    );
+                    self.playlist.prepared = Some(PreparedEntry {
+                        index,
+                        path,
+                        source,
+                        log_sequence,
+                        response: None,
+                    });
+                }
+                Err(e) => {
+                    log::warn!("Skipping {:?} in the playlist: {:?}", path, e);
+                    self.playlist.index = index;
+                }
+            }
+            return;
+        };
+        if prepared.response.is_none() {
+            prepared.response = self.playlist.inbox.try_recv().ok();
+        }
+        let due = self.playlist.skip || position - slot_start >= self.playlist.interval;
+        if !due || prepared.response.is_none() {
+            return;
+        }
+        let Some(PreparedEntry {
+            index,
+            path,
+            source: shader_source,
+            log_sequence,
+            response: Some(response),
+        }) = self.playlist.prepared.take()
+        else {
+            return;
+        };
+        self.playlist.index = index;
+        if let Some(e) = &response.error {
+            log::warn!("Skipping {:?} in the playlist: {:?}", path, e);
+            return;
+        }
+        self.playlist.skip = false;
+        self.playlist.slot_start = Some(position);
+
+        let source = match self.playlist.transition_shader.trim() {
+            "" => CROSSFADE_TRANSITION.to_owned(),
+            transition_path => std::fs::read_to_string(transition_path).unwrap_or_else(|e| {
+                log::error!(
+                    "Error reading transition shader {:?}: {:?}",
+                    transition_path,
+                    e
+                );
+                CROSSFADE_TRANSITION.to_owned()
+            }),
+        };
+        let preparse_result = match &self.last_shader_compile_result {
+            Some(ShaderCompileResponse {
+                preparse_result: Some(Ok(ppr)),
+                ..
+            }) => Some(ppr.clone()),
+            _ => None,
+        };
+        let transition = Transition {
+            start_time: time,
+            duration: self.playlist.transition_duration,
+            source,
+            uniforms_values: self.uniforms_values.clone(),
+            textures: self.textures.clone(),
+            preparse_result,
+        };
+        self.open_shader(path);
+        // The prepared program is already up to date with the file.
+        self.reload_shader = false;
+        self.compile_log_sequence = log_sequence;
+        self.defines = parse_defines(&shader_source);
+        self.shader_source = Some(shader_source);
+        self.compiled_define_values = self.uniforms_values.define_values.clone();
+        self.last_shader_compile_result = Some(response);
+        self.custom3d.request_swap_prepared(transition);
+    }
+
+    fn time_bar(&mut self, ctx: &Context) {
+        egui::TopBottomPanel::top("time").show(ctx, |ui| {
+            time_controls(&mut self.custom3d.clock, ui);
+            let time = self.custom3d.clock.time();
+            beat_controls(&mut self.custom3d.beat_clock, time, ui);
+        });
     }
 
     fn bottom_bar(&mut self, ctx: &Context) {
@@ -256,6 +899,13 @@ impl VarjostinApp {
         egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.checkbox(&mut self.continuous, "Update continuously");
+                ui.add(
+                    egui::DragValue::new(&mut self.custom3d.render_scale)
+                        .range(0.1..=1.0)
+                        .speed(0.01)
+                        .prefix("Scale: "),
+                )
+                .on_hover_text("Render at a fraction of the resolution");
                 ui.label(format!(
                     "Mean: {:.2} ms/f ({:.1} FPS)",
                     1e3 * self.frame_history.mean_frame_time(),
@@ -272,50 +922,106 @@ impl VarjostinApp {
                         result.duration
                     ));
                 }
+                let warnings = self.shader_path.as_deref().map_or(0, |path| {
+                    log_console::warning_count(path, self.compile_log_sequence)
+                });
+                if warnings > 0
+                    && ui
+                        .button(
+                            RichText::new(format!("⚠ {}", warnings)).color(egui::Color32::YELLOW),
+                        )
+                        .on_hover_text("Warnings for this shader; click to show the log")
+                        .clicked()
+                {
+                    self.log_console.open = true;
+                }
             });
         });
     }
 
     fn uniforms_bar(&mut self, ctx: &Context) {
         let last_shader_compile_result = &self.last_shader_compile_result;
-        if let Some(result) = last_shader_compile_result {
-            if let Some(Ok(preparse_result)) = &result.preparse_result {
-                let ppr = preparse_result.clone();
-                let texes = &self.texture_collection.files.clone();
-                egui::SidePanel::right("settings")
-                    .max_width(250f32)
-                    .show(ctx, |ui| {
-                        uniforms_box::uniforms_box(&mut self.uniforms_values, &ppr, ui);
-                        let uniform_names = ppr.sampler_uniform_names();
-                        for index in 0..4 {
-                            ui.group(|ui| {
-                                let default_label = format!("<<sampler {}>>", index + 1);
-                                let label = uniform_names.get(index).unwrap_or(&default_label);
-                                ui.label(label);
-                                egui::ComboBox::new(format!("tex_select_{}", index), "")
-                                    .selected_text("Texture...")
-                                    .show_ui(ui, |ui| {
-                                        if ui.selectable_label(false, "Default").clicked() {
-                                            self.load_image_at_index(index, ctx, None);
-                                        }
-                                        for (label, path_buf) in texes.iter() {
-                                            if ui.selectable_label(false, label).clicked() {
-                                                self.load_image_at_index(
-                                                    index,
-                                                    ctx,
-                                                    Some(path_buf),
-                                                );
-                                            }
-                                        }
-                                    });
-                            });
-                        }
-                    });
-            }
+        if let Some(result) = last_shader_compile_result
+            && let Some(Ok(preparse_result)) = &result.preparse_result
+        {
+            let ppr = preparse_result.clone();
+            egui::SidePanel::right("settings")
+                .max_width(250f32)
+                .show(ctx, |ui| {
+                    uniforms_box::defines_box(&mut self.uniforms_values, &self.defines, ui);
+                    uniforms_box::uniforms_box(
+                        &mut self.uniforms_values,
+                        &ppr,
+                        &mut self.custom3d.point_gizmos,
+                        ui,
+                    );
+                    camera_controls(
+                        ui,
+                        &mut self.custom3d.camera_enabled,
+                        &mut self.uniforms_values.camera,
+                    );
+                    let uniform_names = ppr.sampler_uniform_names();
+                    for index in 0..4 {
+                        ui.group(|ui| {
+                            let default_label = format!("<<sampler {}>>", index + 1);
+                            let label = uniform_names.get(index).unwrap_or(&default_label);
+                            ui.label(label);
+                            if self.textures[index].keyboard {
+                                ui.label("⌨ Keyboard");
+                            }
+                            if let Some(audio) = &self.textures[index].audio {
+                                ui.label(format!(
+                                    "♪ {} ({:.1} s)",
+                                    audio.path.file_name().unwrap_or_default().to_string_lossy(),
+                                    audio.duration()
+                                ));
+                            } else if let Some(path) = &self.textures[index].path
+                                && is_audio_file(path)
+                            {
+                                ui.label(format!(
+                                    "♪ {} (loading…)",
+                                    path.file_name().unwrap_or_default().to_string_lossy()
+                                ));
+                            }
+                            egui::ComboBox::new(format!("tex_select_{}", index), "")
+                                .selected_text("Texture...")
+                                .close_behavior(PopupCloseBehavior::CloseOnClickOutside)
+                                .show_ui(ui, |ui| {
+                                    if ui.selectable_label(false, "Default").clicked() {
+                                        self.load_image_at_index(index, ctx, None);
+                                        ui.memory_mut(|mem| mem.close_popup());
+                                    }
+                                    if ui.selectable_label(false, "Keyboard").clicked() {
+                                        self.textures[index] = WrappedTexture {
+                                            keyboard: true,
+                                            ..Default::default()
+                                        };
+                                        ui.memory_mut(|mem| mem.close_popup());
+                                    }
+                                    if let Some(path) = file_browser(
+                                        ui,
+                                        &self.texture_collection,
+                                        &mut self.texture_search,
+                                    ) {
+                                        self.load_image_at_index(index, ctx, Some(&path));
+                                        ui.memory_mut(|mem| mem.close_popup());
+                                    }
+                                });
+                        });
+                    }
+                });
         }
     }
 
     fn load_image_at_index(&mut self, index: usize, ctx: &Context, path_buf: Option<&PathBuf>) {
+        if let Some(path_buf) = path_buf
+            && is_audio_file(path_buf)
+        {
+            self.load_audio(ctx, self.shader_path.clone(), index, path_buf);
+            return;
+        }
+        self.textures[index].audio = None;
+        self.textures[index].keyboard = false;
         match path_buf {
             Some(path_buf) => match image::open(path_buf) {
                 Ok(img) => match to_color_image(img) {
@@ -326,25 +1032,168 @@ impl VarjostinApp {
                             TextureOptions::LINEAR,
                         );
                         self.textures[index].handle = Some(texture);
+                        self.textures[index].path = Some(path_buf.clone());
                     }
                     Err(e) => {
-                        eprintln!("Error converting image: {:?}", e);
+                        log::error!("Error converting image: {:?}", e);
                     }
                 },
                 Err(e) => {
-                    eprintln!("Error loading image: {:?}", e);
+                    log::error!("Error loading image: {:?}", e);
                 }
             },
             None => {
                 self.textures[index].handle = Some(self.default_texture.clone());
+                self.textures[index].path = None;
             }
         }
     }
 
+    /// Decode an audio file for a sampler slot of `shader_path` on another thread; the slot
+    /// keeps the path meanwhile.
+    fn load_audio(
+        &mut self,
+        ctx: &Context,
+        shader_path: Option<PathBuf>,
+        index: usize,
+        path: &Path,
+    ) {
+        self.textures[index] = WrappedTexture {
+            path: Some(path.to_path_buf()),
+            ..Default::default()
+        };
+        let outbox = self.audio_load_outbox.clone();
+        let ctx = ctx.clone();
+        let path = path.to_path_buf();
+        std::thread::spawn(move || {
+            let result = AudioTrack::load(&path);
+            let _ = outbox.send(AudioLoadResponse {
+                shader_path,
+                index,
+                path,
+                result,
+            });
+            ctx.request_repaint();
+        });
+    }
+
+    /// Put decoded audio into the slots still waiting for it.
+    fn receive_audio(&mut self) {
+        while let Ok(response) = self.audio_load_inbox.try_recv() {
+            let textures = if response.shader_path == self.shader_path {
+                Some(&mut self.textures)
+            } else {
+                response
+                    .shader_path
+                    .as_ref()
+                    .and_then(|path| self.shader_states.get_mut(path))
+                    .map(|(_, textures)| textures)
+            };
+            let Some(slot) = textures.map(|textures| &mut textures[response.index]) else {
+                continue;
+            };
+            // Something else was picked for the slot meanwhile.
+            if slot.path.as_ref() != Some(&response.path)
+                || slot.handle.is_some()
+                || slot.audio.is_some()
+                || slot.keyboard
+            {
+                continue;
+            }
+            match response.result {
+                Ok(track) => slot.audio = Some(Arc::new(track)),
+                Err(e) => log::error!("Error loading audio {:?}: {:?}", response.path, e),
+            }
+        }
+    }
+
+    /// Analyze audio-fed slots at the current virtual time; also updates `iAudioBands`.
+    fn audio_images(&mut self, ppr: &PreparseResult) -> NamedDataImages {
+        let time = self.custom3d.clock.time();
+        let mut bands = None;
+        let images = ppr
+            .sampler_uniform_names()
+            .into_iter()
+            .zip(self.textures.iter())
+            .filter_map(|(name, texture)| {
+                let frame = texture.audio.as_ref()?.analyze(time);
+                bands.get_or_insert(frame.bands);
+                Some((name, frame.image))
+            })
+            .collect();
+        self.custom3d.audio_bands = bands.unwrap_or_default();
+        images
+    }
+
+    fn sound_window(&mut self, ctx: &Context, ppr: Option<&PreparseResult>) {
+        let Some(settings) = self
+            .sound_window
+            .show(ctx, self.shader_path.as_deref(), ppr)
+        else {
+            return;
+        };
+        let fragment_source = match &self.shader_path {
+            Some(path) => std::fs::read_to_string(path),
+            None => Ok(include_str!("test_fragment.glsl").to_owned()),
+        };
+        let response_sender = self.sound_window.outbox.clone();
+        match fragment_source {
+            Ok(source) => {
+                let define_values = &self.uniforms_values.define_values;
+                self.custom3d.request_sound_render(SoundRenderRequest {
+                    fragment_source: inject_defines(
+                        &source,
+                        &parse_defines(&source),
+                        define_values,
+                    ),
+                    default_mode: self.custom3d.prelude_mode,
+                    uniforms_values: self.uniforms_values.clone(),
+                    sample_rate: settings.sample_rate,
+                    duration: settings.duration,
+                    output_path: settings.output_path,
+                    response_sender,
+                });
+            }
+            Err(e) => {
+                response_sender
+                    .send(SoundRenderUpdate::Done(SoundRenderResponse {
+                        duration: Duration::default(),
+                        result: Err(e.into()),
+                    }))
+                    .ok();
+            }
+        }
+    }
+
+    fn keyboard_images(&self, ppr: &PreparseResult) -> NamedDataImages {
+        let slot_names = ppr
+            .sampler_uniform_names()
+            .into_iter()
+            .zip(self.textures.iter())
+            .filter(|(_, texture)| texture.keyboard)
+            .map(|(name, _)| name);
+        let names: Vec<String> = ppr
+            .sourced_sampler_names(UniformSource::Keyboard)
+            .into_iter()
+            .chain(slot_names)
+            .collect();
+        if names.is_empty() {
+            return Vec::new();
+        }
+        let image = self.custom3d.keyboard.image();
+        names
+            .into_iter()
+            .map(|name| (name, image.clone()))
+            .collect()
+    }
+
     fn error_popup(&mut self, ctx: &Context) {
         let last_shader_compile_result = self.last_shader_compile_result.as_ref();
         let err = last_shader_compile_result.and_then(|r| r.error.as_ref());
         let mut show_error = err.is_some();
+        let line = err.and_then(|e| error_line(&e.to_string()));
+        let can_edit = self.shader_path.is_some() && self.editor.is_available();
+        let mut edit_line = None;
         let _error_window = egui::Window::new("Compile Error")
             .fixed_pos([10., 25.])
             .min_size([300., 200.])
@@ -355,29 +1204,211 @@ impl VarjostinApp {
                 if let Some(e) = err {
                     ui.label(RichText::new(e.to_string()).color(egui::Color32::RED));
                 }
+                if can_edit
+                    && let Some(line) = line
+                    && ui
+                        .button(format!("Open in editor at line {}", line))
+                        .clicked()
+                {
+                    edit_line = Some(line);
+                }
             });
+        if let Some(line) = edit_line {
+            self.edit_shader(line);
+        }
+    }
+
+    /// Ask `ctx`'s viewport for a screenshot if one is wanted, and save the shader's part of it
+    /// once it arrives.
+    fn screenshot(&mut self, ctx: &Context) {
+        if self.screenshot_pending {
+            self.screenshot_pending = false;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot(Default::default()));
+        }
+        let image = ctx.input(|i| {
+            i.events.iter().find_map(|event| match event {
+                egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        let Some(image) = image else {
+            return;
+        };
+        let name = self
+            .shader_path
+            .as_ref()
+            .and_then(|path| path.file_stem())
+            .map_or("varjostin".into(), |stem| stem.to_string_lossy());
+        match save_screenshot(
+            &image,
+            self.custom3d.rect,
+            ctx.pixels_per_point(),
+            &self.screenshot_dir,
+            &name,
+        ) {
+            Ok(path) => log::info!("Saved screenshot {:?}", path),
+            Err(e) => log::error!("Error saving screenshot: {:?}", e),
+        }
+    }
+
+    fn set_presentation(&mut self, ctx: &Context, presentation: bool) {
+        self.presentation = presentation;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(presentation));
+    }
+
+    /// Show the shader in `ctx`'s central panel; the clock ticks here, so call this once per frame.
+    fn shader_view(&mut self, ctx: &Context, ppr: Option<&PreparseResult>, bare: bool) {
+        self.screenshot(ctx);
+        self.custom3d.update_keyboard(ctx);
+        let data_images = match ppr {
+            Some(ppr) => {
+                let mut images = bake_ramp_images(ppr, &self.uniforms_values);
+                images.extend(self.audio_images(ppr));
+                images.extend(self.keyboard_images(ppr));
+                images
+            }
+            None => Vec::new(),
+        };
+        egui::CentralPanel::default()
+            .frame(if bare {
+                egui::Frame::NONE
+            } else {
+                egui::Frame::central_panel(&ctx.style())
+            })
+            .show(ctx, |ui| {
+                self.custom3d.update(
+                    ui,
+                    self.frame_history.fps(),
+                    &mut self.uniforms_values,
+                    &mut self.warp,
+                    self.textures.clone(),
+                    data_images,
+                );
+            });
+        // The audio of the first audio-fed sampler, as for `iAudioBands`.
+        let sampler_count = ppr.map_or(0, |ppr| ppr.sampler_uniform_names().len());
+        let track = self
+            .textures
+            .iter()
+            .take(sampler_count)
+            .find_map(|texture| texture.audio.as_ref());
+        self.audio_player.sync(&self.custom3d.clock, track);
+    }
+
+    fn output_window(&mut self, ctx: &Context, ppr: Option<&PreparseResult>) {
+        let viewport_id = egui::ViewportId::from_hash_of("output");
+        let builder = egui::ViewportBuilder::default()
+            .with_title("Varjostin output")
+            .with_inner_size([960.0, 540.0]);
+        ctx.show_viewport_immediate(viewport_id, builder, |ctx, _class| {
+            if self.shortcuts.pressed(ctx, Action::Presentation) {
+                self.output_fullscreen = !self.output_fullscreen;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(self.output_fullscreen));
+            }
+            if self.output_fullscreen && ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+                self.output_fullscreen = false;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(false));
+            }
+            self.shader_view(ctx, ppr, true);
+            if ctx.input(|i| i.viewport().close_requested()) {
+                self.output_window = false;
+                self.output_fullscreen = false;
+            }
+        });
+    }
+
+    fn handle_shortcuts(&mut self, ctx: &Context) {
+        if self.shortcuts.pressed(ctx, Action::Presentation) {
+            self.set_presentation(ctx, !self.presentation);
+        }
+        if self.shortcuts.pressed(ctx, Action::TogglePanels) {
+            self.panels_hidden = !self.panels_hidden;
+        }
+        if self.shortcuts.pressed(ctx, Action::WarpHandles) {
+            self.warp.editing = !self.warp.editing;
+        }
+        if self.shortcuts.pressed(ctx, Action::ResetTime) {
+            self.custom3d.clock.reset();
+        }
+        if self.shortcuts.pressed(ctx, Action::Pause) {
+            self.custom3d.clock.playing = !self.custom3d.clock.playing;
+        }
+        if self.shortcuts.pressed(ctx, Action::Screenshot) {
+            self.screenshot_pending = true;
+        }
     }
 
     fn do_the_thing(&mut self, ctx: &Context, frame: &mut Frame) {
-        self.check_shader_state();
+        let _scope = shader_scope(self.shader_path.as_deref());
+        self.check_shader_state(ctx);
+        self.receive_audio();
+        self.autopilot();
         self.frame_history
             .on_new_frame(ctx.input(|i| i.time), frame.info().cpu_usage);
+        self.handle_shortcuts(ctx);
         let esc_pressed = ctx.input(|i| i.key_pressed(egui::Key::Escape));
-        if esc_pressed {
+        if esc_pressed && self.presentation {
+            self.set_presentation(ctx, false);
+        } else if esc_pressed && !self.custom3d.has_keyboard_focus {
             ctx.send_viewport_cmd(egui::ViewportCommand::Close);
         }
-        self.top_bar(ctx);
-        self.bottom_bar(ctx);
-        self.uniforms_bar(ctx);
-        egui::CentralPanel::default().show(ctx, |ui| {
-            self.custom3d.update(
-                ctx,
-                ui,
-                self.frame_history.fps(),
-                &self.uniforms_values,
-                self.textures.clone(),
-            );
-        });
+        let bare = self.presentation || self.panels_hidden;
+        if !bare {
+            self.window = ctx.input(|i| {
+                let viewport = i.viewport();
+                if viewport.fullscreen == Some(true) {
+                    return self.window;
+                }
+                let size = viewport.inner_rect?.size();
+                Some(WindowSession {
+                    position: viewport.outer_rect.map(|rect| [rect.min.x, rect.min.y]),
+                    size: [size.x, size.y],
+                })
+            });
+            self.top_bar(ctx);
+            self.time_bar(ctx);
+            self.bottom_bar(ctx);
+            self.uniforms_bar(ctx);
+        }
+        let ppr = match &self.last_shader_compile_result {
+            Some(ShaderCompileResponse {
+                preparse_result: Some(Ok(ppr)),
+                ..
+            }) => Some(ppr.clone()),
+            _ => None,
+        };
+        self.custom3d.viewport_targets = ppr.as_ref().map(viewport_targets).unwrap_or_default();
+        self.custom3d.point_gizmos.targets = ppr.as_ref().map(point_targets).unwrap_or_default();
+        if self.output_window {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.centered_and_justified(|ui| {
+                    ui.weak("Output is shown in a separate window");
+                });
+            });
+            self.output_window(ctx, ppr.as_ref());
+        } else {
+            self.shader_view(ctx, ppr.as_ref(), bare);
+        }
+        self.sound_window(ctx, ppr.as_ref());
+        self.warp.show(ctx);
+        let entries = self.playlist.entries(&self.shader_collection.files);
+        self.playlist.show(ctx, &entries);
+        let (thumbnail_request, clicked) = self.gallery.show(ctx, &self.shader_collection.files);
+        if let Some(request) = thumbnail_request {
+            self.custom3d.request_thumbnail_render(request);
+        }
+        if let Some(path) = clicked {
+            self.open_shader(path);
+        }
+        if let Some(path) = self.new_shader.show(ctx, &self.options.shaders_dir) {
+            self.update_collections();
+            self.open_shader(path);
+            self.edit_shader(1);
+        }
+        if !ctx.input(|i| i.pointer.any_down()) {
+            self.warp.save_if_changed();
+        }
+        self.log_console.show(ctx, self.shader_path.as_deref());
         self.error_popup(ctx);
         if self.continuous {
             ctx.request_repaint();
@@ -390,6 +1421,7 @@ impl eframe::App for VarjostinApp {
         self.do_the_thing(ctx, frame);
     }
     fn on_exit(&mut self, glow_ctx: Option<&glow::Context>) {
+        self.session().save();
         self.custom3d.exit(glow_ctx);
     }
 }
@@ -0,0 +1,316 @@
+use crate::textures::DataImage;
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use symphonia::core::codecs::audio::AudioDecoderOptions;
+use symphonia::core::errors::Error;
+use symphonia::core::formats::probe::Hint;
+use symphonia::core::formats::{FormatOptions, TrackType};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+
+pub const AUDIO_SUFFIXES: [&str; 3] = [".wav", ".flac", ".ogg"];
+
+/// Shadertoy-compatible geometry: 512 spectrum bins from a 2048-point FFT, 512 waveform samples.
+const FFT_SIZE: usize = 2048;
+const TEXTURE_WIDTH: usize = 512;
+const MIN_DECIBELS: f32 = -100.0;
+const MAX_DECIBELS: f32 = -30.0;
+
+/// Band smoothing looks at this many earlier analysis windows, on a grid `SMOOTHING_HOP` apart,
+/// so the result depends only on the time and not on the frame rate.
+const SMOOTHING_WINDOWS: usize = 4;
+const SMOOTHING_HOP: f64 = 1.0 / 60.0;
+const SMOOTHING_DECAY: f32 = 0.5;
+
+const BAND_EDGES_HZ: [f32; 4] = [20.0, 250.0, 4000.0, 20000.0];
+
+static FFT: LazyLock<Fft> = LazyLock::new(|| Fft::new(FFT_SIZE));
+
+pub struct AudioTrack {
+    pub path: PathBuf,
+    pub sample_rate: u32,
+    pub channels: usize,
+    /// Mono mixdown of the file, for analysis.
+    samples: Vec<f32>,
+    /// The file's channels interleaved, for playback.
+    frames: Vec<f32>,
+    /// Band levels of recent smoothing windows by grid index, so each is analyzed only once.
+    band_history: Mutex<VecDeque<(i64, [f32; 3])>>,
+}
+
+/// An audio file decoded off the UI thread for a shader's sampler slot.
+pub struct AudioLoadResponse {
+    pub shader_path: Option<PathBuf>,
+    pub index: usize,
+    pub path: PathBuf,
+    pub result: eyre::Result<AudioTrack>,
+}
+
+pub fn is_audio_file(path: &Path) -> bool {
+    AUDIO_SUFFIXES
+        .iter()
+        .any(|suffix| path.to_string_lossy().ends_with(suffix))
+}
+
+pub struct AudioFrame {
+    pub image: DataImage,
+    pub bands: [f32; 3],
+}
+
+impl AudioTrack {
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension() {
+            hint.with_extension(&ext.to_string_lossy());
+        }
+        let mut format = symphonia::default::get_probe().probe(
+            &hint,
+            mss,
+            FormatOptions::default(),
+            MetadataOptions::default(),
+        )?;
+        let track = format
+            .default_track(TrackType::Audio)
+            .ok_or_else(|| eyre::eyre!("No audio track in {:?}", path))?;
+        let codec_params = track
+            .codec_params
+            .as_ref()
+            .and_then(|p| p.audio())
+            .ok_or_else(|| eyre::eyre!("No audio codec parameters in {:?}", path))?;
+        let mut decoder = symphonia::default::get_codecs()
+            .make_audio_decoder(codec_params, &AudioDecoderOptions::default())?;
+        let track_id = track.id;
+
+        let mut sample_rate = 0;
+        let mut channels = 1;
+        let mut samples = Vec::new();
+        let mut frames = Vec::new();
+        let mut interleaved: Vec<f32> = Vec::new();
+        while let Some(packet) = format.next_packet()? {
+            if packet.track_id != track_id {
+                continue;
+            }
+            match decoder.decode(&packet) {
+                Ok(buf) => {
+                    sample_rate = buf.spec().rate();
+                    channels = buf.spec().channels().count().max(1);
+                    interleaved.resize(buf.samples_interleaved(), 0.0);
+                    buf.copy_to_slice_interleaved(&mut interleaved);
+                    samples.extend(
+                        interleaved
+                            .chunks_exact(channels)
+                            .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+                    );
+                    frames.extend_from_slice(&interleaved);
+                }
+                Err(Error::DecodeError(_)) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        if sample_rate == 0 {
+            return Err(eyre::eyre!("No audio decoded from {:?}", path));
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            sample_rate,
+            channels,
+            samples,
+            frames,
+            band_history: Mutex::default(),
+        })
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.samples.len() as f64 / self.sample_rate as f64
+    }
+
+    /// Analyze the audio right before `time`; the result depends on `time` only.
+    pub fn analyze(&self, time: f64) -> AudioFrame {
+        let spectrum = self.spectrum(time);
+        let mut image = DataImage::new(TEXTURE_WIDTH, 2);
+        for (x, value) in spectrum.iter().take(TEXTURE_WIDTH).enumerate() {
+            let v = (value * 255.0).round() as u8;
+            image.set_pixel(x, 0, [v, v, v, 255]);
+        }
+        let end = self.sample_index(time);
+        for x in 0..TEXTURE_WIDTH {
+            let s = self.sample_at(end - TEXTURE_WIDTH as i64 + x as i64);
+            let v = (128.0 + s * 127.0).clamp(0.0, 255.0).round() as u8;
+            image.set_pixel(x, 1, [v, v, v, 255]);
+        }
+
+        let mut bands = [0.0; 3];
+        let mut weight_sum = 0.0;
+        let mut weight = 1.0;
+        let grid = (time / SMOOTHING_HOP).floor() as i64;
+        for window in 0..SMOOTHING_WINDOWS {
+            let levels = if window == 0 {
+                self.band_levels(&spectrum)
+            } else {
+                self.grid_band_levels(grid + 1 - window as i64)
+            };
+            for (band, value) in levels.into_iter().enumerate() {
+                bands[band] += value * weight;
+            }
+            weight_sum += weight;
+            weight *= SMOOTHING_DECAY;
+        }
+        AudioFrame {
+            image,
+            bands: bands.map(|b| b / weight_sum),
+        }
+    }
+
+    /// Band levels of the smoothing window ending at grid point `index`.
+    fn grid_band_levels(&self, index: i64) -> [f32; 3] {
+        let cached = self.band_history.lock().ok().and_then(|history| {
+            history
+                .iter()
+                .find(|(i, _)| *i == index)
+                .map(|(_, levels)| *levels)
+        });
+        if let Some(levels) = cached {
+            return levels;
+        }
+        let levels = self.band_levels(&self.spectrum(index as f64 * SMOOTHING_HOP));
+        if let Ok(mut history) = self.band_history.lock() {
+            history.push_back((index, levels));
+            // Enough for the windows of a frame and of the one after it.
+            while history.len() > 2 * SMOOTHING_WINDOWS {
+                history.pop_front();
+            }
+        }
+        levels
+    }
+
+    /// The sample of `channel` at `time`, interpolated between frames; silence outside the file.
+    pub fn play_sample(&self, time: f64, channel: usize) -> f32 {
+        let position = time * self.sample_rate as f64;
+        let index = position.floor();
+        let frame = |index: f64| {
+            if index < 0.0 {
+                return 0.0;
+            }
+            self.frames
+                .get(index as usize * self.channels + channel % self.channels)
+                .copied()
+                .unwrap_or(0.0)
+        };
+        let t = (position - index) as f32;
+        frame(index) * (1.0 - t) + frame(index + 1.0) * t
+    }
+
+    fn sample_index(&self, time: f64) -> i64 {
+        (time * self.sample_rate as f64).floor() as i64
+    }
+
+    fn sample_at(&self, index: i64) -> f32 {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.samples.get(index))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Normalized (0..1) decibel magnitudes of the `FFT_SIZE / 2` bins for the window ending at `time`.
+    fn spectrum(&self, time: f64) -> Vec<f32> {
+        let end = self.sample_index(time);
+        let mut re: Vec<f32> = FFT
+            .window
+            .iter()
+            .enumerate()
+            .map(|(i, w)| self.sample_at(end - FFT_SIZE as i64 + i as i64) * w)
+            .collect();
+        let mut im = vec![0.0; FFT_SIZE];
+        FFT.transform(&mut re, &mut im);
+        (0..FFT_SIZE / 2)
+            .map(|bin| {
+                let magnitude = (re[bin] * re[bin] + im[bin] * im[bin]).sqrt() / FFT_SIZE as f32;
+                let db = 20.0 * magnitude.max(1e-10).log10();
+                ((db - MIN_DECIBELS) / (MAX_DECIBELS - MIN_DECIBELS)).clamp(0.0, 1.0)
+            })
+            .collect()
+    }
+
+    fn band_levels(&self, spectrum: &[f32]) -> [f32; 3] {
+        let bin_hz = self.sample_rate as f32 / FFT_SIZE as f32;
+        std::array::from_fn(|band| {
+            let lo = ((BAND_EDGES_HZ[band] / bin_hz) as usize).min(spectrum.len());
+            let hi = ((BAND_EDGES_HZ[band + 1] / bin_hz) as usize).clamp(lo, spectrum.len());
+            if hi > lo {
+                spectrum[lo..hi].iter().sum::<f32>() / (hi - lo) as f32
+            } else {
+                0.0
+            }
+        })
+    }
+}
+
+/// Window and twiddle factors for FFTs of one size.
+struct Fft {
+    /// Blackman window.
+    window: Vec<f32>,
+    /// `(cos, sin)` of `-2πk / size` for `k` in `0..size / 2`.
+    twiddles: Vec<(f32, f32)>,
+}
+
+impl Fft {
+    /// `size` must be a power of two.
+    fn new(size: usize) -> Self {
+        Self {
+            window: (0..size)
+                .map(|i| {
+                    let x = 2.0 * PI * i as f32 / size as f32;
+                    0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos()
+                })
+                .collect(),
+            twiddles: (0..size / 2)
+                .map(|k| {
+                    let (sin, cos) = (-2.0 * PI * k as f32 / size as f32).sin_cos();
+                    (cos, sin)
+                })
+                .collect(),
+        }
+    }
+
+    /// In-place iterative radix-2 FFT of `size` points.
+    fn transform(&self, re: &mut [f32], im: &mut [f32]) {
+        let n = re.len();
+        debug_assert_eq!(n, self.window.len());
+        let mut j = 0;
+        for i in 1..n {
+            let mut bit = n >> 1;
+            while j & bit != 0 {
+                j ^= bit;
+                bit >>= 1;
+            }
+            j |= bit;
+            if i < j {
+                re.swap(i, j);
+                im.swap(i, j);
+            }
+        }
+        let mut len = 2;
+        while len <= n {
+            let stride = n / len;
+            for start in (0..n).step_by(len) {
+                for k in 0..len / 2 {
+                    let (cos, sin) = self.twiddles[k * stride];
+                    let a = start + k;
+                    let b = a + len / 2;
+                    let tr = re[b] * cos - im[b] * sin;
+                    let ti = re[b] * sin + im[b] * cos;
+                    re[b] = re[a] - tr;
+                    im[b] = im[a] - ti;
+                    re[a] += tr;
+                    im[a] += ti;
+                }
+            }
+            len <<= 1;
+        }
+    }
+}
@@ -0,0 +1,94 @@
+/// Seconds of virtual time after which a tap starts a new tempo.
+const TAP_TIMEOUT: f64 = 2.0;
+const MAX_TAPS: usize = 8;
+
+/// Musical time derived from the virtual clock: beat 0 is a downbeat at `anchor_time`.
+pub struct BeatClock {
+    bpm: f64,
+    pub beats_per_bar: u32,
+    anchor_time: f64,
+    /// Virtual times of recent taps, so the tempo follows the clock's speed.
+    taps: Vec<f64>,
+}
+
+impl Default for BeatClock {
+    fn default() -> Self {
+        Self {
+            bpm: 120.0,
+            beats_per_bar: 4,
+            anchor_time: 0.0,
+            taps: Vec::new(),
+        }
+    }
+}
+
+impl BeatClock {
+    pub fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    /// Virtual time of the downbeat the beat count starts from.
+    pub fn anchor_time(&self) -> f64 {
+        self.anchor_time
+    }
+
+    /// Continuous beat count at the given virtual time.
+    pub fn beat(&self, time: f64) -> f64 {
+        (time - self.anchor_time) * self.bpm / 60.0
+    }
+
+    /// Continuous bar count at the given virtual time.
+    pub fn bar(&self, time: f64) -> f64 {
+        self.beat(time) / self.beats_per_bar.max(1) as f64
+    }
+
+    /// Change the tempo without making the beat phase jump at `time`.
+    pub fn set_bpm(&mut self, bpm: f64, time: f64) {
+        let bpm = bpm.clamp(1.0, 999.0);
+        let beat = self.beat(time);
+        self.bpm = bpm;
+        self.anchor_time = time - beat * 60.0 / bpm;
+    }
+
+    /// Set the tempo and where the grid starts, as saved from `bpm()` and `anchor_time()`.
+    pub fn set_grid(&mut self, bpm: f64, anchor_time: f64) {
+        self.bpm = bpm.clamp(1.0, 999.0);
+        self.anchor_time = anchor_time;
+    }
+
+    /// Shift the beat grid by `seconds`; positive values make beats arrive later.
+    pub fn nudge(&mut self, seconds: f64) {
+        self.anchor_time += seconds;
+    }
+
+    /// Make `time` the start of a bar.
+    pub fn resync(&mut self, time: f64) {
+        self.anchor_time = time;
+    }
+
+    /// Register a tap; tempo is averaged over recent taps, and the tap is snapped onto the beat grid.
+    pub fn tap(&mut self, time: f64) {
+        // Also start over after scrubbing back.
+        if self
+            .taps
+            .last()
+            .is_some_and(|last| time - last > TAP_TIMEOUT || time < *last)
+        {
+            self.taps.clear();
+        }
+        self.taps.push(time);
+        if self.taps.len() > MAX_TAPS {
+            self.taps.remove(0);
+        }
+        if let (Some(first), Some(last)) = (self.taps.first(), self.taps.last())
+            && self.taps.len() >= 2
+        {
+            let interval = (last - first) / (self.taps.len() - 1) as f64;
+            if interval > 0.0 {
+                self.set_bpm(60.0 / interval, time);
+            }
+        }
+        let beat = self.beat(time);
+        self.nudge((beat - beat.round()) * 60.0 / self.bpm);
+    }
+}
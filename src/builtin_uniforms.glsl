@@ -0,0 +1,19 @@
+precision highp float;
+
+// Shared by the Shadertoy-style image and sound preludes.
+uniform vec4 iViewport;
+uniform vec3 iResolution;
+uniform float iTime;
+uniform float iTimeDelta;
+uniform float iBPM;
+uniform float iBeat;
+uniform float iBar;
+uniform vec3 iAudioBands;
+uniform vec3 iCameraPos;
+uniform vec3 iCameraTarget;
+uniform mat3 iCameraMatrix;
+uniform float iFov;
+uniform int iFrame;
+uniform vec4 iMouse;
+uniform vec2 iMouseWheel;
+uniform int iMouseButtons;
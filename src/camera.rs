@@ -0,0 +1,179 @@
+use egui::{Key, Response, Ui};
+use serde::{Deserialize, Serialize};
+
+const ROTATE_SPEED: f32 = 0.01;
+const ZOOM_SPEED: f32 = 0.002;
+const PITCH_LIMIT: f32 = 1.55;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum CameraMode {
+    /// Drag to rotate around the target, scroll to zoom.
+    #[default]
+    Orbit,
+    /// Drag to look around, WASD/QE to move (shift for speed).
+    Fly,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraState {
+    pub mode: CameraMode,
+    pub target: [f32; 3],
+    pub position: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+    /// Vertical field of view, in degrees.
+    pub fov: f32,
+    pub fly_speed: f32,
+}
+
+impl Default for CameraState {
+    fn default() -> Self {
+        let mut state = Self {
+            mode: CameraMode::Orbit,
+            target: [0.0; 3],
+            position: [0.0; 3],
+            yaw: 0.0,
+            pitch: 0.3,
+            distance: 4.0,
+            fov: 45.0,
+            fly_speed: 2.0,
+        };
+        state.position = state.orbit_position();
+        state
+    }
+}
+
+impl CameraState {
+    fn direction(&self) -> [f32; 3] {
+        let (sy, cy) = self.yaw.sin_cos();
+        let (sp, cp) = self.pitch.sin_cos();
+        [cp * sy, sp, cp * cy]
+    }
+
+    fn orbit_position(&self) -> [f32; 3] {
+        let d = self.direction();
+        std::array::from_fn(|i| self.target[i] + d[i] * self.distance)
+    }
+
+    /// Apply pointer and keyboard input from the viewport for this frame.
+    pub fn handle_input(
+        &mut self,
+        ui: &Ui,
+        response: &Response,
+        has_keyboard_focus: bool,
+        dt: f32,
+    ) {
+        if response.dragged_by(egui::PointerButton::Primary) {
+            let delta = response.drag_delta();
+            let sign = match self.mode {
+                CameraMode::Orbit => -1.0,
+                CameraMode::Fly => 1.0,
+            };
+            self.yaw += sign * delta.x * ROTATE_SPEED;
+            self.pitch = (self.pitch + delta.y * ROTATE_SPEED).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+        }
+        match self.mode {
+            CameraMode::Orbit => {
+                if response.hovered() {
+                    let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+                    self.distance = (self.distance * (-scroll * ZOOM_SPEED).exp()).max(0.01);
+                }
+                self.position = self.orbit_position();
+            }
+            CameraMode::Fly => {
+                if has_keyboard_focus {
+                    let forward = self.direction().map(|c| -c);
+                    let right = normalize(cross(forward, [0.0, 1.0, 0.0]));
+                    let (movement, fast) = ui.input(|i| {
+                        let axis = |pos: Key, neg: Key| {
+                            i.key_down(pos) as i32 as f32 - i.key_down(neg) as i32 as f32
+                        };
+                        (
+                            [
+                                axis(Key::D, Key::A),
+                                axis(Key::E, Key::Q),
+                                axis(Key::W, Key::S),
+                            ],
+                            i.modifiers.shift,
+                        )
+                    });
+                    let speed = self.fly_speed * dt * if fast { 4.0 } else { 1.0 };
+                    for i in 0..3 {
+                        self.position[i] += (right[i] * movement[0]
+                            + [0.0, 1.0, 0.0][i] * movement[1]
+                            + forward[i] * movement[2])
+                            * speed;
+                    }
+                }
+                let forward = self.direction().map(|c| -c);
+                self.target = std::array::from_fn(|i| self.position[i] + forward[i]);
+            }
+        }
+    }
+
+    /// Column-major look-at basis: right, up, forward.
+    pub fn matrix(&self) -> [f32; 9] {
+        let forward = normalize(std::array::from_fn(|i| self.target[i] - self.position[i]));
+        let right = normalize(cross(forward, [0.0, 1.0, 0.0]));
+        let up = cross(right, forward);
+        [
+            right[0], right[1], right[2], up[0], up[1], up[2], forward[0], forward[1], forward[2],
+        ]
+    }
+
+    /// Switch modes while keeping the camera where it is.
+    fn set_mode(&mut self, mode: CameraMode) {
+        if mode == CameraMode::Orbit && self.mode == CameraMode::Fly {
+            let d = self.direction();
+            self.target = std::array::from_fn(|i| self.position[i] - d[i] * self.distance);
+        }
+        self.mode = mode;
+    }
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if len > 0.0 { v.map(|c| c / len) } else { v }
+}
+
+pub fn camera_controls(ui: &mut Ui, enabled: &mut bool, camera: &mut Option<CameraState>) {
+    ui.group(|ui| {
+        ui.horizontal(|ui| {
+            ui.checkbox(enabled, "Camera").on_hover_text(
+                "Control iCameraPos/iCameraTarget/iCameraMatrix/iFov from the viewport; left \
+                 drags and scrolling then leave iMouse and iMouseWheel alone",
+            );
+            if ui.button("reset").on_hover_text("Reset camera").clicked() {
+                *camera = None;
+            }
+        });
+        if !*enabled {
+            return;
+        }
+        let state = camera.get_or_insert_with(CameraState::default);
+        let mut mode = state.mode;
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut mode, CameraMode::Orbit, "Orbit");
+            ui.radio_value(&mut mode, CameraMode::Fly, "Fly");
+        });
+        state.set_mode(mode);
+        ui.add(egui::Slider::new(&mut state.fov, 10.0..=120.0).text("fov"));
+        if state.mode == CameraMode::Fly {
+            ui.add(egui::Slider::new(&mut state.fly_speed, 0.1..=20.0).text("speed"));
+        }
+        ui.label(format!(
+            "pos: {:.2} {:.2} {:.2}",
+            state.position[0], state.position[1], state.position[2]
+        ));
+    });
+}
@@ -0,0 +1,110 @@
+use std::time::Instant;
+
+/// The virtual clock driving `iTime`, `iTimeDelta` and `iFrame`.
+pub struct Clock {
+    pub playing: bool,
+    pub speed: f32,
+    pub looping: bool,
+    pub loop_range: [f64; 2],
+    /// When set, every frame advances time by exactly `1 / fixed_fps` seconds.
+    pub fixed_timestep: bool,
+    pub fixed_fps: f32,
+    time: f64,
+    delta: f32,
+    /// Delta of a step taken while paused, reported by the next tick.
+    step_delta: Option<f32>,
+    frame: u64,
+    last_tick: Instant,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            playing: true,
+            speed: 1.0,
+            looping: false,
+            loop_range: [0.0, 10.0],
+            fixed_timestep: false,
+            fixed_fps: 60.0,
+            time: 0.0,
+            delta: 0.0,
+            step_delta: None,
+            frame: 0,
+            last_tick: Instant::now(),
+        }
+    }
+}
+
+impl Clock {
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Duration of a single step, as used by fixed-timestep mode and frame stepping.
+    pub fn step_duration(&self) -> f64 {
+        1.0 / self.fixed_fps.max(1.0) as f64
+    }
+
+    /// Advance the clock; called once per rendered frame.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        let wall_delta = now.duration_since(self.last_tick).as_secs_f64();
+        self.last_tick = now;
+        let step_delta = self.step_delta.take();
+        if !self.playing {
+            self.delta = step_delta.unwrap_or(0.0);
+            return;
+        }
+        let delta = if self.fixed_timestep {
+            self.step_duration()
+        } else {
+            wall_delta * self.speed as f64
+        };
+        self.advance(delta);
+        self.frame += 1;
+    }
+
+    /// Pause and move a whole number of steps forwards or backwards.
+    pub fn step(&mut self, steps: i64) {
+        self.playing = false;
+        self.advance(self.step_duration() * steps as f64);
+        self.step_delta = Some(self.delta);
+        self.frame = self.frame.saturating_add_signed(steps);
+    }
+
+    pub fn seek(&mut self, time: f64) {
+        self.delta = 0.0;
+        self.step_delta = None;
+        self.time = time.max(0.0);
+    }
+
+    pub fn reset(&mut self) {
+        self.seek(if self.looping {
+            self.loop_range[0]
+        } else {
+            0.0
+        });
+        self.frame = 0;
+    }
+
+    fn advance(&mut self, delta: f64) {
+        let mut time = (self.time + delta).max(0.0);
+        if self.looping {
+            let [a, b] = self.loop_range;
+            let length = b - a;
+            if length > 0.0 && (time >= b || time < a) {
+                time = a + (time - a).rem_euclid(length);
+            }
+        }
+        self.delta = delta as f32;
+        self.time = time;
+    }
+}
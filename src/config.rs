@@ -0,0 +1,124 @@
+use crate::prelude::PreludeMode;
+use crate::session::config_dir;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Where sound renders and screenshots go, and how sound is rendered by default.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RecordingConfig {
+    pub sample_rate: Option<u32>,
+    /// Sound render length, in seconds.
+    pub duration: Option<f32>,
+    /// Sound renders are written next to the shader unless this is set.
+    pub sound_dir: Option<PathBuf>,
+    pub screenshot_dir: Option<PathBuf>,
+}
+
+/// Settings from `varjostin.json5` (or `.toml`), every one of them optional.
+///
+/// The user-global file in the config directory is read first and a project-local one in the
+/// working directory overrides it; command line flags override both.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub images_dirs: Option<Vec<PathBuf>>,
+    pub shaders_dirs: Option<Vec<PathBuf>>,
+    /// Main window size, in points, when the session has none to restore.
+    pub window_size: Option<[f32; 2]>,
+    /// Image shown in the first sampler slot instead of the built-in one.
+    pub default_texture: Option<PathBuf>,
+    /// Fraction of the viewport resolution shaders render at.
+    pub render_scale: Option<f32>,
+    pub recording: RecordingConfig,
+    /// Shortcuts by action name, e.g. `{pause: "Ctrl+Space"}`.
+    pub keys: HashMap<String, String>,
+    /// Command shaders are opened with, e.g. `"code -g {file}:{line}"`; `$VISUAL` or `$EDITOR`
+    /// if unset.
+    pub editor: Option<String>,
+    /// Mode for shaders with a `main` whose mode can't be told from the source, e.g.
+    /// `"glslsandbox"`; raw if unset.
+    pub prelude_mode: Option<PreludeMode>,
+}
+
+impl Config {
+    pub fn load() -> Self {
+        let user = config_dir().and_then(|dir| load_file(&dir));
+        let project = load_file(Path::new("."));
+        [user, project]
+            .into_iter()
+            .flatten()
+            .fold(Self::default(), Self::merge)
+    }
+
+    /// `other`'s settings where it has them, else ours.
+    fn merge(self, other: Self) -> Self {
+        let mut keys = self.keys;
+        keys.extend(other.keys);
+        Self {
+            images_dirs: other.images_dirs.or(self.images_dirs),
+            shaders_dirs: other.shaders_dirs.or(self.shaders_dirs),
+            window_size: other.window_size.or(self.window_size),
+            default_texture: other.default_texture.or(self.default_texture),
+            render_scale: other.render_scale.or(self.render_scale),
+            recording: RecordingConfig {
+                sample_rate: other.recording.sample_rate.or(self.recording.sample_rate),
+                duration: other.recording.duration.or(self.recording.duration),
+                sound_dir: other.recording.sound_dir.or(self.recording.sound_dir),
+                screenshot_dir: other
+                    .recording
+                    .screenshot_dir
+                    .or(self.recording.screenshot_dir),
+            },
+            keys,
+            editor: other.editor.or(self.editor),
+            prelude_mode: other.prelude_mode.or(self.prelude_mode),
+        }
+    }
+
+    /// Make relative paths relative to `base`, the directory the file was in.
+    fn resolve_paths(mut self, base: &Path) -> Self {
+        let resolve = |path: &mut PathBuf| {
+            if path.is_relative() {
+                *path = base.join(&*path);
+            }
+        };
+        self.images_dirs.iter_mut().flatten().for_each(resolve);
+        self.shaders_dirs.iter_mut().flatten().for_each(resolve);
+        self.default_texture.iter_mut().for_each(resolve);
+        self.recording.sound_dir.iter_mut().for_each(resolve);
+        self.recording.screenshot_dir.iter_mut().for_each(resolve);
+        self
+    }
+}
+
+/// `varjostin.json5` or `varjostin.toml` in `dir`, if either exists.
+fn load_file(dir: &Path) -> Option<Config> {
+    let json5_path = dir.join("varjostin.json5");
+    let toml_path = dir.join("varjostin.toml");
+    let (path, result) = match std::fs::read_to_string(&json5_path) {
+        Ok(text) => (
+            json5_path,
+            serde_json5::from_str::<Config>(&text).map_err(|e| eyre::eyre!(e)),
+        ),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            match std::fs::read_to_string(&toml_path) {
+                Ok(text) => (
+                    toml_path,
+                    toml::from_str::<Config>(&text).map_err(|e| eyre::eyre!(e)),
+                ),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+                Err(e) => (toml_path, Err(e.into())),
+            }
+        }
+        Err(e) => (json5_path, Err(e.into())),
+    };
+    match result {
+        Ok(config) => Some(config.resolve_paths(dir)),
+        Err(e) => {
+            log::error!("Error loading config {:?}: {}", path, e);
+            None
+        }
+    }
+}
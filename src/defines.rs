@@ -0,0 +1,279 @@
+use egui::ahash::HashMap;
+use serde::Deserialize;
+
+/// A `#define` that can be switched from the UI.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DefineInfo {
+    pub name: String,
+    /// Choices from `#pragma @define NAME {values: [...]}`; empty for an on/off define.
+    pub values: Vec<String>,
+    /// The value in the file, `None` if it's not defined there.
+    pub default: Option<String>,
+    /// Index of the line that defines it, or would if uncommented.
+    line: Option<usize>,
+}
+
+/// What a define is set to: `None` undefines it, `Some("")` defines it without a value.
+pub type DefineValues = HashMap<String, Option<String>>;
+
+#[derive(Deserialize)]
+struct DefinePragmaInfo {
+    values: Vec<DefinePragmaValue>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DefinePragmaValue {
+    Number(f64),
+    Text(String),
+}
+
+impl DefinePragmaValue {
+    fn into_string(self) -> String {
+        match self {
+            DefinePragmaValue::Number(number) => number.to_string(),
+            DefinePragmaValue::Text(text) => text,
+        }
+    }
+}
+
+/// `#define NAME rest` in `line`, commented out or not, as `(name, rest, commented)`.
+fn define_line(line: &str) -> Option<(&str, &str, bool)> {
+    let line = line.trim();
+    let (line, commented) = match line.strip_prefix("//") {
+        Some(rest) => (rest.trim_start(), true),
+        None => (line, false),
+    };
+    let rest = line
+        .strip_prefix('#')?
+        .trim_start()
+        .strip_prefix("define")?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let rest = rest.trim();
+    let name_end = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    let (name, value) = rest.split_at(name_end);
+    let value = value.split_once("//").map_or(value, |(value, _)| value);
+    // Function-like macros aren't switches.
+    if name.is_empty() || value.starts_with('(') {
+        return None;
+    }
+    Some((name, value.trim(), commented))
+}
+
+/// Defines with a `#pragma @define` and value-less ones, which become on/off switches; a bare
+/// `// #define NAME` counts as one that's off.
+pub fn parse_defines(source: &str) -> Vec<DefineInfo> {
+    let mut defines: Vec<DefineInfo> = Vec::new();
+    let mut pragmas: Vec<(String, Vec<String>)> = Vec::new();
+    for line in source.lines() {
+        let Some(rest) = line.trim().strip_prefix("#pragma") else {
+            continue;
+        };
+        let Some(rest) = rest.trim().strip_prefix("@define") else {
+            continue;
+        };
+        let rest = rest.trim();
+        let (name, info) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let values = match info.trim() {
+            "" => Vec::new(),
+            info => match serde_json5::from_str::<DefinePragmaInfo>(info) {
+                Ok(info) => info
+                    .values
+                    .into_iter()
+                    .map(DefinePragmaValue::into_string)
+                    .collect(),
+                Err(e) => {
+                    log::warn!("Error parsing define pragma {:?}: {}", line.trim(), e);
+                    continue;
+                }
+            },
+        };
+        pragmas.push((name.to_owned(), values));
+    }
+    for (index, line) in source.lines().enumerate() {
+        let Some((name, value, commented)) = define_line(line) else {
+            continue;
+        };
+        if defines.iter().any(|d| d.name == name) {
+            continue;
+        }
+        let pragma = pragmas.iter().find(|(n, _)| n == name);
+        if pragma.is_none() && !value.is_empty() {
+            continue;
+        }
+        // A commented-out define only stands in for one that isn't there at all.
+        if commented
+            && source
+                .lines()
+                .any(|l| matches!(define_line(l), Some((n, _, false)) if n == name))
+        {
+            continue;
+        }
+        defines.push(DefineInfo {
+            name: name.to_owned(),
+            values: pragma.map(|(_, values)| values.clone()).unwrap_or_default(),
+            default: (!commented).then(|| value.to_owned()),
+            line: Some(index),
+        });
+    }
+    for (name, values) in pragmas {
+        if !defines.iter().any(|d| d.name == name) {
+            defines.push(DefineInfo {
+                default: values.first().cloned(),
+                name,
+                values,
+                line: None,
+            });
+        }
+    }
+    defines
+}
+
+/// The source with the defines set to `values`, keeping line numbers where they were; defines
+/// that aren't in the file go after its `#version`, if it has one.
+pub fn inject_defines(source: &str, defines: &[DefineInfo], values: &DefineValues) -> String {
+    let mut lines: Vec<String> = source.lines().map(str::to_owned).collect();
+    let mut header = String::new();
+    for define in defines {
+        let value = values.get(&define.name).unwrap_or(&define.default);
+        let text = match value {
+            Some(value) if value.is_empty() => format!("#define {}", define.name),
+            Some(value) => format!("#define {} {}", define.name, value),
+            None => String::new(),
+        };
+        match define.line {
+            Some(index) => lines[index] = text,
+            None if !text.is_empty() => {
+                header.push_str(&text);
+                header.push('\n');
+            }
+            None => {}
+        }
+    }
+    if header.is_empty() {
+        return lines.join("\n");
+    }
+    match lines
+        .iter()
+        .position(|line| line.trim_start().starts_with("#version"))
+    {
+        Some(index) => {
+            let rest = lines.split_off(index + 1);
+            format!(
+                "{}\n{}#line {} 1\n{}",
+                lines.join("\n"),
+                header,
+                index + 2,
+                rest.join("\n")
+            )
+        }
+        None => format!("{}#line 1 1\n{}", header, lines.join("\n")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inject(source: &str, values: &[(&str, Option<&str>)]) -> String {
+        let values = values
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.map(str::to_owned)))
+            .collect();
+        inject_defines(source, &parse_defines(source), &values)
+    }
+
+    #[test]
+    fn parses_switches_and_choices() {
+        let source = "#pragma @define QUALITY {values: [1, 2, 4]}\n\
+                      #define QUALITY 2\n\
+                      #define SHADOWS\n\
+                      // #define DEBUG\n\
+                      #define PI 3.14159\n\
+                      #define SQ(x) ((x) * (x))\n";
+        let defines = parse_defines(source);
+        let summary: Vec<(&str, Vec<String>, Option<&str>)> = defines
+            .iter()
+            .map(|d| (d.name.as_str(), d.values.clone(), d.default.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "QUALITY",
+                    vec!["1".into(), "2".into(), "4".into()],
+                    Some("2")
+                ),
+                ("SHADOWS", vec![], Some("")),
+                ("DEBUG", vec![], None),
+            ]
+        );
+    }
+
+    #[test]
+    fn commented_out_define_yields_to_a_live_one() {
+        let source = "// #define FAST\n#define FAST\n";
+        let defines = parse_defines(source);
+        assert_eq!(defines.len(), 1);
+        assert_eq!(defines[0].default.as_deref(), Some(""));
+        assert_eq!(defines[0].line, Some(1));
+    }
+
+    #[test]
+    fn defaults_keep_live_defines_and_blank_commented_ones() {
+        let source = "#define SHADOWS\n// #define DEBUG\nvoid main() {}";
+        assert_eq!(inject(source, &[]), "#define SHADOWS\n\nvoid main() {}");
+    }
+
+    #[test]
+    fn overrides_replace_their_line() {
+        let source = "#pragma @define QUALITY {values: [1, 2, 4]}\n\
+                      #define QUALITY 2\n\
+                      #define SHADOWS\n\
+                      // #define DEBUG\n\
+                      void main() {}";
+        let injected = inject(
+            source,
+            &[
+                ("QUALITY", Some("4")),
+                ("SHADOWS", None),
+                ("DEBUG", Some("")),
+            ],
+        );
+        assert_eq!(
+            injected,
+            "#pragma @define QUALITY {values: [1, 2, 4]}\n\
+             #define QUALITY 4\n\
+             \n\
+             #define DEBUG\n\
+             void main() {}"
+        );
+        assert_eq!(injected.lines().count(), source.lines().count());
+    }
+
+    #[test]
+    fn pragma_only_define_goes_in_a_renumbered_header() {
+        let source = "#pragma @define MODE {values: [\"a\", \"b\"]}\nvoid main() {}";
+        assert_eq!(
+            inject(source, &[("MODE", Some("b"))]),
+            "#define MODE b\n#line 1 1\n#pragma @define MODE {values: [\"a\", \"b\"]}\n\
+             void main() {}"
+        );
+        assert_eq!(inject(source, &[("MODE", None)]), source);
+    }
+
+    #[test]
+    fn header_goes_after_the_version() {
+        let source = "// Title\n#version 300 es\n#pragma @define MODE {values: [1, 2]}\n\
+                      void main() {}";
+        assert_eq!(
+            inject(source, &[]),
+            "// Title\n#version 300 es\n#define MODE 1\n#line 3 1\n\
+             #pragma @define MODE {values: [1, 2]}\nvoid main() {}"
+        );
+    }
+}
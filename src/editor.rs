@@ -0,0 +1,101 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Opens files in the user's editor at a given line.
+pub(crate) struct Editor {
+    /// Command line with optional `{file}` and `{line}` placeholders.
+    command: Option<String>,
+}
+
+impl Editor {
+    /// The configured command, else `$VISUAL`, else `$EDITOR`.
+    pub fn new(configured: Option<String>) -> Self {
+        let command = configured
+            .or_else(|| std::env::var("VISUAL").ok())
+            .or_else(|| std::env::var("EDITOR").ok())
+            .filter(|command| !command.trim().is_empty());
+        Self { command }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.command.is_some()
+    }
+
+    /// Start the editor without waiting for it to exit.
+    pub fn open(&self, path: &Path, line: usize) -> eyre::Result<()> {
+        let Some(command) = &self.command else {
+            eyre::bail!("No editor configured; set `editor` in the config, $VISUAL or $EDITOR");
+        };
+        let args = editor_args(
+            &split_command(command),
+            &path.to_string_lossy(),
+            line.max(1),
+        );
+        let (program, args) = args
+            .split_first()
+            .ok_or_else(|| eyre::eyre!("Empty editor command"))?;
+        let mut child = Command::new(program).args(args).spawn()?;
+        std::thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
+
+/// Split a command line on whitespace, keeping single- or double-quoted runs together.
+fn split_command(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in command.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.get_or_insert_default().push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                word.get_or_insert_default();
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+/// Fill in the placeholders; without any, pass the line the way well-known editors expect it.
+fn editor_args(words: &[String], file: &str, line: usize) -> Vec<String> {
+    let line = line.to_string();
+    if words
+        .iter()
+        .any(|w| w.contains("{file}") || w.contains("{line}"))
+    {
+        return words
+            .iter()
+            .map(|w| w.replace("{file}", file).replace("{line}", &line))
+            .collect();
+    }
+    let program = words
+        .first()
+        .and_then(|w| Path::new(w).file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extra = match program.as_str() {
+        "code" | "codium" | "code-insiders" => vec!["-g".to_owned(), format!("{}:{}", file, line)],
+        "vi" | "vim" | "nvim" | "gvim" | "nano" | "emacs" | "emacsclient" | "hx" | "kak"
+        | "micro" => vec![format!("+{}", line), file.to_owned()],
+        "subl" | "zed" => vec![format!("{}:{}", file, line)],
+        _ => vec![file.to_owned()],
+    };
+    words.iter().cloned().chain(extra).collect()
+}
+
+/// The shader file line of the first error in a GL info log: `1:12(5):` (Mesa), `ERROR: 1:12:`
+/// (ANGLE and others) or `1(12) :` (NVIDIA). Source string 1 is where the shader file starts.
+pub(crate) fn error_line(log: &str) -> Option<usize> {
+    log.split_whitespace().find_map(|word| {
+        let rest = word
+            .strip_prefix("1:")
+            .or_else(|| word.strip_prefix("1("))?;
+        let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+        digits.parse().ok()
+    })
+}
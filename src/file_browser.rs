@@ -0,0 +1,58 @@
+use crate::file_collection::{FileCollection, FileTree, fuzzy_score};
+use egui::Ui;
+use std::path::PathBuf;
+
+/// Search field over a collection's folder tree, or over a ranked flat list while searching;
+/// returns the file that was clicked.
+pub(crate) fn file_browser(
+    ui: &mut Ui,
+    collection: &FileCollection,
+    search: &mut String,
+) -> Option<PathBuf> {
+    let mut clicked = None;
+    ui.text_edit_singleline(search).request_focus();
+    egui::ScrollArea::vertical()
+        .max_height(400.0)
+        .show(ui, |ui| {
+            if search.trim().is_empty() {
+                folder(ui, collection, &collection.tree, &mut clicked);
+            } else {
+                let mut matches: Vec<(i32, &String, &PathBuf)> = collection
+                    .files
+                    .iter()
+                    .filter_map(|(label, path)| Some((fuzzy_score(search, label)?, label, path)))
+                    .collect();
+                matches.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+                for (_, label, path) in matches {
+                    if ui.selectable_label(false, label).clicked() {
+                        clicked = Some(path.clone());
+                    }
+                }
+            }
+        });
+    clicked
+}
+
+fn folder(
+    ui: &mut Ui,
+    collection: &FileCollection,
+    tree: &FileTree,
+    clicked: &mut Option<PathBuf>,
+) {
+    for (name, subtree) in &tree.folders {
+        egui::CollapsingHeader::new(format!("🗀 {}", name))
+            .id_salt(ui.id().with(name))
+            .show(ui, |ui| folder(ui, collection, subtree, clicked));
+    }
+    for index in &tree.files {
+        let (label, path) = &collection.files[*index];
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if ui
+            .selectable_label(false, name)
+            .on_hover_text(label)
+            .clicked()
+        {
+            *clicked = Some(path.clone());
+        }
+    }
+}
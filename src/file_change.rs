@@ -19,10 +19,10 @@ pub fn has_changed(
     last_state: &Option<FileChangeState>,
     min_check_interval: std::time::Duration,
 ) -> eyre::Result<Option<FileChangeState>> {
-    if let Some(s) = last_state {
-        if s.checked_at.elapsed() < min_check_interval {
-            return Ok(None); // Too soon to check again
-        }
+    if let Some(s) = last_state
+        && s.checked_at.elapsed() < min_check_interval
+    {
+        return Ok(None); // Too soon to check again
     }
     let stat = file_path.metadata()?;
     let new_state = FileChangeState {
@@ -30,10 +30,10 @@ pub fn has_changed(
         last_modified: stat.modified()?,
         size: stat.len(),
     };
-    if let Some(last_state) = last_state {
-        if new_state.state_equal(last_state) {
-            return Ok(None); // No change
-        }
+    if let Some(last_state) = last_state
+        && new_state.state_equal(last_state)
+    {
+        return Ok(None); // No change
     }
     Ok(Some(new_state))
 }
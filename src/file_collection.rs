@@ -1,36 +1,146 @@
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+/// Folders and files of a collection, mirroring the directories under its roots.
+#[derive(Default, Debug)]
+pub(crate) struct FileTree {
+    pub folders: BTreeMap<String, FileTree>,
+    /// Indices into `FileCollection::files`.
+    pub files: Vec<usize>,
+}
+
 #[derive(Default, Debug)]
 pub(crate) struct FileCollection {
-    pub root: PathBuf,
+    pub roots: Vec<PathBuf>,
     pub suffixes: Vec<String>,
+    /// Labels are file names, or relative paths where file names alone would be ambiguous.
     pub files: Vec<(String, PathBuf)>,
+    pub tree: FileTree,
 }
 
 impl FileCollection {
-    pub fn new(root: &Path, patterns: &[&str]) -> Self {
+    pub fn new(roots: &[PathBuf], patterns: &[&str]) -> Self {
         Self {
-            root: root.to_path_buf(),
+            roots: roots.to_vec(),
             suffixes: patterns.iter().map(|p| p.to_string()).collect(),
             files: Vec::new(),
+            tree: FileTree::default(),
         }
     }
 
     pub fn collect_files(&mut self) -> eyre::Result<usize> {
-        let mut files: Vec<(String, PathBuf)> = Vec::new();
-        for entry in WalkDir::new(&self.root).into_iter().flatten() {
-            if !entry.file_type().is_file() {
-                continue;
+        // Path components from the top of the tree down to the file name.
+        let mut found: Vec<(Vec<String>, PathBuf)> = Vec::new();
+        for root in &self.roots {
+            let root_name = root_name(root);
+            for entry in WalkDir::new(root).into_iter().flatten() {
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let name = entry.file_name().to_string_lossy();
+                if !self.suffixes.iter().any(|suffix| name.ends_with(suffix)) {
+                    continue;
+                }
+                let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+                let mut components: Vec<String> = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                    .collect();
+                if self.roots.len() > 1 {
+                    components.insert(0, root_name.clone());
+                }
+                found.push((components, PathBuf::from(entry.path())));
             }
-            let name = entry.file_name().to_string_lossy();
-            if !self.suffixes.iter().any(|suffix| name.ends_with(suffix)) {
-                continue;
-            }
-            files.push((name.parse()?, PathBuf::from(entry.path())));
         }
+        let labels = disambiguated_labels(&found);
+        let mut files: Vec<(String, PathBuf, Vec<String>)> = found
+            .into_iter()
+            .zip(labels)
+            .map(|((components, path), label)| (label, path, components))
+            .collect();
         files.sort_by(|a, b| a.0.cmp(&b.0));
-        self.files = files;
+        let mut tree = FileTree::default();
+        for (index, (_, _, components)) in files.iter().enumerate() {
+            let (_, folders) = components.split_last().expect("file has a name");
+            folders
+                .iter()
+                .fold(&mut tree, |node, folder| {
+                    node.folders.entry(folder.clone()).or_default()
+                })
+                .files
+                .push(index);
+        }
+        self.files = files
+            .into_iter()
+            .map(|(label, path, _)| (label, path))
+            .collect();
+        self.tree = tree;
         Ok(self.files.len())
     }
 }
+
+/// Tell roots apart by their last component, e.g. `shaders` for `./shaders`.
+fn root_name(root: &Path) -> String {
+    root.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| root.to_string_lossy().into_owned())
+}
+
+/// The shortest path suffix of each file that no other file shares.
+fn disambiguated_labels(files: &[(Vec<String>, PathBuf)]) -> Vec<String> {
+    let suffix = |components: &[String], length: usize| {
+        components[components.len().saturating_sub(length)..].join("/")
+    };
+    let mut lengths = vec![1; files.len()];
+    loop {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for ((components, _), length) in files.iter().zip(&lengths) {
+            *counts.entry(suffix(components, *length)).or_default() += 1;
+        }
+        let mut grew = false;
+        for ((components, _), length) in files.iter().zip(lengths.iter_mut()) {
+            if counts[&suffix(components, *length)] > 1 && *length < components.len() {
+                *length += 1;
+                grew = true;
+            }
+        }
+        if !grew {
+            break;
+        }
+    }
+    files
+        .iter()
+        .zip(lengths)
+        .map(|((components, _), length)| suffix(components, length))
+        .collect()
+}
+
+/// Case-insensitive subsequence match; higher scores for runs of consecutive characters and
+/// matches at the start of words. `None` if `pattern` doesn't match at all.
+pub(crate) fn fuzzy_score(pattern: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in pattern
+        .to_lowercase()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+    {
+        let found = position + text[position..].iter().position(|c| *c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        if let Some(previous) = previous {
+            score -= (found - previous - 1).min(5) as i32;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
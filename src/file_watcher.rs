@@ -0,0 +1,161 @@
+use notify::event::{EventKind, ModifyKind};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Editors often save in several steps (truncate, write, rename); wait for them to settle.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// What changed since the last poll, once things have been quiet for `DEBOUNCE`.
+#[derive(Debug, Default)]
+pub(crate) struct WatchEvents {
+    /// Watched files that were written, replaced or removed, as originally given.
+    pub files: Vec<PathBuf>,
+    /// Files were added, removed or renamed under one of the watched trees.
+    pub trees: bool,
+}
+
+/// Event-driven watcher for individual files and whole directory trees.
+///
+/// Files are watched through their parent directories, so saves that write a temporary
+/// file and rename it over the original are seen as changes rather than removals.
+pub(crate) struct FileWatcher {
+    ctx: egui::Context,
+    watcher: RecommendedWatcher,
+    inbox: mpsc::Receiver<notify::Result<notify::Event>>,
+    /// Canonical tree roots, watched recursively.
+    trees: Vec<PathBuf>,
+    /// Files as last given to `watch_files`.
+    requested_files: Vec<PathBuf>,
+    /// Canonical file paths, mapped back to the paths they were given as.
+    files: HashMap<PathBuf, PathBuf>,
+    /// Canonical directories watched non-recursively on behalf of `files`.
+    file_dirs: HashSet<PathBuf>,
+    pending_files: HashMap<PathBuf, Instant>,
+    pending_trees: Option<Instant>,
+}
+
+impl FileWatcher {
+    pub fn new(ctx: &egui::Context) -> eyre::Result<Self> {
+        let (outbox, inbox) = mpsc::channel();
+        let repaint_ctx = ctx.clone();
+        let watcher = notify::recommended_watcher(move |event| {
+            outbox.send(event).ok();
+            // Wake up to deliver it even when not updating continuously.
+            repaint_ctx.request_repaint_after(DEBOUNCE);
+        })?;
+        Ok(Self {
+            ctx: ctx.clone(),
+            watcher,
+            inbox,
+            trees: Vec::new(),
+            requested_files: Vec::new(),
+            files: HashMap::new(),
+            file_dirs: HashSet::new(),
+            pending_files: HashMap::new(),
+            pending_trees: None,
+        })
+    }
+
+    /// Watch everything under `root` for files coming and going.
+    pub fn watch_tree(&mut self, root: &Path) {
+        let result = root
+            .canonicalize()
+            .map_err(eyre::Error::from)
+            .and_then(|root| {
+                self.watcher.watch(&root, RecursiveMode::Recursive)?;
+                self.trees.push(root);
+                Ok(())
+            });
+        if let Err(e) = result {
+            log::error!("Error watching {:?}: {:?}", root, e);
+        }
+    }
+
+    /// Watch exactly these files from now on; cheap to call every frame.
+    pub fn watch_files(&mut self, files: Vec<PathBuf>) {
+        if files == self.requested_files {
+            return;
+        }
+        self.files = files
+            .iter()
+            .filter_map(|path| Some((canonical_file_path(path)?, path.clone())))
+            .collect();
+        self.requested_files = files;
+        let dirs: HashSet<PathBuf> = self
+            .files
+            .keys()
+            .filter_map(|path| path.parent())
+            .filter(|dir| !self.trees.iter().any(|root| dir.starts_with(root)))
+            .map(Path::to_path_buf)
+            .collect();
+        for dir in self.file_dirs.difference(&dirs) {
+            self.watcher.unwatch(dir).ok();
+        }
+        for dir in dirs.difference(&self.file_dirs) {
+            if let Err(e) = self.watcher.watch(dir, RecursiveMode::NonRecursive) {
+                log::error!("Error watching {:?}: {:?}", dir, e);
+            }
+        }
+        self.file_dirs = dirs;
+    }
+
+    pub fn poll(&mut self) -> WatchEvents {
+        let now = Instant::now();
+        while let Ok(event) = self.inbox.try_recv() {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    log::error!("Error watching files: {:?}", e);
+                    continue;
+                }
+            };
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            let structural = matches!(
+                event.kind,
+                EventKind::Create(_)
+                    | EventKind::Remove(_)
+                    | EventKind::Modify(ModifyKind::Name(_))
+            );
+            for path in &event.paths {
+                if let Some(original) = self.files.get(path) {
+                    self.pending_files.insert(original.clone(), now);
+                }
+                if structural && self.trees.iter().any(|root| path.starts_with(root)) {
+                    self.pending_trees = Some(now);
+                }
+            }
+        }
+        let settled = |at: &Instant| now.duration_since(*at) >= DEBOUNCE;
+        let mut events = WatchEvents::default();
+        self.pending_files.retain(|path, at| {
+            if settled(at) {
+                events.files.push(path.clone());
+                false
+            } else {
+                true
+            }
+        });
+        if self.pending_trees.as_ref().is_some_and(settled) {
+            self.pending_trees = None;
+            events.trees = true;
+        }
+        if !self.pending_files.is_empty() || self.pending_trees.is_some() {
+            self.ctx.request_repaint_after(DEBOUNCE);
+        }
+        events
+    }
+}
+
+/// The file's canonical path; its directory must exist, but the file itself needn't.
+fn canonical_file_path(path: &Path) -> Option<PathBuf> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    Some(dir.canonicalize().ok()?.join(path.file_name()?))
+}
@@ -0,0 +1,9 @@
+precision highp float;
+
+uniform vec4 iViewport;
+
+// Relative to the shader's viewport rather than the window.
+#define varjostin_FragCoord (gl_FragCoord - vec4(iViewport.xy, 0.0, 0.0))
+
+// Stands in for `gl_FragColor`, which newer GLSL versions don't have.
+out vec4 varjostin_FragColor;
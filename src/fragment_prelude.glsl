@@ -1,11 +1,3 @@
-precision highp float;
-
-uniform vec4 iViewport;
-uniform vec3 iResolution;
-uniform float iTime;
-uniform int iFrame;
-uniform vec4 iMouse;
-
 out vec4 shadertoy_out_color;
 
 void mainImage(out vec4 fragColor, in vec2 fragCoord);
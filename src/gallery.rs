@@ -0,0 +1,245 @@
+use crate::file_collection::fuzzy_score;
+use crate::thumbnail::{THUMBNAIL_SIZE, ThumbnailRenderRequest, ThumbnailRenderResponse};
+use egui::{Color32, ColorImage, RichText, TextureHandle, TextureOptions};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+enum Thumbnail {
+    /// Waiting for the GPU to render it.
+    Rendering,
+    Ready(TextureHandle),
+    Failed(String),
+}
+
+/// Window showing a rendered thumbnail of every shader in the collection.
+pub struct Gallery {
+    pub open: bool,
+    search: String,
+    thumbnails: HashMap<PathBuf, Thumbnail>,
+    cache_dir: Option<PathBuf>,
+    inbox: mpsc::Receiver<ThumbnailRenderResponse>,
+    outbox: mpsc::Sender<ThumbnailRenderResponse>,
+}
+
+impl Default for Gallery {
+    fn default() -> Self {
+        let (outbox, inbox) = mpsc::channel();
+        Self {
+            open: false,
+            search: String::new(),
+            thumbnails: HashMap::new(),
+            cache_dir: cache_dir(),
+            inbox,
+            outbox,
+        }
+    }
+}
+
+impl Gallery {
+    /// Show the gallery; returns a shader to render if one needs a thumbnail, and the shader
+    /// that was clicked, if any.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        shaders: &[(String, PathBuf)],
+    ) -> (Option<ThumbnailRenderRequest>, Option<PathBuf>) {
+        if !self.open {
+            // Shaders may change while we're closed, so start afresh next time.
+            self.thumbnails.clear();
+            return (None, None);
+        }
+        while let Ok(response) = self.inbox.try_recv() {
+            self.receive(ctx, response);
+        }
+        let request = self.next_request(ctx, shaders);
+        let mut clicked = None;
+        let mut open = self.open;
+        egui::Window::new("Gallery")
+            .open(&mut open)
+            .default_size([720.0, 480.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Search");
+                    ui.text_edit_singleline(&mut self.search);
+                    if ui
+                        .button("R")
+                        .on_hover_text("Reload changed shaders")
+                        .clicked()
+                    {
+                        self.thumbnails.clear();
+                    }
+                });
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.horizontal_wrapped(|ui| {
+                        for (label, path) in shaders {
+                            if fuzzy_score(&self.search, label).is_none() {
+                                continue;
+                            }
+                            if self.card(ui, label, path) {
+                                clicked = Some(path.clone());
+                            }
+                        }
+                    });
+                });
+            });
+        self.open = open;
+        (request, clicked)
+    }
+
+    fn card(&self, ui: &mut egui::Ui, label: &str, path: &Path) -> bool {
+        let size = egui::vec2(THUMBNAIL_SIZE[0] as f32, THUMBNAIL_SIZE[1] as f32);
+        let response = ui
+            .group(|ui| {
+                ui.set_width(size.x);
+                ui.vertical(|ui| {
+                    match self.thumbnails.get(path) {
+                        Some(Thumbnail::Ready(texture)) => {
+                            ui.add(egui::Image::new((texture.id(), size)));
+                        }
+                        Some(Thumbnail::Failed(error)) => {
+                            egui::ScrollArea::vertical()
+                                .id_salt(path)
+                                .max_height(size.y)
+                                .show(ui, |ui| {
+                                    ui.label(RichText::new(error).small().color(Color32::RED));
+                                });
+                        }
+                        Some(Thumbnail::Rendering) | None => {
+                            ui.allocate_ui(size, |ui| ui.spinner());
+                        }
+                    }
+                    ui.horizontal(|ui| {
+                        match self.thumbnails.get(path) {
+                            Some(Thumbnail::Ready(_)) => {
+                                ui.label(RichText::new("✔").color(Color32::GREEN))
+                                    .on_hover_text("Compiles");
+                            }
+                            Some(Thumbnail::Failed(_)) => {
+                                ui.label(RichText::new("✖").color(Color32::RED))
+                                    .on_hover_text("Fails to compile");
+                            }
+                            _ => {}
+                        }
+                        ui.label(label);
+                    });
+                })
+            })
+            .response
+            .interact(egui::Sense::click())
+            .on_hover_cursor(egui::CursorIcon::PointingHand);
+        response.clicked()
+    }
+
+    /// Load the first missing thumbnail from the disk cache, or ask for it to be rendered.
+    fn next_request(
+        &mut self,
+        ctx: &egui::Context,
+        shaders: &[(String, PathBuf)],
+    ) -> Option<ThumbnailRenderRequest> {
+        let path = shaders
+            .iter()
+            .map(|(_, path)| path)
+            .find(|path| !self.thumbnails.contains_key(*path))?
+            .clone();
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(e) => {
+                self.thumbnails
+                    .insert(path, Thumbnail::Failed(e.to_string()));
+                return None;
+            }
+        };
+        let hash = fnv1a(source.as_bytes());
+        if let Some(thumbnail) = self.load_cached(ctx, &path, hash) {
+            self.thumbnails.insert(path, thumbnail);
+            return None;
+        }
+        self.thumbnails.insert(path.clone(), Thumbnail::Rendering);
+        Some(ThumbnailRenderRequest {
+            path,
+            hash,
+            fragment_source: source,
+            response_sender: self.outbox.clone(),
+        })
+    }
+
+    fn receive(&mut self, ctx: &egui::Context, response: ThumbnailRenderResponse) {
+        let thumbnail = match response.result {
+            Ok(pixels) => {
+                if let Some(dir) = &self.cache_dir {
+                    let path = dir.join(format!("{:016x}.png", response.hash));
+                    let saved = std::fs::create_dir_all(dir)
+                        .map_err(eyre::Error::from)
+                        .and_then(|_| {
+                            image::save_buffer(
+                                &path,
+                                &pixels,
+                                THUMBNAIL_SIZE[0] as u32,
+                                THUMBNAIL_SIZE[1] as u32,
+                                image::ExtendedColorType::Rgba8,
+                            )
+                            .map_err(eyre::Error::from)
+                        });
+                    if let Err(e) = saved {
+                        log::error!("Error caching thumbnail {:?}: {:?}", path, e);
+                    }
+                }
+                Thumbnail::Ready(load_texture(ctx, &response.path, &pixels))
+            }
+            Err(e) => {
+                if let Some(dir) = &self.cache_dir {
+                    std::fs::create_dir_all(dir)
+                        .and_then(|_| {
+                            std::fs::write(
+                                dir.join(format!("{:016x}.err", response.hash)),
+                                e.to_string(),
+                            )
+                        })
+                        .ok();
+                }
+                Thumbnail::Failed(e.to_string())
+            }
+        };
+        self.thumbnails.insert(response.path, thumbnail);
+    }
+
+    fn load_cached(&self, ctx: &egui::Context, path: &Path, hash: u64) -> Option<Thumbnail> {
+        let dir = self.cache_dir.as_ref()?;
+        if let Ok(error) = std::fs::read_to_string(dir.join(format!("{:016x}.err", hash))) {
+            return Some(Thumbnail::Failed(error));
+        }
+        let image = image::open(dir.join(format!("{:016x}.png", hash))).ok()?;
+        if [image.width() as usize, image.height() as usize] != THUMBNAIL_SIZE {
+            return None;
+        }
+        Some(Thumbnail::Ready(load_texture(
+            ctx,
+            path,
+            image.to_rgba8().as_raw(),
+        )))
+    }
+}
+
+fn load_texture(ctx: &egui::Context, path: &Path, pixels: &[u8]) -> TextureHandle {
+    ctx.load_texture(
+        format!("thumbnail:{}", path.display()),
+        ColorImage::from_rgba_unmultiplied(THUMBNAIL_SIZE, pixels),
+        TextureOptions::LINEAR,
+    )
+}
+
+/// `$XDG_CACHE_HOME/varjostin/thumbnails`, falling back to `~/.cache`.
+fn cache_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(base.join("varjostin").join("thumbnails"))
+}
+
+/// Stable across builds, unlike `DefaultHasher`, so the disk cache stays valid.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
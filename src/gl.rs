@@ -1,14 +1,36 @@
 #![allow(clippy::undocumented_unsafe_blocks)]
 
+use crate::prelude::PreludeMode;
+use crate::textures::DataImage;
 use eframe::egui_glow;
 use eframe::egui_glow::ShaderVersion;
 use eframe::glow::{HasContext, NativeProgram};
 use egui_glow::glow;
 
 const VERTEX_SHADER: &str = include_str!("vertex.glsl");
-const FRAGMENT_PRELUDE: &str = include_str!("fragment_prelude.glsl");
+/// Compile a shader written for `mode`'s conventions.
+pub fn compile_program(
+    gl: &glow::Context,
+    fragment_source: &str,
+    mode: PreludeMode,
+) -> eyre::Result<NativeProgram> {
+    compile_program_with_prelude(gl, mode.prelude(), &mode.adapt_source(fragment_source))
+}
+
+pub fn compile_program_with_prelude(
+    gl: &glow::Context,
+    fragment_prelude: &str,
+    fragment_source: &str,
+) -> eyre::Result<NativeProgram> {
+    compile_program_with_vertex_shader(gl, VERTEX_SHADER, fragment_prelude, fragment_source)
+}
 
-pub fn compile_program(gl: &glow::Context, fragment_source: &str) -> eyre::Result<NativeProgram> {
+pub fn compile_program_with_vertex_shader(
+    gl: &glow::Context,
+    vertex_shader: &str,
+    fragment_prelude: &str,
+    fragment_source: &str,
+) -> eyre::Result<NativeProgram> {
     let shader_version = ShaderVersion::get(gl);
 
     unsafe {
@@ -22,8 +44,8 @@ pub fn compile_program(gl: &glow::Context, fragment_source: &str) -> eyre::Resul
         }
 
         let shader_sources = [
-            (glow::VERTEX_SHADER, VERTEX_SHADER, ""),
-            (glow::FRAGMENT_SHADER, FRAGMENT_PRELUDE, fragment_source),
+            (glow::VERTEX_SHADER, vertex_shader, ""),
+            (glow::FRAGMENT_SHADER, fragment_prelude, fragment_source),
         ];
 
         let shaders: Vec<_> = shader_sources
@@ -70,3 +92,45 @@ pub fn compile_program(gl: &glow::Context, fragment_source: &str) -> eyre::Resul
         Ok(program)
     }
 }
+
+/// Create an edge-clamped texture for data images; `upload_data_texture` sets its filtering.
+pub fn create_data_texture(gl: &glow::Context) -> eyre::Result<glow::Texture> {
+    unsafe {
+        let texture = gl.create_texture().map_err(|e| eyre::eyre!(e))?;
+        gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+        for (param, value) in [
+            (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+            (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+        ] {
+            gl.tex_parameter_i32(glow::TEXTURE_2D, param, value as i32);
+        }
+        Ok(texture)
+    }
+}
+
+/// Upload `image` into `texture`, filtered linearly so ramps interpolate between their texels,
+/// or nearest for images that ask for it.
+pub fn upload_data_texture(gl: &glow::Context, texture: glow::Texture, image: &DataImage) {
+    unsafe {
+        gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+        let filter = if image.nearest {
+            glow::NEAREST
+        } else {
+            glow::LINEAR
+        };
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, filter as i32);
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, filter as i32);
+        gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
+        gl.tex_image_2d(
+            glow::TEXTURE_2D,
+            0,
+            glow::RGBA8 as i32,
+            image.width as i32,
+            image.height as i32,
+            0,
+            glow::RGBA,
+            glow::UNSIGNED_BYTE,
+            glow::PixelUnpackData::Slice(Some(&image.pixels)),
+        );
+    }
+}
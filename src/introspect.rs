@@ -0,0 +1,163 @@
+#![allow(clippy::undocumented_unsafe_blocks)]
+
+use crate::prelude::PreludeMode;
+use crate::shader_parser::{
+    FloatUniformSpec, IntUniformSpec, PointCoords, PreparseResult, UniformInfo, UniformSmell,
+    UniformSpec, UniformWidget, Vec2UniformSpec, Vec3UniformSpec, Vec4UniformSpec,
+};
+use eframe::glow::{self, HasContext};
+
+/// Functions the app looks for, found by name when the preparser can't tell.
+const ENTRY_POINTS: [&str; 2] = ["mainImage", "mainSound"];
+
+/// The shader's own uniforms as the linked program sees them, with their initial values, and
+/// descriptions of those that can't be shown. Uniforms the compiler optimized out aren't there.
+pub(crate) fn program_uniforms(
+    gl: &glow::Context,
+    program: glow::Program,
+    mode: PreludeMode,
+) -> (Vec<UniformInfo>, Vec<String>) {
+    let mut uniforms = Vec::new();
+    let mut unsupported = Vec::new();
+    unsafe {
+        for index in 0..gl.get_active_uniforms(program) {
+            let Some(active) = gl.get_active_uniform(program, index) else {
+                continue;
+            };
+            if mode.is_builtin_uniform(&active.name) {
+                continue;
+            }
+            if active.size > 1 || active.name.ends_with(']') {
+                unsupported.push(format!(
+                    "{}, an array of {}",
+                    active.name.trim_end_matches("[0]"),
+                    active.size
+                ));
+                continue;
+            }
+            let location = gl.get_uniform_location(program, &active.name);
+            let floats = |n: usize| {
+                let mut value = [0.0; 4];
+                if let Some(location) = &location {
+                    gl.get_uniform_f32(program, location, &mut value[..n]);
+                }
+                value
+            };
+            let spec = match active.utype {
+                glow::INT => {
+                    let mut value = [0];
+                    if let Some(location) = &location {
+                        gl.get_uniform_i32(program, location, &mut value);
+                    }
+                    UniformSpec::Int(IntUniformSpec {
+                        default: Some(value[0]),
+                    })
+                }
+                glow::FLOAT => UniformSpec::Float(FloatUniformSpec {
+                    default: Some(floats(1)[0]),
+                }),
+                glow::FLOAT_VEC2 => {
+                    let [x, y, _, _] = floats(2);
+                    UniformSpec::Vec2(Vec2UniformSpec {
+                        default: Some([x, y]),
+                    })
+                }
+                glow::FLOAT_VEC3 => {
+                    let [x, y, z, _] = floats(3);
+                    UniformSpec::Vec3(Vec3UniformSpec {
+                        default: Some([x, y, z]),
+                    })
+                }
+                glow::FLOAT_VEC4 => UniformSpec::Vec4(Vec4UniformSpec {
+                    default: Some(floats(4)),
+                }),
+                glow::SAMPLER_2D => UniformSpec::Sampler2D,
+                utype => {
+                    unsupported.push(format!("{}, of GL type {:#x}", active.name, utype));
+                    continue;
+                }
+            };
+            uniforms.push(UniformInfo {
+                smell: if active.name.to_lowercase().contains("color") {
+                    UniformSmell::Color
+                } else {
+                    UniformSmell::Unperfumed
+                },
+                name: active.name,
+                spec,
+                range: 0.0..=1.0,
+                widget: UniformWidget::Default,
+                source: None,
+                zoom: None,
+                coords: PointCoords::default(),
+                label: None,
+            });
+        }
+    }
+    (uniforms, unsupported)
+}
+
+/// Stand-in for a preparse result, for when the preparser can't handle the source.
+pub(crate) fn fallback_preparse(source: &str, uniforms: Vec<UniformInfo>) -> PreparseResult {
+    let functions = ENTRY_POINTS
+        .iter()
+        .filter(|name| {
+            source
+                .match_indices(*name)
+                .any(|(at, _)| source[at + name.len()..].trim_start().starts_with('('))
+        })
+        .map(|name| name.to_string())
+        .collect();
+    PreparseResult {
+        uniforms,
+        functions,
+    }
+}
+
+/// Report where the preparser and the program disagree, and fill in initial values the
+/// preparser couldn't work out.
+pub(crate) fn cross_check(ppr: &mut PreparseResult, program_uniforms: &[UniformInfo]) {
+    for active in program_uniforms {
+        match ppr.uniforms.iter_mut().find(|u| u.name == active.name) {
+            None => log::warn!(
+                "Uniform {} is in the program but the preparser didn't find it",
+                active.name
+            ),
+            Some(parsed) => match (&mut parsed.spec, &active.spec) {
+                (UniformSpec::Int(p), UniformSpec::Int(a)) => p.default = p.default.or(a.default),
+                (UniformSpec::Float(p), UniformSpec::Float(a)) => {
+                    p.default = p.default.or(a.default)
+                }
+                (UniformSpec::Vec2(p), UniformSpec::Vec2(a)) => p.default = p.default.or(a.default),
+                (UniformSpec::Vec3(p), UniformSpec::Vec3(a)) => p.default = p.default.or(a.default),
+                (UniformSpec::Vec4(p), UniformSpec::Vec4(a)) => p.default = p.default.or(a.default),
+                (UniformSpec::Sampler2D, UniformSpec::Sampler2D) => {}
+                (parsed_spec, active_spec) => log::warn!(
+                    "Uniform {} is {} to the preparser but {} in the program",
+                    active.name,
+                    spec_name(parsed_spec),
+                    spec_name(active_spec)
+                ),
+            },
+        }
+    }
+    for parsed in &ppr.uniforms {
+        if !program_uniforms.iter().any(|u| u.name == parsed.name) {
+            log::info!(
+                "Uniform {} isn't used, so the compiler left it out",
+                parsed.name
+            );
+        }
+    }
+}
+
+fn spec_name(spec: &UniformSpec) -> &'static str {
+    match spec {
+        UniformSpec::Int(_) => "an int",
+        UniformSpec::Float(_) => "a float",
+        UniformSpec::Vec2(_) => "a vec2",
+        UniformSpec::Vec3(_) => "a vec3",
+        UniformSpec::Vec4(_) => "a vec4",
+        UniformSpec::Sampler2D => "a sampler2D",
+    }
+}
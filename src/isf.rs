@@ -0,0 +1,799 @@
+use crate::prelude::{PreludeMode, declared_uniform, mentions_identifier};
+use crate::shader_parser::{PreparseResult, UniformSource, UniformSpec, UniformWidget};
+use serde::{Deserialize, Serialize};
+
+/// Varjostin's builtins that an exported ISF shader has to do without, and what they become.
+const BUILTIN_STAND_INS: [(&str, &str); 16] = [
+    ("iResolution", "vec3(RENDERSIZE, 1.0)"),
+    ("iViewport", "vec4(0.0, 0.0, RENDERSIZE)"),
+    ("iTime", "TIME"),
+    ("iTimeDelta", "TIMEDELTA"),
+    ("iFrame", "FRAMEINDEX"),
+    ("iMouse", "vec4(0.0)"),
+    ("iMouseWheel", "vec2(0.0)"),
+    ("iMouseButtons", "0"),
+    ("iBPM", "120.0"),
+    ("iBeat", "(TIME * 2.0)"),
+    ("iBar", "(TIME * 0.5)"),
+    ("iAudioBands", "vec3(0.0)"),
+    ("iCameraPos", "vec3(0.0)"),
+    ("iCameraTarget", "vec3(0.0)"),
+    ("iCameraMatrix", "mat3(0.0)"),
+    ("iFov", "0.0"),
+];
+
+/// The JSON header of an ISF file, in the comment at its top.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "UPPERCASE", default)]
+struct IsfHeader {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    credit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    isfvsn: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    categories: Vec<String>,
+    inputs: Vec<IsfInput>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    passes: Vec<IsfPass>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+struct IsfInput {
+    name: String,
+    #[serde(rename = "TYPE")]
+    kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default: Option<IsfValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min: Option<IsfValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max: Option<IsfValue>,
+    /// The choices of a `long`.
+    #[serde(default, skip_serializing)]
+    values: Vec<f64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum IsfValue {
+    Bool(bool),
+    Number(f64),
+    Vector(Vec<f64>),
+    Text(String),
+}
+
+impl IsfValue {
+    fn number(&self) -> Option<f64> {
+        match self {
+            IsfValue::Bool(b) => Some(f64::from(u8::from(*b))),
+            IsfValue::Number(n) => Some(*n),
+            IsfValue::Vector(v) => v.first().copied(),
+            IsfValue::Text(t) => t.trim().parse().ok(),
+        }
+    }
+
+    /// The first `N` components, the rest zero.
+    fn vector<const N: usize>(&self) -> [f32; N] {
+        let mut result = [0.0; N];
+        match self {
+            IsfValue::Vector(v) => {
+                for (r, v) in result.iter_mut().zip(v) {
+                    *r = *v as f32;
+                }
+            }
+            value => result.fill(value.number().unwrap_or_default() as f32),
+        }
+        result
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "UPPERCASE", default)]
+struct IsfPass {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    persistent: Option<IsfValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    float: Option<IsfValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<IsfValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<IsfValue>,
+}
+
+/// An ISF pass, drawn into its target or, for the last one, to the output.
+#[derive(Clone, Debug)]
+pub(crate) struct RenderPass {
+    pub target: Option<String>,
+    width: Option<IsfValue>,
+    height: Option<IsfValue>,
+}
+
+impl RenderPass {
+    /// The target's size when the output is `width`×`height`.
+    pub fn size(&self, width: i32, height: i32) -> (i32, i32) {
+        let evaluate = |size: &Option<IsfValue>, full: i32| {
+            match size {
+                Some(IsfValue::Text(expression)) => {
+                    eval_size(expression, f64::from(width), f64::from(height))
+                }
+                Some(value) => value.number(),
+                None => None,
+            }
+            .map_or(full, |size| (size.round() as i32).max(1))
+        };
+        (evaluate(&self.width, width), evaluate(&self.height, height))
+    }
+}
+
+/// Where the header comment's JSON is, if the first block comment holds an object.
+fn header_span(source: &str) -> Option<(usize, usize)> {
+    let start = source.find("/*")? + 2;
+    let end = start + source[start..].find("*/")?;
+    source[start..end]
+        .trim_start()
+        .starts_with('{')
+        .then_some((start, end))
+}
+
+pub(crate) fn has_header(source: &str) -> bool {
+    header_span(source).is_some()
+}
+
+fn parse_header(source: &str) -> Option<eyre::Result<IsfHeader>> {
+    let (start, end) = header_span(source)?;
+    Some(
+        serde_json5::from_str(&source[start..end])
+            .map_err(|e| eyre::eyre!("Error parsing ISF header: {}", e)),
+    )
+}
+
+/// The passes of an ISF shader; empty for a single pass.
+pub(crate) fn passes(source: &str) -> Vec<RenderPass> {
+    let Some(Ok(header)) = parse_header(source) else {
+        return Vec::new();
+    };
+    header
+        .passes
+        .into_iter()
+        .map(|pass| RenderPass {
+            target: pass.target.filter(|target| !target.is_empty()),
+            width: pass.width,
+            height: pass.height,
+        })
+        .collect()
+}
+
+/// Uniform declarations with pragmas for an ISF shader's inputs and pass targets, followed by
+/// the shader.
+pub(crate) fn expand(source: &str) -> String {
+    let header = match parse_header(source) {
+        Some(Ok(header)) => header,
+        Some(Err(e)) => {
+            log::warn!("{}", e);
+            return source.to_owned();
+        }
+        None => return source.to_owned(),
+    };
+    let mut declarations = String::new();
+    for input in &header.inputs {
+        declarations.push_str(&declaration(input));
+    }
+    for pass in &header.passes {
+        if let Some(target) = &pass.target
+            && !target.is_empty()
+            && !header.inputs.iter().any(|input| &input.name == target)
+        {
+            declarations.push_str(&format!("uniform sampler2D {};\n", target));
+        }
+        let size = |value: &Option<IsfValue>| match value {
+            Some(IsfValue::Text(expression)) => eval_size(expression, 1920.0, 1080.0).is_some(),
+            _ => true,
+        };
+        if !size(&pass.width) || !size(&pass.height) {
+            log::warn!(
+                "Can't work out the size of pass {}, so it's the output's",
+                pass.target.as_deref().unwrap_or_default()
+            );
+        }
+        if pass.float.as_ref().and_then(IsfValue::number) == Some(1.0) {
+            log::info!(
+                "Pass {} is rendered with 8 bits per channel rather than floats",
+                pass.target.as_deref().unwrap_or_default()
+            );
+        }
+    }
+    format!("{}#line 1 1\n{}", declarations, source)
+}
+
+/// A Shadertoy-style shader as an ISF file, with its uniforms as inputs described by their
+/// pragmas and the previous frame fed through a persistent pass.
+pub(crate) fn export(
+    source: &str,
+    ppr: &PreparseResult,
+    mode: PreludeMode,
+) -> eyre::Result<String> {
+    if mode != PreludeMode::Shadertoy {
+        eyre::bail!(
+            "Only Shadertoy-style shaders can be exported to ISF, not {:?} ones",
+            mode
+        );
+    }
+    let mut inputs = Vec::new();
+    // What replaces the uniforms' declarations, by name.
+    let mut replacements: Vec<(&str, String)> = Vec::new();
+    let mut feedback: Option<&str> = None;
+    for uniform in &ppr.uniforms {
+        let name = uniform.name.as_str();
+        let (min, max) = (widen(*uniform.range.start()), widen(*uniform.range.end()));
+        let mut input = IsfInput {
+            name: name.to_owned(),
+            kind: String::new(),
+            label: uniform.label.clone(),
+            default: None,
+            min: Some(IsfValue::Number(min)),
+            max: Some(IsfValue::Number(max)),
+            values: Vec::new(),
+        };
+        let vector = |v: &[f32]| Some(IsfValue::Vector(v.iter().copied().map(widen).collect()));
+        match &uniform.spec {
+            UniformSpec::Int(spec) => {
+                input.kind = "long".to_owned();
+                input.default = Some(IsfValue::Number(f64::from(spec.certain_default())));
+            }
+            UniformSpec::Float(spec) => {
+                input.kind = "float".to_owned();
+                input.default = Some(IsfValue::Number(widen(spec.certain_default())));
+            }
+            UniformSpec::Vec2(spec) => {
+                input.kind = "point2D".to_owned();
+                input.default = vector(&spec.certain_default());
+                input.min = vector(&[min as f32; 2]);
+                input.max = vector(&[max as f32; 2]);
+            }
+            UniformSpec::Vec3(spec) => {
+                // ISF colors are vec4s.
+                input.kind = "color".to_owned();
+                input.name = format!("{}_rgba", name);
+                let [r, g, b] = spec.certain_default();
+                input.default = vector(&[r, g, b, 1.0]);
+                (input.min, input.max) = (None, None);
+                replacements.push((name, format!("#define {} {}.rgb", name, input.name)));
+            }
+            UniformSpec::Vec4(spec) => {
+                input.kind = "color".to_owned();
+                input.default = vector(&spec.certain_default());
+                (input.min, input.max) = (None, None);
+            }
+            UniformSpec::Sampler2D => {
+                (input.min, input.max) = (None, None);
+                match (uniform.source, &uniform.widget) {
+                    (Some(UniformSource::Feedback), _) => {
+                        match feedback {
+                            Some(first) => {
+                                replacements.push((name, format!("#define {} {}", name, first)))
+                            }
+                            None => {
+                                feedback = Some(name);
+                                replacements.push((name, String::new()));
+                            }
+                        }
+                        continue;
+                    }
+                    (Some(UniformSource::Keyboard), _)
+                    | (_, UniformWidget::Gradient | UniformWidget::Curve) => log::warn!(
+                        "{} becomes an image input, to be fed an image in place of what it gets \
+                         here",
+                        name
+                    ),
+                    _ => {}
+                }
+                input.kind = "image".to_owned();
+            }
+        }
+        if !replacements.iter().any(|(n, _)| *n == name) {
+            replacements.push((name, String::new()));
+        }
+        inputs.push(input);
+    }
+    let header = IsfHeader {
+        description: source
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("//"))
+            .map(|line| line.trim().to_owned()),
+        credit: None,
+        isfvsn: Some("2".to_owned()),
+        categories: vec!["Generator".to_owned()],
+        inputs,
+        passes: match feedback {
+            Some(target) => vec![
+                IsfPass {
+                    target: Some(target.to_owned()),
+                    persistent: Some(IsfValue::Bool(true)),
+                    ..Default::default()
+                },
+                IsfPass::default(),
+            ],
+            None => Vec::new(),
+        },
+    };
+    let mut text = format!("/*{}*/\n\n", serde_json5::to_string(&header)?);
+    for (name, stand_in) in BUILTIN_STAND_INS {
+        if mentions_identifier(source, name) {
+            text.push_str(&format!("#define {} {}\n", name, stand_in));
+        }
+    }
+    for line in source.lines() {
+        if let Some(rest) = line.trim_start().strip_prefix("#pragma") {
+            let rest = rest.trim_start();
+            if rest.starts_with('@') || rest.starts_with("mode") {
+                continue;
+            }
+        }
+        if let Some(name) = declared_uniform(line)
+            && let Some((_, replacement)) = replacements.iter().find(|(n, _)| *n == name)
+        {
+            if !replacement.is_empty() {
+                text.push_str(replacement);
+                text.push('\n');
+            }
+            continue;
+        }
+        text.push_str(line);
+        text.push('\n');
+    }
+    // Named so as not to collide with the shader's own names or macros.
+    let draw = "vec4 varjostin_color = vec4(0.0);\n    \
+                mainImage(varjostin_color, gl_FragCoord.xy);\n    \
+                gl_FragColor = vec4(varjostin_color.rgb, 1.0);";
+    match feedback {
+        Some(target) => text.push_str(&format!(
+            "\nvoid main() {{\n    if (PASSINDEX == 0) {{\n        {}\n    }} else {{\n        \
+             gl_FragColor = IMG_THIS_PIXEL({});\n    }}\n}}\n",
+            draw.replace("\n    ", "\n        "),
+            target
+        )),
+        None => text.push_str(&format!("\nvoid main() {{\n    {}\n}}\n", draw)),
+    }
+    Ok(text)
+}
+
+/// `value` as the shortest `f64` that reads back as it, `0.97` rather than `0.9700000286102295`.
+fn widen(value: f32) -> f64 {
+    value.to_string().parse().unwrap_or(f64::from(value))
+}
+
+/// What `#pragma @name` says about an input.
+#[derive(Default, Serialize)]
+struct PragmaInfo<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    range: Option<[f32; 2]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    widget: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    coords: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<&'a str>,
+}
+
+/// GLSL for an input: a uniform, its pragma, and for flags a define turning an int into a bool.
+fn declaration(input: &IsfInput) -> String {
+    let name = &input.name;
+    let number = |value: &Option<IsfValue>| value.as_ref().and_then(IsfValue::number);
+    let range = |min: f64, max: f64| {
+        Some([
+            number(&input.min).unwrap_or(min) as f32,
+            number(&input.max).unwrap_or(max) as f32,
+        ])
+    };
+    let mut pragma = PragmaInfo {
+        label: input.label.as_deref(),
+        ..Default::default()
+    };
+    let uniform = match input.kind.as_str() {
+        "float" => {
+            pragma.range = range(0.0, 1.0);
+            let default = number(&input.default).unwrap_or_default() as f32;
+            format!("uniform float {} = {:?};", name, default)
+        }
+        "long" => {
+            let min = input.values.iter().copied().reduce(f64::min).unwrap_or(0.0);
+            let max = input.values.iter().copied().reduce(f64::max).unwrap_or(1.0);
+            pragma.range = range(min, max);
+            let default = number(&input.default).unwrap_or(min) as i32;
+            format!("uniform int {} = {};", name, default)
+        }
+        "bool" | "event" => {
+            let default = number(&input.default).unwrap_or_default() as i32;
+            let label = input.label.as_deref().unwrap_or(name);
+            let pragma = PragmaInfo {
+                range: Some([0.0, 1.0]),
+                label: Some(label),
+                ..Default::default()
+            };
+            return format!(
+                "uniform int isf_{name} = {default};\n#pragma @isf_{name} {}\n\
+                 #define {name} (isf_{name} != 0)\n",
+                pragma_json(&pragma)
+            );
+        }
+        "color" => {
+            pragma.widget = Some("color");
+            let [r, g, b, a] = input
+                .default
+                .as_ref()
+                .map_or([0.0, 0.0, 0.0, 1.0], IsfValue::vector);
+            format!(
+                "uniform vec4 {} = vec4({:?}, {:?}, {:?}, {:?});",
+                name, r, g, b, a
+            )
+        }
+        "point2D" => {
+            pragma.widget = Some("point");
+            pragma.coords = Some("pixels");
+            if let (Some(min), Some(max)) = (&input.min, &input.max) {
+                let ([min_x, min_y], [max_x, max_y]) = (min.vector(), max.vector());
+                pragma.range = Some([min_x.min(min_y), max_x.max(max_y)]);
+            }
+            let [x, y] = input.default.as_ref().map_or([0.0; 2], IsfValue::vector);
+            format!("uniform vec2 {} = vec2({:?}, {:?});", name, x, y)
+        }
+        "image" | "audio" | "audioFFT" => {
+            if input.kind != "image" {
+                log::info!(
+                    "ISF {} input {} is fed from its sampler slot like an image",
+                    input.kind,
+                    name
+                );
+            }
+            format!("uniform sampler2D {};", name)
+        }
+        kind => {
+            log::warn!("Unsupported ISF input {} of type {}", name, kind);
+            return String::new();
+        }
+    };
+    let pragma_json = pragma_json(&pragma);
+    if pragma_json == "{}" {
+        format!("{}\n", uniform)
+    } else {
+        format!("{}\n#pragma @{} {}\n", uniform, name, pragma_json)
+    }
+}
+
+fn pragma_json(pragma: &PragmaInfo<'_>) -> String {
+    serde_json5::to_string(pragma).unwrap_or_else(|_| "{}".to_owned())
+}
+
+/// Evaluate a pass size expression like `floor($WIDTH / 2.0)`.
+fn eval_size(expression: &str, width: f64, height: f64) -> Option<f64> {
+    let expression = expression
+        .replace("$WIDTH", &format!("({})", width))
+        .replace("$HEIGHT", &format!("({})", height));
+    let mut parser = SizeExpression {
+        chars: expression.chars().filter(|c| !c.is_whitespace()).collect(),
+        at: 0,
+    };
+    let value = parser.sum()?;
+    (parser.at == parser.chars.len() && value.is_finite()).then_some(value)
+}
+
+/// Recursive descent over arithmetic with a few functions.
+struct SizeExpression {
+    chars: Vec<char>,
+    at: usize,
+}
+
+impl SizeExpression {
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.chars.get(self.at) == Some(&c);
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    fn sum(&mut self) -> Option<f64> {
+        let mut value = self.product()?;
+        loop {
+            if self.eat('+') {
+                value += self.product()?;
+            } else if self.eat('-') {
+                value -= self.product()?;
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Option<f64> {
+        let mut value = self.factor()?;
+        loop {
+            if self.eat('*') {
+                value *= self.factor()?;
+            } else if self.eat('/') {
+                value /= self.factor()?;
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Option<f64> {
+        if self.eat('-') {
+            return Some(-self.factor()?);
+        }
+        if self.eat('(') {
+            let value = self.sum()?;
+            return self.eat(')').then_some(value);
+        }
+        let start = self.at;
+        while self
+            .chars
+            .get(self.at)
+            .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '.' || *c == '_')
+        {
+            self.at += 1;
+        }
+        let word: String = self.chars[start..self.at].iter().collect();
+        if !self.eat('(') {
+            return word.parse().ok();
+        }
+        let mut arguments = vec![self.sum()?];
+        while self.eat(',') {
+            arguments.push(self.sum()?);
+        }
+        if !self.eat(')') {
+            return None;
+        }
+        match (word.as_str(), arguments.as_slice()) {
+            ("floor", [x]) => Some(x.floor()),
+            ("ceil", [x]) => Some(x.ceil()),
+            ("round", [x]) => Some(x.round()),
+            ("abs", [x]) => Some(x.abs()),
+            ("sqrt", [x]) => Some(x.sqrt()),
+            ("min", [x, y]) => Some(x.min(*y)),
+            ("max", [x, y]) => Some(x.max(*y)),
+            ("pow", [x, y]) => Some(x.powf(*y)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shader_parser::{
+        FloatUniformSpec, IntUniformSpec, PointCoords, UniformInfo, Vec4UniformSpec,
+        preparse_shader,
+    };
+
+    const ISF: &str = r#"/*{
+    "DESCRIPTION": "Test",
+    "INPUTS": [
+        {"NAME": "speed", "TYPE": "float", "DEFAULT": 0.5, "MIN": 0.0, "MAX": 2.0, "LABEL": "Speed"},
+        {"NAME": "count", "TYPE": "long", "VALUES": [1, 2, 3], "DEFAULT": 2},
+        {"NAME": "invert", "TYPE": "bool", "DEFAULT": true},
+        {"NAME": "tint", "TYPE": "color", "DEFAULT": [1.0, 0.5, 0.25, 1.0]},
+        {"NAME": "center", "TYPE": "point2D", "DEFAULT": [10, 20], "MIN": [0, 0], "MAX": [100, 200]},
+        {"NAME": "inputImage", "TYPE": "image"},
+        {"NAME": "sky", "TYPE": "cube"}
+    ],
+    "PASSES": [
+        {"TARGET": "halfSize", "WIDTH": "floor($WIDTH / 2.0)", "HEIGHT": "$HEIGHT / 2"},
+        {}
+    ]
+}*/
+void main() {
+    gl_FragColor = vec4(speed);
+}
+"#;
+
+    const SHADERTOY: &str = "// Glow
+#pragma @gain {range: [0, 4], label: \"Gain\"}
+uniform float gain = 1.5;
+uniform int steps = 3;
+uniform vec2 offset = vec2(0.25, 0.5);
+uniform vec3 glow_color = vec3(1.0, 0.5, 0.0);
+uniform sampler2D image;
+#pragma @prev {source: \"feedback\"}
+uniform sampler2D prev;
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy + offset;
+    fragColor = texture(image, uv) * gain + texture(prev, uv) + vec4(glow_color, 1.0) * iTime;
+}
+";
+
+    fn uniform<'a>(uniforms: &'a [UniformInfo], name: &str) -> &'a UniformInfo {
+        uniforms
+            .iter()
+            .find(|u| u.name == name)
+            .unwrap_or_else(|| panic!("no uniform {}", name))
+    }
+
+    #[test]
+    fn inputs_become_uniforms_with_pragmas() {
+        let expanded = expand(ISF);
+        assert!(expanded.ends_with(&format!("#line 1 1\n{}", ISF)));
+        assert!(expanded.contains("#define invert (isf_invert != 0)\n"));
+        let uniforms = preparse_shader(&expanded).unwrap().uniforms;
+        let names: Vec<&str> = uniforms.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "speed",
+                "count",
+                "isf_invert",
+                "tint",
+                "center",
+                "inputImage",
+                "halfSize"
+            ]
+        );
+
+        let speed = uniform(&uniforms, "speed");
+        assert_eq!(
+            speed.spec,
+            UniformSpec::Float(FloatUniformSpec { default: Some(0.5) })
+        );
+        assert_eq!(speed.range, 0.0..=2.0);
+        assert_eq!(speed.label.as_deref(), Some("Speed"));
+
+        let count = uniform(&uniforms, "count");
+        assert_eq!(
+            count.spec,
+            UniformSpec::Int(IntUniformSpec { default: Some(2) })
+        );
+        assert_eq!(count.range, 1.0..=3.0);
+
+        let invert = uniform(&uniforms, "isf_invert");
+        assert_eq!(
+            invert.spec,
+            UniformSpec::Int(IntUniformSpec { default: Some(1) })
+        );
+        assert_eq!(invert.label.as_deref(), Some("invert"));
+
+        let tint = uniform(&uniforms, "tint");
+        assert_eq!(
+            tint.spec,
+            UniformSpec::Vec4(Vec4UniformSpec {
+                default: Some([1.0, 0.5, 0.25, 1.0])
+            })
+        );
+        assert_eq!(tint.widget, UniformWidget::Color);
+
+        let center = uniform(&uniforms, "center");
+        assert_eq!(center.widget, UniformWidget::Point);
+        assert_eq!(center.coords, PointCoords::Pixels);
+        assert_eq!(center.range, 0.0..=200.0);
+
+        assert_eq!(
+            uniform(&uniforms, "inputImage").spec,
+            UniformSpec::Sampler2D
+        );
+        assert_eq!(uniform(&uniforms, "halfSize").spec, UniformSpec::Sampler2D);
+    }
+
+    #[test]
+    fn unsupported_inputs_are_left_out() {
+        let source = r#"/*{"INPUTS": [
+            {"NAME": "sky", "TYPE": "cube"},
+            {"NAME": "caption", "TYPE": "text"},
+            {"NAME": "level", "TYPE": "float"}
+        ]}*/
+void main() {}
+"#;
+        assert_eq!(
+            expand(source),
+            format!(
+                "uniform float level = 0.0;\n#pragma @level {{\"range\":[0,1]}}\n#line 1 1\n{}",
+                source
+            )
+        );
+    }
+
+    #[test]
+    fn only_a_json_comment_is_a_header() {
+        assert!(has_header(ISF));
+        assert!(!has_header("/* Not JSON */\nvoid main() {}"));
+        assert!(!has_header("void main() {}"));
+        // A header that doesn't parse leaves the source as it is.
+        let broken = "/*{\"INPUTS\": [}*/\nvoid main() {}";
+        assert!(has_header(broken));
+        assert_eq!(expand(broken), broken);
+        assert!(passes(broken).is_empty());
+    }
+
+    #[test]
+    fn passes_are_sized_from_their_expressions() {
+        let passes = passes(ISF);
+        assert_eq!(passes.len(), 2);
+        assert_eq!(passes[0].target.as_deref(), Some("halfSize"));
+        assert_eq!(passes[0].size(1921, 1080), (960, 540));
+        assert_eq!(passes[1].target, None);
+        assert_eq!(passes[1].size(1921, 1080), (1921, 1080));
+    }
+
+    #[test]
+    fn evaluates_size_expressions() {
+        assert_eq!(eval_size("$WIDTH * 0.25 + 1", 800.0, 600.0), Some(201.0));
+        assert_eq!(
+            eval_size("max($WIDTH, 2.0 * $HEIGHT)", 800.0, 600.0),
+            Some(1200.0)
+        );
+        assert_eq!(eval_size("-(2 - pow(2.0, 3.0))", 0.0, 0.0), Some(6.0));
+        assert_eq!(eval_size("$WIDTH /", 800.0, 600.0), None);
+        assert_eq!(eval_size("log($WIDTH)", 800.0, 600.0), None);
+        assert_eq!(eval_size("1 / 0", 800.0, 600.0), None);
+    }
+
+    #[test]
+    fn export_maps_uniforms_to_inputs() {
+        let ppr = preparse_shader(SHADERTOY).unwrap();
+        let exported = export(SHADERTOY, &ppr, PreludeMode::Shadertoy).unwrap();
+        let header = parse_header(&exported).unwrap().unwrap();
+        assert_eq!(header.description.as_deref(), Some("Glow"));
+        let inputs: Vec<(&str, &str)> = header
+            .inputs
+            .iter()
+            .map(|input| (input.name.as_str(), input.kind.as_str()))
+            .collect();
+        assert_eq!(
+            inputs,
+            [
+                ("gain", "float"),
+                ("steps", "long"),
+                ("offset", "point2D"),
+                ("glow_color_rgba", "color"),
+                ("image", "image"),
+            ]
+        );
+        // The feedback sampler is fed by a persistent pass rather than an input.
+        assert_eq!(header.passes.len(), 2);
+        assert_eq!(header.passes[0].target.as_deref(), Some("prev"));
+        assert!(exported.contains("gl_FragColor = IMG_THIS_PIXEL(prev);"));
+
+        assert!(exported.contains("#define glow_color glow_color_rgba.rgb\n"));
+        assert!(exported.contains("#define iTime TIME\n"));
+        assert!(!exported.contains("#define iMouse"));
+        assert!(!exported.contains("#pragma"));
+        assert!(!exported.lines().any(|line| line.starts_with("uniform")));
+    }
+
+    #[test]
+    fn export_reads_back_as_isf() {
+        let ppr = preparse_shader(SHADERTOY).unwrap();
+        let exported = export(SHADERTOY, &ppr, PreludeMode::Shadertoy).unwrap();
+        assert_eq!(PreludeMode::detect(&exported, None), PreludeMode::Isf);
+        let mut ppr = preparse_shader(&expand(&exported)).unwrap();
+        PreludeMode::Isf.adapt_preparse(&exported, &mut ppr);
+        let gain = uniform(&ppr.uniforms, "gain");
+        assert_eq!(gain.range, 0.0..=4.0);
+        assert_eq!(gain.label.as_deref(), Some("Gain"));
+        assert_eq!(
+            uniform(&ppr.uniforms, "glow_color_rgba").spec,
+            UniformSpec::Vec4(Vec4UniformSpec {
+                default: Some([1.0, 0.5, 0.0, 1.0])
+            })
+        );
+        assert_eq!(
+            uniform(&ppr.uniforms, "prev").source,
+            Some(UniformSource::Pass)
+        );
+    }
+
+    #[test]
+    fn export_takes_only_shadertoy_shaders() {
+        let source = "uniform float time;\nvoid main() { gl_FragColor = vec4(time); }\n";
+        let ppr = preparse_shader(source).unwrap();
+        assert!(export(source, &ppr, PreludeMode::GlslSandbox).is_err());
+    }
+}
@@ -0,0 +1,25 @@
+precision highp float;
+
+uniform vec4 iViewport;
+
+// Relative to the shader's viewport rather than the window.
+#define varjostin_FragCoord (gl_FragCoord - vec4(iViewport.xy, 0.0, 0.0))
+
+// Stands in for `gl_FragColor`, which newer GLSL versions don't have.
+out vec4 varjostin_FragColor;
+
+uniform vec2 RENDERSIZE;
+uniform float TIME;
+uniform float TIMEDELTA;
+uniform int FRAMEINDEX;
+uniform int PASSINDEX;
+uniform vec4 DATE;
+
+#define isf_FragNormCoord (varjostin_FragCoord.xy / RENDERSIZE)
+#define vv_FragNormCoord isf_FragNormCoord
+
+#define IMG_SIZE(image) vec2(textureSize(image, 0))
+#define IMG_NORM_PIXEL(image, coord) texture(image, coord)
+#define IMG_PIXEL(image, coord) texture(image, (coord) / IMG_SIZE(image))
+#define IMG_THIS_NORM_PIXEL(image) texture(image, isf_FragNormCoord)
+#define IMG_THIS_PIXEL(image) texture(image, isf_FragNormCoord)
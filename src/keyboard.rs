@@ -0,0 +1,126 @@
+use crate::textures::DataImage;
+use egui::{Event, Key, Modifiers};
+
+const KEY_COUNT: usize = 256;
+
+/// Shadertoy-style keyboard state: held, pressed this frame and toggled, indexed by JavaScript keycode.
+pub struct Keyboard {
+    held: [bool; KEY_COUNT],
+    pressed: [bool; KEY_COUNT],
+    toggled: [bool; KEY_COUNT],
+}
+
+impl Default for Keyboard {
+    fn default() -> Self {
+        Self {
+            held: [false; KEY_COUNT],
+            pressed: [false; KEY_COUNT],
+            toggled: [false; KEY_COUNT],
+        }
+    }
+}
+
+impl Keyboard {
+    /// Process this frame's input; events are only consumed while the viewport has focus.
+    pub fn update(&mut self, ctx: &egui::Context, focused: bool) {
+        self.pressed = [false; KEY_COUNT];
+        if !focused {
+            self.held = [false; KEY_COUNT];
+            return;
+        }
+        ctx.input(|i| {
+            for event in &i.events {
+                if let Event::Key {
+                    key,
+                    pressed,
+                    repeat,
+                    ..
+                } = event
+                    && let Some(code) = js_keycode(*key)
+                {
+                    self.set(code as usize, *pressed, *repeat);
+                }
+            }
+            self.set_modifiers(i.modifiers);
+        });
+    }
+
+    fn set(&mut self, code: usize, down: bool, repeat: bool) {
+        if down && !repeat && !self.held[code] {
+            self.pressed[code] = true;
+            self.toggled[code] = !self.toggled[code];
+        }
+        self.held[code] = down;
+    }
+
+    /// egui reports modifiers as state rather than key events.
+    fn set_modifiers(&mut self, modifiers: Modifiers) {
+        for (code, down) in [
+            (16, modifiers.shift),
+            (17, modifiers.ctrl),
+            (18, modifiers.alt),
+            (91, modifiers.mac_cmd),
+        ] {
+            self.set(code, down, false);
+        }
+    }
+
+    pub fn image(&self) -> DataImage {
+        let mut image = DataImage::new(KEY_COUNT, 3);
+        // Shadertoy's keyboard texture isn't interpolated, so `texture()` reads single keys too.
+        image.nearest = true;
+        for (row, states) in [&self.held, &self.pressed, &self.toggled]
+            .into_iter()
+            .enumerate()
+        {
+            for (code, state) in states.iter().enumerate() {
+                let v = if *state { 255 } else { 0 };
+                image.set_pixel(code, row, [v, v, v, 255]);
+            }
+        }
+        image
+    }
+}
+
+/// Map an egui key to the JavaScript `keyCode` Shadertoy shaders expect.
+fn js_keycode(key: Key) -> Option<u8> {
+    use Key::*;
+    let code = match key {
+        Backspace => 8,
+        Tab => 9,
+        Enter => 13,
+        Escape => 27,
+        Space => 32,
+        PageUp => 33,
+        PageDown => 34,
+        End => 35,
+        Home => 36,
+        ArrowLeft => 37,
+        ArrowUp => 38,
+        ArrowRight => 39,
+        ArrowDown => 40,
+        Insert => 45,
+        Delete => 46,
+        Num0 | Num1 | Num2 | Num3 | Num4 | Num5 | Num6 | Num7 | Num8 | Num9 => {
+            48 + (key as u8 - Num0 as u8)
+        }
+        A | B | C | D | E | F | G | H | I | J | K | L | M | N | O | P | Q | R | S | T | U | V
+        | W | X | Y | Z => 65 + (key as u8 - A as u8),
+        F1 | F2 | F3 | F4 | F5 | F6 | F7 | F8 | F9 | F10 | F11 | F12 => {
+            112 + (key as u8 - F1 as u8)
+        }
+        Semicolon | Colon => 186,
+        Equals | Plus => 187,
+        Comma => 188,
+        Minus => 189,
+        Period => 190,
+        Slash | Questionmark => 191,
+        Backtick => 192,
+        OpenBracket | OpenCurlyBracket => 219,
+        Backslash | Pipe => 220,
+        CloseBracket | CloseCurlyBracket => 221,
+        Quote => 222,
+        _ => return None,
+    };
+    Some(code)
+}
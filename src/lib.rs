@@ -1,15 +1,53 @@
 #![warn(clippy::all, rust_2018_idioms)]
 mod app;
+mod audio;
+mod beat_clock;
+mod calendar;
+mod camera;
+mod clock;
+mod config;
+mod defines;
+mod editor;
+mod file_browser;
 mod file_change;
 mod file_collection;
+mod file_watcher;
 mod frame_history;
+mod gallery;
 mod gl;
+mod introspect;
+mod isf;
+mod keyboard;
 mod label_strip;
+mod log_console;
+mod mouse;
+mod new_shader;
 mod options;
+mod playback;
+mod playlist;
+mod point_gizmos;
+mod prelude;
+mod ramp_editor;
+mod ramps;
+mod render_target;
+mod screenshot;
+mod session;
 mod shader_frame;
 mod shader_parser;
+mod shadertoy_export;
+mod shortcuts;
+mod sound;
+mod sound_window;
 mod textures;
+mod thumbnail;
+mod time_controls;
 mod uniforms_box;
 mod uniforms_values;
+mod viewport_nav;
+mod warp;
+mod warp_pass;
 
 pub use app::{Options, VarjostinApp};
+pub use config::Config;
+pub use log_console::init_logging;
+pub use session::Session;
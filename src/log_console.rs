@@ -0,0 +1,253 @@
+use egui::{Color32, Context, RichText};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Oldest records are dropped beyond this.
+const MAX_RECORDS: usize = 2000;
+/// How far back a record repeated e.g. every frame or every compile is looked for.
+const REPEAT_WINDOW: usize = 16;
+
+static RECORDS: Mutex<VecDeque<LogRecord>> = Mutex::new(VecDeque::new());
+static NEXT_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static SHADER: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+#[derive(Clone)]
+struct LogRecord {
+    level: Level,
+    message: String,
+    /// The shader being worked on when the record was logged.
+    shader: Option<PathBuf>,
+    /// Times the same record was logged in a row, give or take a few others.
+    count: usize,
+    /// Sequence number of the latest time it was logged.
+    sequence: u64,
+}
+
+/// Sends records to `env_logger` (stderr, filtered by `RUST_LOG`) and keeps ours, and warnings
+/// from anywhere, for the log console.
+struct ConsoleLogger {
+    stderr: env_logger::Logger,
+}
+
+impl ConsoleLogger {
+    fn keeps(metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= Level::Warn
+            || (metadata.level() <= Level::Debug && metadata.target().starts_with("varjostin"))
+    }
+}
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        Self::keeps(metadata) || self.stderr.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        self.stderr.log(record);
+        if !Self::keeps(record.metadata()) {
+            return;
+        }
+        let record = LogRecord {
+            level: record.level(),
+            message: record.args().to_string(),
+            shader: SHADER.with_borrow(Clone::clone),
+            count: 1,
+            sequence: NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed),
+        };
+        let mut records = RECORDS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(repeated) = records.iter_mut().rev().take(REPEAT_WINDOW).find(|r| {
+            r.level == record.level && r.shader == record.shader && r.message == record.message
+        }) {
+            repeated.count += 1;
+            repeated.sequence = record.sequence;
+            return;
+        }
+        if records.len() >= MAX_RECORDS {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+    }
+}
+
+/// Log to stderr and the log console. Without `RUST_LOG`, our info messages go to stderr too.
+pub fn init_logging() {
+    let stderr = env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or("varjostin=info"),
+    )
+    .build();
+    let max_level = stderr.filter().max(LevelFilter::Debug);
+    if log::set_boxed_logger(Box::new(ConsoleLogger { stderr })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// Tags records logged on this thread with a shader until dropped.
+pub(crate) struct ShaderScope(Option<PathBuf>);
+
+pub(crate) fn shader_scope(shader: Option<&Path>) -> ShaderScope {
+    ShaderScope(SHADER.replace(shader.map(Path::to_path_buf)))
+}
+
+impl Drop for ShaderScope {
+    fn drop(&mut self) {
+        SHADER.set(self.0.take());
+    }
+}
+
+/// Where records logged from now on will start; see `warning_count`.
+pub(crate) fn next_sequence() -> u64 {
+    NEXT_SEQUENCE.load(Ordering::Relaxed)
+}
+
+/// Warnings and errors logged about `shader` since `next_sequence` returned `since`.
+pub(crate) fn warning_count(shader: &Path, since: u64) -> usize {
+    let records = RECORDS.lock().unwrap_or_else(|e| e.into_inner());
+    records
+        .iter()
+        .filter(|r| {
+            r.level <= Level::Warn && r.sequence >= since && r.shader.as_deref() == Some(shader)
+        })
+        .count()
+}
+
+fn level_color(level: Level) -> Color32 {
+    match level {
+        Level::Error => Color32::RED,
+        Level::Warn => Color32::YELLOW,
+        Level::Info => Color32::LIGHT_GRAY,
+        Level::Debug | Level::Trace => Color32::GRAY,
+    }
+}
+
+/// Window listing log records by shader.
+pub struct LogConsole {
+    pub open: bool,
+    level: LevelFilter,
+}
+
+impl Default for LogConsole {
+    fn default() -> Self {
+        Self {
+            open: false,
+            level: LevelFilter::Info,
+        }
+    }
+}
+
+impl LogConsole {
+    pub fn show(&mut self, ctx: &Context, current_shader: Option<&Path>) {
+        let records: Vec<LogRecord> = RECORDS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|r| r.level <= self.level)
+            .cloned()
+            .collect();
+        // Records without a shader first, then each shader's in order of their first record.
+        let mut groups: Vec<(Option<&Path>, Vec<&LogRecord>)> = vec![(None, Vec::new())];
+        for record in &records {
+            let shader = record.shader.as_deref();
+            match groups.iter_mut().find(|(s, _)| *s == shader) {
+                Some((_, group)) => group.push(record),
+                None => groups.push((shader, vec![record])),
+            }
+        }
+        let mut clear = false;
+        let mut open = self.open;
+        egui::Window::new("Log")
+            .open(&mut open)
+            .default_size([560.0, 320.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::new("log_level", "")
+                        .selected_text(self.level.as_str())
+                        .show_ui(ui, |ui| {
+                            for level in [
+                                LevelFilter::Error,
+                                LevelFilter::Warn,
+                                LevelFilter::Info,
+                                LevelFilter::Debug,
+                            ] {
+                                ui.selectable_value(&mut self.level, level, level.as_str());
+                            }
+                        });
+                    if ui.button("Clear").clicked() {
+                        clear = true;
+                    }
+                    if ui
+                        .button("Copy")
+                        .on_hover_text("Copy the shown records to the clipboard")
+                        .clicked()
+                    {
+                        ctx.copy_text(copy_text(&groups));
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .auto_shrink(false)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for (shader, group) in &groups {
+                            if group.is_empty() {
+                                continue;
+                            }
+                            let title = match shader {
+                                Some(path) => path.to_string_lossy().into_owned(),
+                                None => "General".to_owned(),
+                            };
+                            egui::CollapsingHeader::new(format!("{} ({})", title, group.len()))
+                                .id_salt(shader)
+                                .default_open(shader.is_none() || *shader == current_shader)
+                                .show(ui, |ui| {
+                                    for record in group {
+                                        let repeats = match record.count {
+                                            1 => String::new(),
+                                            n => format!(" (×{})", n),
+                                        };
+                                        ui.label(
+                                            RichText::new(format!(
+                                                "{:5} {}{}",
+                                                record.level, record.message, repeats
+                                            ))
+                                            .monospace()
+                                            .color(level_color(record.level)),
+                                        );
+                                    }
+                                });
+                        }
+                    });
+            });
+        self.open = open;
+        if clear {
+            RECORDS.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+    }
+}
+
+fn copy_text(groups: &[(Option<&Path>, Vec<&LogRecord>)]) -> String {
+    let mut text = String::new();
+    for (shader, group) in groups {
+        for record in group {
+            match shader {
+                Some(path) => text.push_str(&format!(
+                    "{} {}: {}\n",
+                    record.level,
+                    path.display(),
+                    record.message
+                )),
+                None => text.push_str(&format!("{} {}\n", record.level, record.message)),
+            }
+        }
+    }
+    text
+}
@@ -2,22 +2,34 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
 use clap::Parser;
-use varjostin::{Options, VarjostinApp};
+use varjostin::{Config, Options, Session, VarjostinApp, init_logging};
 
 fn main() -> eframe::Result {
-    env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
+    init_logging(); // Log to stderr (more with `RUST_LOG=debug`) and the log window.
     let options = Options::parse();
+    let config = Config::load();
+    let session = Session::load();
 
+    // The configured size is for when there's no session to restore the window from.
+    let size = session
+        .window
+        .map(|window| window.size)
+        .or(config.window_size)
+        .unwrap_or([1280.0, 720.0]);
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size(size)
+        .with_min_inner_size([300.0, 220.0]);
+    if let Some(position) = session.window.and_then(|window| window.position) {
+        viewport = viewport.with_position(position);
+    }
     let native_options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([1280.0, 720.0])
-            .with_min_inner_size([300.0, 220.0]),
+        viewport,
         vsync: options.vsync,
         ..Default::default()
     };
     eframe::run_native(
         "Varjostin",
         native_options,
-        Box::new(|cc| Ok(Box::new(VarjostinApp::new(cc, options)))),
+        Box::new(|cc| Ok(Box::new(VarjostinApp::new(cc, options, config, session)))),
     )
 }
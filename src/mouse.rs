@@ -0,0 +1,91 @@
+use egui::{PointerButton, Rect, Response, Ui};
+
+/// Shadertoy-style mouse state for the viewport, in pixels from its bottom-left corner.
+#[derive(Default)]
+pub struct Mouse {
+    /// Last position while the primary button was held on the viewport.
+    pub position: [f32; 2],
+    /// Where the primary button last went down.
+    pub click: [f32; 2],
+    pub down: bool,
+    /// Whether the primary button went down this frame.
+    pub clicked: bool,
+    /// Scroll accumulated over the viewport, in pixels.
+    pub wheel: [f32; 2],
+    /// Bitmask of buttons held on the viewport: 1 primary, 2 secondary, 4 middle.
+    pub buttons: i32,
+    down_since: f64,
+}
+
+impl Mouse {
+    /// Follow the pointer over the viewport; with `camera_driven`, the primary button and the
+    /// wheel move the camera instead and leave the state as it was.
+    pub fn update(
+        &mut self,
+        ui: &Ui,
+        response: &Response,
+        rect: Rect,
+        time: f64,
+        camera_driven: bool,
+    ) {
+        let pixels_per_point = ui.ctx().pixels_per_point();
+        let held_on_viewport = response.is_pointer_button_down_on();
+        let (primary, secondary, middle, scroll) = ui.input(|i| {
+            (
+                i.pointer.button_down(PointerButton::Primary) && !camera_driven,
+                i.pointer.button_down(PointerButton::Secondary),
+                i.pointer.button_down(PointerButton::Middle),
+                i.smooth_scroll_delta,
+            )
+        });
+        self.buttons = if held_on_viewport {
+            primary as i32 | (secondary as i32) << 1 | (middle as i32) << 2
+        } else {
+            0
+        };
+        let down = held_on_viewport && primary;
+        self.clicked = down && !self.down;
+        self.down = down;
+        if down && let Some(pos) = response.interact_pointer_pos() {
+            self.position = [
+                (pos.x - rect.left()) * pixels_per_point,
+                (rect.bottom() - pos.y) * pixels_per_point,
+            ];
+            if self.clicked {
+                self.click = self.position;
+                self.down_since = time;
+            }
+        }
+        if response.hovered() && !camera_driven {
+            self.wheel[0] += scroll.x * pixels_per_point;
+            self.wheel[1] += scroll.y * pixels_per_point;
+        }
+    }
+
+    /// `iMouse`: xy while dragging, zw the click origin, negated when the button is up
+    /// (z) or wasn't pressed this frame (w).
+    pub fn uniform(&self) -> [f32; 4] {
+        [
+            self.position[0],
+            self.position[1],
+            if self.down {
+                self.click[0]
+            } else {
+                -self.click[0]
+            },
+            if self.clicked {
+                self.click[1]
+            } else {
+                -self.click[1]
+            },
+        ]
+    }
+
+    pub fn down_seconds(&self, time: f64) -> f32 {
+        if self.down {
+            (time - self.down_since).max(0.0) as f32
+        } else {
+            0.0
+        }
+    }
+}
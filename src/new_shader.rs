@@ -0,0 +1,135 @@
+use crate::calendar::civil_from_days;
+use egui::{Context, RichText};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+struct Template {
+    name: &'static str,
+    source: &'static str,
+}
+
+const TEMPLATES: [Template; 4] = [
+    Template {
+        name: "Blank",
+        source: include_str!("templates/blank.glsl"),
+    },
+    Template {
+        name: "Raymarcher",
+        source: include_str!("templates/raymarcher.glsl"),
+    },
+    Template {
+        name: "2D SDF",
+        source: include_str!("templates/sdf_2d.glsl"),
+    },
+    Template {
+        name: "Feedback",
+        source: include_str!("templates/feedback.glsl"),
+    },
+];
+
+/// Creates shader files from templates.
+#[derive(Default)]
+pub struct NewShaderWindow {
+    pub open: bool,
+    name: String,
+    template: usize,
+    dir: usize,
+    error: Option<String>,
+}
+
+impl NewShaderWindow {
+    /// Returns the path of the shader file when one was created.
+    pub fn show(&mut self, ctx: &Context, dirs: &[PathBuf]) -> Option<PathBuf> {
+        let mut created = None;
+        let mut open = self.open;
+        egui::Window::new("New shader")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Name");
+                    ui.text_edit_singleline(&mut self.name);
+                });
+                egui::ComboBox::new("new_shader_template", "Template")
+                    .selected_text(TEMPLATES[self.template].name)
+                    .show_ui(ui, |ui| {
+                        for (index, template) in TEMPLATES.iter().enumerate() {
+                            ui.selectable_value(&mut self.template, index, template.name);
+                        }
+                    });
+                if dirs.len() > 1 {
+                    self.dir = self.dir.min(dirs.len() - 1);
+                    egui::ComboBox::new("new_shader_dir", "Directory")
+                        .selected_text(dirs[self.dir].to_string_lossy())
+                        .show_ui(ui, |ui| {
+                            for (index, dir) in dirs.iter().enumerate() {
+                                ui.selectable_value(&mut self.dir, index, dir.to_string_lossy());
+                            }
+                        });
+                }
+                let name = self.name.trim();
+                if ui
+                    .add_enabled(
+                        !name.is_empty() && !dirs.is_empty(),
+                        egui::Button::new("Create"),
+                    )
+                    .clicked()
+                {
+                    let dir = &dirs[self.dir.min(dirs.len() - 1)];
+                    match create_shader(dir, name, &TEMPLATES[self.template]) {
+                        Ok(path) => {
+                            self.error = None;
+                            self.name.clear();
+                            created = Some(path);
+                        }
+                        Err(e) => self.error = Some(e.to_string()),
+                    }
+                }
+                if let Some(e) = &self.error {
+                    ui.label(RichText::new(e).color(egui::Color32::RED));
+                }
+            });
+        self.open = open && created.is_none();
+        created
+    }
+}
+
+/// Write `template` with a header to `<dir>/<name>.glsl`, never overwriting an existing file.
+fn create_shader(dir: &Path, name: &str, template: &Template) -> eyre::Result<PathBuf> {
+    let file_name = if name.ends_with(".glsl") {
+        name.to_owned()
+    } else {
+        format!("{}.glsl", name)
+    };
+    if file_name.contains(['/', '\\']) {
+        eyre::bail!("The name can't contain path separators");
+    }
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(file_name);
+    let header = format!(
+        "// {}\n// Created {} from the {} template.\n\n",
+        name.trim_end_matches(".glsl"),
+        today(),
+        template.name
+    );
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| eyre::eyre!("Can't create {}: {}", path.display(), e))?;
+    std::io::Write::write_all(
+        &mut file,
+        format!("{}{}", header, template.source).as_bytes(),
+    )?;
+    Ok(path)
+}
+
+/// Today's UTC date as `YYYY-MM-DD`.
+fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
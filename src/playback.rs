@@ -0,0 +1,119 @@
+use crate::audio::AudioTrack;
+use crate::clock::Clock;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample};
+use std::sync::{Arc, Mutex};
+
+/// How far playback may drift from the virtual clock, in seconds, before it's moved back onto it.
+const MAX_DRIFT: f64 = 0.1;
+
+/// What the output callback plays, shared with the UI thread.
+#[derive(Default)]
+struct Transport {
+    track: Option<Arc<AudioTrack>>,
+    /// Position in the track in seconds, as on the virtual clock.
+    time: f64,
+    speed: f64,
+    playing: bool,
+    loop_range: Option<[f64; 2]>,
+}
+
+impl Transport {
+    fn fill<T: SizedSample + FromSample<f32>>(
+        &mut self,
+        data: &mut [T],
+        channels: usize,
+        sample_rate: f64,
+    ) {
+        for frame in data.chunks_mut(channels) {
+            match &self.track {
+                Some(track) if self.playing => {
+                    for (channel, sample) in frame.iter_mut().enumerate() {
+                        *sample = T::from_sample(track.play_sample(self.time, channel));
+                    }
+                    self.time += self.speed / sample_rate;
+                    if let Some([a, b]) = self.loop_range
+                        && b > a
+                        && (self.time >= b || self.time < a)
+                    {
+                        self.time = a + (self.time - a).rem_euclid(b - a);
+                    }
+                }
+                _ => frame.fill(T::EQUILIBRIUM),
+            }
+        }
+    }
+}
+
+/// Plays the audio feeding the shader in step with the virtual clock.
+#[derive(Default)]
+pub struct AudioPlayer {
+    transport: Arc<Mutex<Transport>>,
+    /// Opened when there's first something to play.
+    stream: Option<eyre::Result<cpal::Stream>>,
+}
+
+impl AudioPlayer {
+    /// Follow `clock`'s time, pausing, speed and loop with `track`; call once per frame.
+    pub fn sync(&mut self, clock: &Clock, track: Option<&Arc<AudioTrack>>) {
+        if track.is_some() && self.stream.is_none() {
+            let stream = open_stream(self.transport.clone());
+            if let Err(e) = &stream {
+                log::warn!("Audio playback is unavailable: {:?}", e);
+            }
+            self.stream = Some(stream);
+        }
+        let Ok(mut transport) = self.transport.lock() else {
+            return;
+        };
+        let same_track = match (&transport.track, track) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        // A fixed timestep doesn't follow the wall clock, so playback would only stutter.
+        let playing = clock.playing && !clock.fixed_timestep && track.is_some();
+        if !playing || !same_track || (transport.time - clock.time()).abs() > MAX_DRIFT {
+            transport.time = clock.time();
+        }
+        transport.track = track.cloned();
+        transport.speed = clock.speed as f64;
+        transport.playing = playing;
+        transport.loop_range = clock.looping.then_some(clock.loop_range);
+    }
+}
+
+fn open_stream(transport: Arc<Mutex<Transport>>) -> eyre::Result<cpal::Stream> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| eyre::eyre!("No audio output device"))?;
+    let supported = device.default_output_config()?;
+    let config = supported.config();
+    let stream = match supported.sample_format() {
+        SampleFormat::F32 => build_stream::<f32>(&device, &config, transport),
+        SampleFormat::I16 => build_stream::<i16>(&device, &config, transport),
+        SampleFormat::U16 => build_stream::<u16>(&device, &config, transport),
+        format => Err(eyre::eyre!("Unsupported sample format {:?}", format)),
+    }?;
+    stream.play()?;
+    Ok(stream)
+}
+
+fn build_stream<T: SizedSample + FromSample<f32>>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    transport: Arc<Mutex<Transport>>,
+) -> eyre::Result<cpal::Stream> {
+    let channels = config.channels.max(1) as usize;
+    let sample_rate = config.sample_rate.0 as f64;
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _| match transport.lock() {
+            Ok(mut transport) => transport.fill(data, channels, sample_rate),
+            Err(_) => data.fill(T::EQUILIBRIUM),
+        },
+        |e| log::error!("Audio playback error: {}", e),
+        None,
+    )?;
+    Ok(stream)
+}
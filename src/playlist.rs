@@ -0,0 +1,162 @@
+use crate::shader_frame::ShaderCompileResponse;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AdvanceUnit {
+    Seconds,
+    Beats,
+}
+
+/// The playlist entry compiled ahead of switching to it.
+pub struct PreparedEntry {
+    pub index: usize,
+    pub path: PathBuf,
+    /// The file as read, before defines were injected.
+    pub source: String,
+    /// Where its compile's log records start.
+    pub log_sequence: u64,
+    /// `None` until the compile result arrives.
+    pub response: Option<ShaderCompileResponse>,
+}
+
+/// Autopilot that cycles through shaders, from a playlist file or the shader collection.
+pub struct Playlist {
+    pub open: bool,
+    pub enabled: bool,
+    pub file: Option<PathBuf>,
+    file_entries: Vec<PathBuf>,
+    pub interval: f64,
+    pub unit: AdvanceUnit,
+    /// Transition length, in seconds.
+    pub transition_duration: f64,
+    /// gl-transitions.com style shader; crossfade if empty.
+    pub transition_shader: String,
+    /// Index of the entry playing now.
+    pub index: usize,
+    /// Clock position (in `unit`s) the current entry started at.
+    pub slot_start: Option<f64>,
+    /// Advance as soon as the next entry is ready.
+    pub skip: bool,
+    /// Entry picked to play next instead of the following one.
+    pub next: Option<usize>,
+    pub prepared: Option<PreparedEntry>,
+    pub inbox: mpsc::Receiver<ShaderCompileResponse>,
+    pub outbox: mpsc::Sender<ShaderCompileResponse>,
+}
+
+impl Playlist {
+    pub fn new(file: Option<&Path>) -> Self {
+        let (outbox, inbox) = mpsc::channel();
+        let file_entries = match file {
+            Some(file) => read_playlist(file).unwrap_or_else(|e| {
+                log::error!("Error reading playlist {:?}: {:?}", file, e);
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        Self {
+            open: false,
+            enabled: false,
+            file: file.map(Path::to_path_buf),
+            file_entries,
+            interval: 30.0,
+            unit: AdvanceUnit::Seconds,
+            transition_duration: 2.0,
+            transition_shader: String::new(),
+            index: 0,
+            slot_start: None,
+            skip: false,
+            next: None,
+            prepared: None,
+            inbox,
+            outbox,
+        }
+    }
+
+    /// The playlist file's entries, or else the given collection's.
+    pub fn entries(&self, collection: &[(String, PathBuf)]) -> Vec<PathBuf> {
+        if self.file.is_some() {
+            self.file_entries.clone()
+        } else {
+            collection.iter().map(|(_, path)| path.clone()).collect()
+        }
+    }
+
+    /// Start over from the current entry.
+    pub fn reset(&mut self) {
+        self.discard_prepared();
+        self.slot_start = None;
+    }
+
+    /// Forget the prepared entry, including a compile result that's still on its way.
+    fn discard_prepared(&mut self) {
+        self.prepared = None;
+        while self.inbox.try_recv().is_ok() {}
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, entries: &[PathBuf]) {
+        let mut open = self.open;
+        egui::Window::new("Playlist").open(&mut open).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.checkbox(&mut self.enabled, "Autopilot").changed() {
+                    self.reset();
+                }
+                if ui.button("Next").clicked() {
+                    self.skip = true;
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Every");
+                ui.add(
+                    egui::DragValue::new(&mut self.interval)
+                        .range(1.0..=3600.0)
+                        .speed(0.5),
+                );
+                ui.radio_value(&mut self.unit, AdvanceUnit::Seconds, "seconds");
+                ui.radio_value(&mut self.unit, AdvanceUnit::Beats, "beats");
+            });
+            ui.add(
+                egui::Slider::new(&mut self.transition_duration, 0.0..=10.0)
+                    .text("transition (s)"),
+            );
+            ui.horizontal(|ui| {
+                ui.label("Transition shader");
+                ui.text_edit_singleline(&mut self.transition_shader)
+                    .on_hover_text("gl-transitions.com style `vec4 transition(vec2 uv)`; empty for a crossfade");
+            });
+            match &self.file {
+                Some(file) => ui.weak(format!("From {}", file.display())),
+                None => ui.weak("From the shader collection"),
+            };
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for (index, path) in entries.iter().enumerate() {
+                        let name = path.file_name().unwrap_or_default().to_string_lossy();
+                        if ui
+                            .selectable_label(self.enabled && index == self.index, name)
+                            .on_hover_text("Play next")
+                            .clicked()
+                        {
+                            self.next = Some(index);
+                            self.discard_prepared();
+                            self.skip = true;
+                        }
+                    }
+                });
+        });
+        self.open = open;
+    }
+}
+
+/// One shader path per line, relative to the playlist file; `#` starts a comment.
+fn read_playlist(file: &Path) -> eyre::Result<Vec<PathBuf>> {
+    let base = file.parent().unwrap_or(Path::new("."));
+    Ok(std::fs::read_to_string(file)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| base.join(line))
+        .collect())
+}
@@ -0,0 +1,124 @@
+use crate::shader_parser::{PointCoords, PreparseResult, UniformSpec, UniformWidget};
+use crate::uniforms_values::UniformsValues;
+use egui::{Color32, CursorIcon, Id, Pos2, Rect, Sense, Stroke, Ui, Vec2};
+
+const HANDLE_RADIUS: f32 = 6.0;
+
+/// A `vec2` uniform shown as a draggable handle on the viewport.
+#[derive(Clone, Debug)]
+pub struct PointTarget {
+    pub name: String,
+    pub coords: PointCoords,
+    pub default: [f32; 2],
+}
+
+pub fn point_targets(ppr: &PreparseResult) -> Vec<PointTarget> {
+    ppr.uniforms
+        .iter()
+        .filter(|u| u.widget == UniformWidget::Point)
+        .filter_map(|u| match &u.spec {
+            UniformSpec::Vec2(spec) => Some(PointTarget {
+                name: u.name.clone(),
+                coords: u.coords,
+                default: spec.certain_default(),
+            }),
+            _ => {
+                log::warn!("Point widget on {} needs a vec2", u.name);
+                None
+            }
+        })
+        .collect()
+}
+
+pub struct PointGizmos {
+    pub targets: Vec<PointTarget>,
+    /// Show every point's handle rather than only the selected one's.
+    pub show_all: bool,
+    pub selected: Option<String>,
+}
+
+impl Default for PointGizmos {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            show_all: true,
+            selected: None,
+        }
+    }
+}
+
+impl PointGizmos {
+    fn visible(&self) -> impl Iterator<Item = &PointTarget> {
+        self.targets
+            .iter()
+            .filter(|t| self.show_all || self.selected.as_ref() == Some(&t.name))
+    }
+
+    /// Drag and draw the handles over the viewport at `rect`; call after allocating the viewport
+    /// so the handles take precedence over it for pointer input.
+    pub fn show(&mut self, ui: &Ui, rect: Rect, uv: &mut UniformsValues, render_scale: f32) {
+        let pixels_per_point = ui.ctx().pixels_per_point() * render_scale;
+        let mut selected = None;
+        for target in self.visible() {
+            let scale = match target.coords {
+                PointCoords::Normalized => Vec2::new(1.0 / rect.width(), 1.0 / rect.height()),
+                PointCoords::Pixels => Vec2::splat(pixels_per_point),
+            };
+            let value = uv
+                .vec2_values
+                .get(&target.name)
+                .copied()
+                .unwrap_or(target.default);
+            let center = Pos2::new(
+                rect.left() + value[0] / scale.x,
+                rect.bottom() - value[1] / scale.y,
+            );
+            let response = ui
+                .interact(
+                    Rect::from_center_size(center, Vec2::splat(HANDLE_RADIUS * 3.0)),
+                    Id::new("point_gizmo").with(&target.name),
+                    Sense::drag(),
+                )
+                .on_hover_cursor(CursorIcon::Grab)
+                .on_hover_text(&target.name);
+            let mut center = center;
+            if response.dragged() {
+                center = (center + response.drag_delta()).clamp(rect.min, rect.max);
+                uv.set_vec2_value(
+                    &target.name,
+                    [
+                        (center.x - rect.left()) * scale.x,
+                        (rect.bottom() - center.y) * scale.y,
+                    ],
+                );
+            }
+            if response.drag_started() {
+                selected = Some(target.name.clone());
+            }
+            let active = response.hovered() || response.dragged();
+            let painter = ui.painter_at(rect);
+            painter.circle(
+                center,
+                HANDLE_RADIUS,
+                if active {
+                    Color32::from_white_alpha(160)
+                } else {
+                    Color32::from_black_alpha(96)
+                },
+                Stroke::new(2.0, Color32::WHITE),
+            );
+            if active {
+                painter.text(
+                    center + Vec2::new(HANDLE_RADIUS * 1.5, -HANDLE_RADIUS * 1.5),
+                    egui::Align2::LEFT_BOTTOM,
+                    &target.name,
+                    egui::FontId::monospace(12.0),
+                    Color32::WHITE,
+                );
+            }
+        }
+        if selected.is_some() {
+            self.selected = selected;
+        }
+    }
+}
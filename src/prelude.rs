@@ -0,0 +1,299 @@
+#![allow(clippy::undocumented_unsafe_blocks)]
+
+use crate::calendar::civil_from_days;
+use crate::isf;
+use crate::shader_parser::{PreparseResult, UniformSource};
+use eframe::glow::{self, HasContext};
+use serde::Deserialize;
+use std::time::SystemTime;
+
+const SHADERTOY_PRELUDE: &str = concat!(
+    include_str!("builtin_uniforms.glsl"),
+    include_str!("fragment_prelude.glsl")
+);
+const RAW_PRELUDE: &str = include_str!("raw_prelude.glsl");
+/// For shaders that write `gl_FragColor`.
+const FRAGCOLOR_PRELUDE: &str = include_str!("fragcolor_prelude.glsl");
+const ISF_PRELUDE: &str = include_str!("isf_prelude.glsl");
+
+/// Conventions a shader is written for: what it defines and which uniforms it expects.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PreludeMode {
+    /// `mainImage(out vec4, in vec2)` with `iTime`, `iResolution` and friends.
+    #[default]
+    Shadertoy,
+    /// The shader has its own `main` and outputs, and declares whatever uniforms it uses.
+    Raw,
+    /// glslsandbox.com: `main` writes `gl_FragColor`, with `time`, `resolution`, `mouse` and the
+    /// previous frame in `backbuffer`.
+    GlslSandbox,
+    /// glslCanvas and The Book of Shaders: `main` writes `gl_FragColor`, with `u_time`,
+    /// `u_resolution`, `u_mouse`, and `u_tex0` and on in the sampler slots.
+    GlslCanvas,
+    /// Interactive Shader Format: a JSON header describing inputs and passes, `main` writing
+    /// `gl_FragColor`, with `TIME`, `RENDERSIZE`, `isf_FragNormCoord` and the `IMG_` macros.
+    Isf,
+}
+
+/// What a mode's shader is fed each frame.
+pub(crate) struct ModeInputs {
+    pub resolution: [f32; 2],
+    pub time: f32,
+    pub time_delta: f32,
+    pub frame: i32,
+    /// Position in pixels from the bottom left.
+    pub mouse: [f32; 2],
+    /// Index of the ISF pass being drawn.
+    pub pass_index: i32,
+}
+
+impl PreludeMode {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "shadertoy" => Some(PreludeMode::Shadertoy),
+            "raw" => Some(PreludeMode::Raw),
+            "glslsandbox" | "sandbox" => Some(PreludeMode::GlslSandbox),
+            "glslcanvas" | "canvas" | "bookofshaders" => Some(PreludeMode::GlslCanvas),
+            "isf" => Some(PreludeMode::Isf),
+            _ => None,
+        }
+    }
+
+    /// `#pragma mode <name>` if there is one, ISF if there's a header, else a guess from the
+    /// source, with `default` for a `main` that could be either.
+    pub fn detect(source: &str, default: Option<PreludeMode>) -> Self {
+        for line in source.lines() {
+            let Some(rest) = line.trim().strip_prefix("#pragma") else {
+                continue;
+            };
+            let Some(name) = rest.trim().strip_prefix("mode") else {
+                continue;
+            };
+            match Self::from_name(name.trim()) {
+                Some(mode) => return mode,
+                None => log::warn!("Unknown mode in {:?}", line.trim()),
+            }
+        }
+        if isf::has_header(source) {
+            return PreludeMode::Isf;
+        }
+        if defines_function(source, "mainImage") {
+            return PreludeMode::Shadertoy;
+        }
+        if !defines_function(source, "main") {
+            return PreludeMode::Shadertoy;
+        }
+        let uniforms: Vec<&str> = source.lines().filter_map(declared_uniform).collect();
+        let declares = |names: &[&str]| uniforms.iter().any(|u| names.contains(u));
+        if declares(&["u_time", "u_resolution", "u_mouse", "u_tex0"]) {
+            PreludeMode::GlslCanvas
+        } else if declares(&["time", "resolution", "mouse", "backbuffer"]) {
+            PreludeMode::GlslSandbox
+        } else {
+            default.unwrap_or(PreludeMode::Raw)
+        }
+    }
+
+    pub(crate) fn prelude(self) -> &'static str {
+        match self {
+            PreludeMode::Shadertoy => SHADERTOY_PRELUDE,
+            PreludeMode::Raw => RAW_PRELUDE,
+            PreludeMode::GlslSandbox | PreludeMode::GlslCanvas => FRAGCOLOR_PRELUDE,
+            PreludeMode::Isf => ISF_PRELUDE,
+        }
+    }
+
+    /// Uniforms the shader declares itself but that are fed by us rather than the panel.
+    fn fed_uniforms(self) -> &'static [&'static str] {
+        match self {
+            PreludeMode::Shadertoy | PreludeMode::Raw | PreludeMode::Isf => &[],
+            PreludeMode::GlslSandbox => &["time", "resolution", "mouse"],
+            PreludeMode::GlslCanvas => &["u_time", "u_resolution", "u_mouse"],
+        }
+    }
+
+    /// The sampler fed with the shader's previous frame by convention.
+    fn feedback_sampler(self) -> Option<&'static str> {
+        match self {
+            PreludeMode::GlslSandbox => Some("backbuffer"),
+            _ => None,
+        }
+    }
+
+    /// Whether the prelude declares `name` or we feed it.
+    pub(crate) fn is_builtin_uniform(self, name: &str) -> bool {
+        self.fed_uniforms().contains(&name)
+            || self
+                .prelude()
+                .lines()
+                .any(|line| declared_uniform(line) == Some(name))
+    }
+
+    /// The source with declarations for what its ISF header describes, numbered as before.
+    pub(crate) fn expand_source(self, source: &str) -> String {
+        match self {
+            PreludeMode::Isf => isf::expand(source),
+            _ => source.to_owned(),
+        }
+    }
+
+    /// The source in terms the prelude and a GLSL 3 context understand, line for line.
+    pub(crate) fn adapt_source(self, source: &str) -> String {
+        if self == PreludeMode::Shadertoy {
+            return source.to_owned();
+        }
+        // The version comes first in what's compiled; the line is kept to keep the numbering.
+        let source: Vec<&str> = source
+            .lines()
+            .map(|line| {
+                if line.trim_start().starts_with("#version") {
+                    ""
+                } else {
+                    line
+                }
+            })
+            .collect();
+        let source = replace_identifier(&source.join("\n"), "gl_FragCoord", "varjostin_FragCoord");
+        let source = replace_identifier(&source, "texture2D", "texture");
+        match self {
+            PreludeMode::Raw => source,
+            _ => replace_identifier(&source, "gl_FragColor", "varjostin_FragColor"),
+        }
+    }
+
+    /// Hide fed uniforms from the panel and feed the conventional feedback sampler and the
+    /// ISF pass targets of `source`.
+    pub(crate) fn adapt_preparse(self, source: &str, ppr: &mut PreparseResult) {
+        ppr.uniforms
+            .retain(|u| !self.fed_uniforms().contains(&u.name.as_str()));
+        let pass_targets = match self {
+            PreludeMode::Isf => isf::passes(source)
+                .into_iter()
+                .filter_map(|pass| pass.target)
+                .collect(),
+            _ => Vec::new(),
+        };
+        for uniform in &mut ppr.uniforms {
+            if Some(uniform.name.as_str()) == self.feedback_sampler() {
+                uniform.source = Some(UniformSource::Feedback);
+            } else if pass_targets.contains(&uniform.name) {
+                uniform.source = Some(UniformSource::Pass);
+            }
+        }
+    }
+
+    /// Set the uniforms of the mode's conventions on the bound `program`.
+    pub(crate) fn apply(self, gl: &glow::Context, program: glow::Program, inputs: &ModeInputs) {
+        let (time, resolution, mouse) = match self {
+            PreludeMode::Shadertoy | PreludeMode::Raw => return,
+            PreludeMode::Isf => return apply_isf(gl, program, inputs),
+            PreludeMode::GlslSandbox => ("time", "resolution", "mouse"),
+            PreludeMode::GlslCanvas => ("u_time", "u_resolution", "u_mouse"),
+        };
+        let [width, height] = inputs.resolution;
+        let [x, y] = match self {
+            // Normalized in GLSL Sandbox.
+            PreludeMode::GlslSandbox => [
+                inputs.mouse[0] / width.max(1.0),
+                inputs.mouse[1] / height.max(1.0),
+            ],
+            _ => inputs.mouse,
+        };
+        unsafe {
+            gl.uniform_1_f32(gl.get_uniform_location(program, time).as_ref(), inputs.time);
+            gl.uniform_2_f32(
+                gl.get_uniform_location(program, resolution).as_ref(),
+                width,
+                height,
+            );
+            gl.uniform_2_f32(gl.get_uniform_location(program, mouse).as_ref(), x, y);
+        }
+    }
+}
+
+/// ISF's builtins; `DATE` is in UTC.
+fn apply_isf(gl: &glow::Context, program: glow::Program, inputs: &ModeInputs) {
+    let seconds = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or_default();
+    let (year, month, day) = civil_from_days((seconds / 86400.0) as i64);
+    let [width, height] = inputs.resolution;
+    unsafe {
+        gl.uniform_2_f32(
+            gl.get_uniform_location(program, "RENDERSIZE").as_ref(),
+            width,
+            height,
+        );
+        gl.uniform_1_f32(
+            gl.get_uniform_location(program, "TIME").as_ref(),
+            inputs.time,
+        );
+        gl.uniform_1_f32(
+            gl.get_uniform_location(program, "TIMEDELTA").as_ref(),
+            inputs.time_delta,
+        );
+        gl.uniform_1_i32(
+            gl.get_uniform_location(program, "FRAMEINDEX").as_ref(),
+            inputs.frame,
+        );
+        gl.uniform_1_i32(
+            gl.get_uniform_location(program, "PASSINDEX").as_ref(),
+            inputs.pass_index,
+        );
+        gl.uniform_4_f32(
+            gl.get_uniform_location(program, "DATE").as_ref(),
+            year as f32,
+            month as f32,
+            day as f32,
+            seconds.rem_euclid(86400.0) as f32,
+        );
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether `name` is followed by an opening parenthesis somewhere after a type.
+fn defines_function(source: &str, name: &str) -> bool {
+    source.match_indices(name).any(|(at, _)| {
+        let before = source[..at].trim_end();
+        let after = source[at + name.len()..].trim_start();
+        after.starts_with('(')
+            && !source[..at].ends_with(is_identifier_char)
+            && before.ends_with(is_identifier_char)
+    })
+}
+
+/// The name `line` declares a uniform by, if it does.
+pub(crate) fn declared_uniform(line: &str) -> Option<&str> {
+    let rest = line.trim().strip_prefix("uniform ")?;
+    let rest = rest.split([';', '=']).next()?;
+    let name = rest.split_whitespace().last()?;
+    name.split('[').next()
+}
+
+/// Whether `source` has `name` as a whole word.
+pub(crate) fn mentions_identifier(source: &str, name: &str) -> bool {
+    source.match_indices(name).any(|(at, _)| {
+        !source[..at].ends_with(is_identifier_char)
+            && !source[at + name.len()..].starts_with(is_identifier_char)
+    })
+}
+
+/// `source` with whole-word occurrences of `from` replaced.
+pub(crate) fn replace_identifier(source: &str, from: &str, to: &str) -> String {
+    let mut result = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(at) = rest.find(from) {
+        let whole = !rest[..at].ends_with(is_identifier_char)
+            && !rest[at + from.len()..].starts_with(is_identifier_char);
+        result.push_str(&rest[..at]);
+        result.push_str(if whole { to } else { from });
+        rest = &rest[at + from.len()..];
+    }
+    result.push_str(rest);
+    result
+}
@@ -0,0 +1,200 @@
+use crate::ramps::{Curve, Gradient, GradientStop};
+use egui::{Color32, Id, Pos2, Rect, Rgba, Sense, Shape, Stroke, Ui, Vec2, pos2, vec2};
+
+const HANDLE_RADIUS: f32 = 5.0;
+
+fn to_color32(c: [f32; 4]) -> Color32 {
+    Color32::from(Rgba::from_rgba_unmultiplied(c[0], c[1], c[2], c[3]))
+}
+
+/// Returns whether the gradient was changed.
+pub fn gradient_editor(ui: &mut Ui, id: Id, gradient: &mut Gradient) -> bool {
+    let mut changed = false;
+    let mut selected = ui.data(|d| d.get_temp::<usize>(id)).unwrap_or(0);
+
+    let width = ui.available_width();
+    let (bar_rect, bar_response) = ui.allocate_exact_size(vec2(width, 20.0), Sense::click());
+    let (handle_rect, _) = ui.allocate_exact_size(vec2(width, 12.0), Sense::hover());
+    let painter = ui.painter();
+    let slices = 64;
+    for i in 0..slices {
+        let t0 = i as f32 / slices as f32;
+        let t1 = (i + 1) as f32 / slices as f32;
+        let rect = Rect::from_x_y_ranges(
+            bar_rect.lerp_inside(vec2(t0, 0.0)).x..=bar_rect.lerp_inside(vec2(t1, 0.0)).x,
+            bar_rect.y_range(),
+        );
+        painter.rect_filled(rect, 0.0, to_color32(gradient.sample((t0 + t1) * 0.5)));
+    }
+    painter.rect_stroke(
+        bar_rect,
+        0.0,
+        ui.visuals().widgets.noninteractive.bg_stroke,
+        egui::StrokeKind::Inside,
+    );
+
+    if bar_response.clicked()
+        && let Some(pos) = bar_response.interact_pointer_pos()
+    {
+        let position = ((pos.x - bar_rect.left()) / bar_rect.width()).clamp(0.0, 1.0);
+        gradient.stops.push(GradientStop {
+            position,
+            color: gradient.sample(position),
+        });
+        selected = gradient.stops.len() - 1;
+        changed = true;
+    }
+
+    for (index, stop) in gradient.stops.iter_mut().enumerate() {
+        let x = handle_rect.left() + stop.position * handle_rect.width();
+        let center = pos2(x, handle_rect.center().y);
+        let rect = Rect::from_center_size(center, Vec2::splat(HANDLE_RADIUS * 2.0 + 2.0));
+        let response = ui.interact(rect, id.with(index), Sense::click_and_drag());
+        if response.clicked() || response.drag_started() {
+            selected = index;
+        }
+        if response.dragged() {
+            stop.position =
+                (stop.position + response.drag_delta().x / handle_rect.width()).clamp(0.0, 1.0);
+            changed = true;
+        }
+        let stroke = if index == selected {
+            Stroke::new(2.0, ui.visuals().selection.stroke.color)
+        } else {
+            Stroke::new(1.0, ui.visuals().widgets.inactive.fg_stroke.color)
+        };
+        ui.painter().add(Shape::convex_polygon(
+            vec![
+                pos2(x, handle_rect.top()),
+                pos2(x + HANDLE_RADIUS, handle_rect.bottom()),
+                pos2(x - HANDLE_RADIUS, handle_rect.bottom()),
+            ],
+            to_color32(stop.color),
+            stroke,
+        ));
+    }
+
+    if changed {
+        let selected_stop = gradient.stops.get(selected).cloned();
+        gradient.sort();
+        if let Some(selected_stop) = selected_stop {
+            selected = gradient
+                .stops
+                .iter()
+                .position(|s| *s == selected_stop)
+                .unwrap_or(0);
+        }
+    }
+
+    selected = selected.min(gradient.stops.len().saturating_sub(1));
+    let can_remove = gradient.stops.len() > 2;
+    let mut remove_selected = false;
+    if let Some(stop) = gradient.stops.get_mut(selected) {
+        ui.horizontal(|ui| {
+            changed |= ui
+                .color_edit_button_rgba_unmultiplied(&mut stop.color)
+                .changed();
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut stop.position)
+                        .range(0.0..=1.0)
+                        .speed(0.005),
+                )
+                .changed();
+            if ui
+                .add_enabled(can_remove, egui::Button::new("remove"))
+                .on_hover_text("Remove selected stop")
+                .clicked()
+            {
+                remove_selected = true;
+            }
+        });
+    }
+    if remove_selected {
+        gradient.stops.remove(selected);
+        selected = 0;
+        changed = true;
+    }
+    if changed {
+        gradient.sort();
+    }
+    ui.data_mut(|d| d.insert_temp(id, selected));
+    changed
+}
+
+/// Returns whether the curve was changed.
+pub fn curve_editor(ui: &mut Ui, id: Id, curve: &mut Curve) -> bool {
+    let mut changed = false;
+    let width = ui.available_width();
+    let (rect, response) = ui.allocate_exact_size(vec2(width, width * 0.6), Sense::click());
+    let to_screen = |p: [f32; 2]| -> Pos2 { rect.lerp_inside(vec2(p[0], 1.0 - p[1])) };
+    let from_screen = |p: Pos2| -> [f32; 2] {
+        [
+            ((p.x - rect.left()) / rect.width()).clamp(0.0, 1.0),
+            (1.0 - (p.y - rect.top()) / rect.height()).clamp(0.0, 1.0),
+        ]
+    };
+
+    let visuals = ui.visuals();
+    let painter = ui.painter();
+    painter.rect_filled(rect, 0.0, visuals.extreme_bg_color);
+    for i in 1..4 {
+        let f = i as f32 / 4.0;
+        let grid_stroke = Stroke::new(1.0, visuals.faint_bg_color);
+        painter.line_segment([to_screen([f, 0.0]), to_screen([f, 1.0])], grid_stroke);
+        painter.line_segment([to_screen([0.0, f]), to_screen([1.0, f])], grid_stroke);
+    }
+    let samples = 64;
+    let line: Vec<Pos2> = (0..=samples)
+        .map(|i| {
+            let x = i as f32 / samples as f32;
+            to_screen([x, curve.sample(x)])
+        })
+        .collect();
+    painter.add(Shape::line(line, visuals.widgets.active.fg_stroke));
+
+    if response.clicked()
+        && let Some(pos) = response.interact_pointer_pos()
+    {
+        curve.points.push(from_screen(pos));
+        changed = true;
+    }
+
+    let can_remove = curve.points.len() > 2;
+    let mut remove = None;
+    for (index, point) in curve.points.iter_mut().enumerate() {
+        let center = to_screen(*point);
+        let handle = Rect::from_center_size(center, Vec2::splat(HANDLE_RADIUS * 2.0 + 2.0));
+        let handle_response = ui
+            .interact(handle, id.with(index), Sense::click_and_drag())
+            .on_hover_text("Drag to move, right-click to remove");
+        if handle_response.dragged()
+            && let Some(pos) = handle_response.interact_pointer_pos()
+        {
+            *point = from_screen(pos);
+            changed = true;
+        }
+        if can_remove && handle_response.secondary_clicked() {
+            remove = Some(index);
+        }
+        let fill = if handle_response.hovered() || handle_response.dragged() {
+            ui.visuals().selection.bg_fill
+        } else {
+            ui.visuals().widgets.inactive.bg_fill
+        };
+        ui.painter().circle(
+            center,
+            HANDLE_RADIUS,
+            fill,
+            ui.visuals().widgets.active.fg_stroke,
+        );
+    }
+    if let Some(index) = remove {
+        curve.points.remove(index);
+        changed = true;
+    }
+    if changed {
+        curve.sort();
+    }
+    changed
+}
@@ -0,0 +1,181 @@
+use crate::shader_parser::{PreparseResult, UniformSpec, UniformWidget};
+use crate::textures::{DataImage, NamedDataImages};
+use crate::uniforms_values::UniformsValues;
+use serde::{Deserialize, Serialize};
+
+pub const RAMP_WIDTH: usize = 256;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GradientStop {
+    pub position: f32,
+    pub color: [f32; 4],
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gradient {
+    pub stops: Vec<GradientStop>,
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Self {
+            stops: vec![
+                GradientStop {
+                    position: 0.0,
+                    color: [0.0, 0.0, 0.0, 1.0],
+                },
+                GradientStop {
+                    position: 1.0,
+                    color: [1.0, 1.0, 1.0, 1.0],
+                },
+            ],
+        }
+    }
+}
+
+impl Gradient {
+    pub fn sort(&mut self) {
+        self.stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+    }
+
+    pub fn sample(&self, t: f32) -> [f32; 4] {
+        let (Some(first), Some(last)) = (self.stops.first(), self.stops.last()) else {
+            return [0.0; 4];
+        };
+        if t <= first.position {
+            return first.color;
+        }
+        if t >= last.position {
+            return last.color;
+        }
+        for pair in self.stops.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            if t >= a.position && t <= b.position {
+                let span = b.position - a.position;
+                let f = if span > 0.0 {
+                    (t - a.position) / span
+                } else {
+                    0.0
+                };
+                return std::array::from_fn(|index| {
+                    a.color[index] + (b.color[index] - a.color[index]) * f
+                });
+            }
+        }
+        last.color
+    }
+
+    pub fn bake(&self) -> DataImage {
+        let mut image = DataImage::new(RAMP_WIDTH, 1);
+        for x in 0..RAMP_WIDTH {
+            let color = self.sample(x as f32 / (RAMP_WIDTH - 1) as f32);
+            image.set_pixel(x, 0, color.map(to_u8));
+        }
+        image
+    }
+}
+
+/// A curve through control points in the unit square, interpolated with a Catmull-Rom spline.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Curve {
+    pub points: Vec<[f32; 2]>,
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Self {
+            points: vec![[0.0, 0.0], [1.0, 1.0]],
+        }
+    }
+}
+
+impl Curve {
+    pub fn sort(&mut self) {
+        self.points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+    }
+
+    pub fn sample(&self, x: f32) -> f32 {
+        let points = &self.points;
+        let (Some(first), Some(last)) = (points.first(), points.last()) else {
+            return 0.0;
+        };
+        if x <= first[0] {
+            return first[1];
+        }
+        if x >= last[0] {
+            return last[1];
+        }
+        let index = points
+            .windows(2)
+            .position(|pair| x >= pair[0][0] && x <= pair[1][0])
+            .unwrap_or(0);
+        let p1 = points[index];
+        let p2 = points[index + 1];
+        let p0 = if index > 0 { points[index - 1] } else { p1 };
+        let p3 = points.get(index + 2).copied().unwrap_or(p2);
+        let span = p2[0] - p1[0];
+        if span <= 0.0 {
+            return p1[1];
+        }
+        let t = (x - p1[0]) / span;
+        // Tangents scaled to the segment's x span so uneven point spacing doesn't overshoot.
+        let m1 = if p2[0] > p0[0] {
+            (p2[1] - p0[1]) / (p2[0] - p0[0]) * span
+        } else {
+            0.0
+        };
+        let m2 = if p3[0] > p1[0] {
+            (p3[1] - p1[1]) / (p3[0] - p1[0]) * span
+        } else {
+            0.0
+        };
+        let t2 = t * t;
+        let t3 = t2 * t;
+        let y = (2.0 * t3 - 3.0 * t2 + 1.0) * p1[1]
+            + (t3 - 2.0 * t2 + t) * m1
+            + (-2.0 * t3 + 3.0 * t2) * p2[1]
+            + (t3 - t2) * m2;
+        y.clamp(0.0, 1.0)
+    }
+
+    pub fn bake(&self) -> DataImage {
+        let mut image = DataImage::new(RAMP_WIDTH, 1);
+        for x in 0..RAMP_WIDTH {
+            let v = to_u8(self.sample(x as f32 / (RAMP_WIDTH - 1) as f32));
+            image.set_pixel(x, 0, [v, v, v, 255]);
+        }
+        image
+    }
+}
+
+fn to_u8(f: f32) -> u8 {
+    (f.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Bake the lookup textures for every gradient/curve sampler in the shader,
+/// falling back to the default ramp for ones that haven't been edited.
+pub fn bake_ramp_images(ppr: &PreparseResult, uv: &UniformsValues) -> NamedDataImages {
+    ppr.uniforms
+        .iter()
+        .filter(|u| matches!(u.spec, UniformSpec::Sampler2D))
+        .filter_map(|u| match u.widget {
+            UniformWidget::Gradient => Some((
+                u.name.clone(),
+                uv.gradient_values
+                    .get(&u.name)
+                    .cloned()
+                    .unwrap_or_default()
+                    .bake(),
+            )),
+            UniformWidget::Curve => Some((
+                u.name.clone(),
+                uv.curve_values
+                    .get(&u.name)
+                    .cloned()
+                    .unwrap_or_default()
+                    .bake(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,144 @@
+#![allow(clippy::undocumented_unsafe_blocks)]
+
+use eframe::glow;
+use eframe::glow::HasContext as _;
+
+/// Framebuffer binding, viewport and scissor state to return to after offscreen rendering.
+pub(crate) struct SavedTarget {
+    framebuffer: Option<glow::Framebuffer>,
+    viewport: [i32; 4],
+    scissor: bool,
+}
+
+#[allow(unsafe_code)]
+impl SavedTarget {
+    pub(crate) fn save(gl: &glow::Context) -> Self {
+        unsafe {
+            let mut viewport = [0; 4];
+            gl.get_parameter_i32_slice(glow::VIEWPORT, &mut viewport);
+            Self {
+                framebuffer: gl.get_parameter_framebuffer(glow::FRAMEBUFFER_BINDING),
+                viewport,
+                scissor: gl.is_enabled(glow::SCISSOR_TEST),
+            }
+        }
+    }
+
+    pub(crate) fn restore(&self, gl: &glow::Context) {
+        unsafe {
+            gl.bind_framebuffer(glow::FRAMEBUFFER, self.framebuffer);
+            let [x, y, width, height] = self.viewport;
+            gl.viewport(x, y, width, height);
+            if self.scissor {
+                gl.enable(glow::SCISSOR_TEST);
+            } else {
+                gl.disable(glow::SCISSOR_TEST);
+            }
+        }
+    }
+}
+
+/// An RGBA8 texture with a framebuffer to render into it, resized on demand.
+pub(crate) struct RenderTarget {
+    framebuffer: glow::Framebuffer,
+    pub(crate) texture: glow::Texture,
+    size: (i32, i32),
+}
+
+#[allow(unsafe_code)]
+impl RenderTarget {
+    pub(crate) fn new(gl: &glow::Context) -> eyre::Result<Self> {
+        unsafe {
+            let texture = gl.create_texture().map_err(|e| eyre::eyre!(e))?;
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            for (param, value) in [
+                (glow::TEXTURE_MIN_FILTER, glow::LINEAR),
+                (glow::TEXTURE_MAG_FILTER, glow::LINEAR),
+                (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+                (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+            ] {
+                gl.tex_parameter_i32(glow::TEXTURE_2D, param, value as i32);
+            }
+            let framebuffer = gl.create_framebuffer().map_err(|e| eyre::eyre!(e))?;
+            Ok(Self {
+                framebuffer,
+                texture,
+                size: (0, 0),
+            })
+        }
+    }
+
+    /// Draw into this target from now on, sized `width`×`height`, with the viewport covering it.
+    pub(crate) fn bind(&mut self, gl: &glow::Context, width: i32, height: i32) {
+        unsafe {
+            if self.size != (width, height) {
+                gl.bind_texture(glow::TEXTURE_2D, Some(self.texture));
+                gl.tex_image_2d(
+                    glow::TEXTURE_2D,
+                    0,
+                    glow::RGBA8 as i32,
+                    width,
+                    height,
+                    0,
+                    glow::RGBA,
+                    glow::UNSIGNED_BYTE,
+                    glow::PixelUnpackData::Slice(None),
+                );
+                gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer));
+                gl.framebuffer_texture_2d(
+                    glow::FRAMEBUFFER,
+                    glow::COLOR_ATTACHMENT0,
+                    glow::TEXTURE_2D,
+                    Some(self.texture),
+                    0,
+                );
+                self.size = (width, height);
+            }
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer));
+            gl.disable(glow::SCISSOR_TEST);
+            gl.viewport(0, 0, width, height);
+        }
+    }
+
+    /// Copy the bottom-left `width`×`height` of the bound framebuffer into this target,
+    /// sized to match, leaving the bindings as they were.
+    pub(crate) fn copy_from_bound(&mut self, gl: &glow::Context, width: i32, height: i32) {
+        let saved = SavedTarget::save(gl);
+        self.bind(gl, width, height);
+        unsafe {
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, saved.framebuffer);
+            gl.blit_framebuffer(
+                0,
+                0,
+                width,
+                height,
+                0,
+                0,
+                width,
+                height,
+                glow::COLOR_BUFFER_BIT,
+                glow::NEAREST,
+            );
+        }
+        saved.restore(gl);
+    }
+
+    /// Clear to transparent black.
+    pub(crate) fn clear(&mut self, gl: &glow::Context) {
+        let saved = SavedTarget::save(gl);
+        let (width, height) = self.size;
+        self.bind(gl, width, height);
+        unsafe {
+            gl.clear_color(0.0, 0.0, 0.0, 0.0);
+            gl.clear(glow::COLOR_BUFFER_BIT);
+        }
+        saved.restore(gl);
+    }
+
+    pub(crate) fn destroy(&self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_framebuffer(self.framebuffer);
+            gl.delete_texture(self.texture);
+        }
+    }
+}
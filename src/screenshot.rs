@@ -0,0 +1,27 @@
+use egui::{ColorImage, Rect};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Crop the shader's `rect` out of a window screenshot and save it as a PNG in `dir`.
+pub(crate) fn save_screenshot(
+    image: &ColorImage,
+    rect: Rect,
+    pixels_per_point: f32,
+    dir: &Path,
+    name: &str,
+) -> eyre::Result<PathBuf> {
+    let image = image.region(&rect, Some(pixels_per_point));
+    let millis = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_millis();
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}-{}.png", name, millis));
+    image::save_buffer(
+        &path,
+        image.as_raw(),
+        image.width() as u32,
+        image.height() as u32,
+        image::ExtendedColorType::Rgba8,
+    )?;
+    Ok(path)
+}
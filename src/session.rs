@@ -0,0 +1,155 @@
+use crate::beat_clock::BeatClock;
+use crate::clock::Clock;
+use crate::uniforms_values::UniformsValues;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// What a sampler slot is fed from, as far as it can be restored.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum TextureSlot {
+    #[default]
+    Empty,
+    /// The built-in texture.
+    Default,
+    Keyboard,
+    /// An image or audio file.
+    File(PathBuf),
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShaderSession {
+    pub uniforms_values: UniformsValues,
+    pub textures: [TextureSlot; 4],
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClockSession {
+    pub time: f64,
+    pub playing: bool,
+    pub speed: f32,
+    pub looping: bool,
+    pub loop_range: [f64; 2],
+    pub fixed_timestep: bool,
+    pub fixed_fps: f32,
+    pub bpm: f64,
+    pub beats_per_bar: u32,
+    /// Virtual time of a downbeat, so the beat grid keeps its phase.
+    pub beat_anchor: f64,
+}
+
+impl Default for ClockSession {
+    fn default() -> Self {
+        Self::from_clocks(&Clock::default(), &BeatClock::default())
+    }
+}
+
+impl ClockSession {
+    pub fn from_clocks(clock: &Clock, beat_clock: &BeatClock) -> Self {
+        Self {
+            time: clock.time(),
+            playing: clock.playing,
+            speed: clock.speed,
+            looping: clock.looping,
+            loop_range: clock.loop_range,
+            fixed_timestep: clock.fixed_timestep,
+            fixed_fps: clock.fixed_fps,
+            bpm: beat_clock.bpm(),
+            beats_per_bar: beat_clock.beats_per_bar,
+            beat_anchor: beat_clock.anchor_time(),
+        }
+    }
+
+    pub fn apply(&self, clock: &mut Clock, beat_clock: &mut BeatClock) {
+        clock.playing = self.playing;
+        clock.speed = self.speed;
+        clock.looping = self.looping;
+        clock.loop_range = self.loop_range;
+        clock.fixed_timestep = self.fixed_timestep;
+        clock.fixed_fps = self.fixed_fps;
+        clock.seek(self.time);
+        beat_clock.set_grid(self.bpm, self.beat_anchor);
+        beat_clock.beats_per_bar = self.beats_per_bar;
+    }
+}
+
+/// Which windows were open.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PanelsSession {
+    pub gallery: bool,
+    pub playlist: bool,
+    pub warp: bool,
+    pub sound: bool,
+    pub output_window: bool,
+    pub log: bool,
+}
+
+/// Main window geometry, in points.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WindowSession {
+    pub position: Option<[f32; 2]>,
+    pub size: [f32; 2],
+}
+
+/// State carried over between runs, in `$XDG_CONFIG_HOME/varjostin/session.json5`.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub shader_path: Option<PathBuf>,
+    /// Uniform values and textures by shader, the current one included.
+    pub shaders: HashMap<PathBuf, ShaderSession>,
+    pub clock: Option<ClockSession>,
+    pub panels: PanelsSession,
+    pub window: Option<WindowSession>,
+}
+
+impl Session {
+    pub fn load() -> Self {
+        let Some(path) = session_path() else {
+            return Self::default();
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json5::from_str(&text).unwrap_or_else(|e| {
+                log::error!("Error parsing session {:?}: {}", path, e);
+                Self::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                log::error!("Error reading session {:?}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let Some(path) = session_path() else {
+            return;
+        };
+        let result = serde_json5::to_string(self)
+            .map_err(|e| eyre::eyre!(e))
+            .and_then(|text| {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                Ok(std::fs::write(&path, text)?)
+            });
+        if let Err(e) = result {
+            log::error!("Error saving session {:?}: {}", path, e);
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/varjostin`, falling back to `~/.config`.
+pub(crate) fn config_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("varjostin"))
+}
+
+fn session_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("session.json5"))
+}
@@ -1,19 +1,49 @@
 #![allow(clippy::undocumented_unsafe_blocks)]
 
-use crate::gl::compile_program;
-use crate::shader_parser::{PreparseResult, preparse_shader};
-use crate::textures::Textures;
+use crate::beat_clock::BeatClock;
+use crate::clock::Clock;
+use crate::gl::{
+    compile_program, compile_program_with_prelude, create_data_texture, upload_data_texture,
+};
+use crate::introspect::{cross_check, fallback_preparse, program_uniforms};
+use crate::isf::{self, RenderPass};
+use crate::keyboard::Keyboard;
+use crate::log_console::shader_scope;
+use crate::mouse::Mouse;
+use crate::point_gizmos::PointGizmos;
+use crate::prelude::{ModeInputs, PreludeMode};
+use crate::ramps::bake_ramp_images;
+use crate::render_target::{RenderTarget, SavedTarget};
+use crate::shader_parser::{PreparseResult, UniformSource, preparse_shader};
+use crate::sound::{SoundRender, SoundRenderRequest};
+use crate::textures::{DataImage, NamedDataImages, Textures};
+use crate::thumbnail::{ThumbnailRenderRequest, render_thumbnail};
 use crate::uniforms_values::UniformsValues;
+use crate::viewport_nav::{ViewportTarget, navigate};
+use crate::warp::{Warp, WarpMode, WarpSettings};
+use crate::warp_pass::WarpPass;
 use eframe::egui_glow;
 use eframe::egui_glow::Painter;
 use eframe::epaint::PaintCallbackInfo;
 use egui::Ui;
+use egui::ahash::HashMap;
 use egui::mutex::Mutex;
 use egui_glow::glow;
+use std::collections::VecDeque;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::mpsc::Sender;
 use std::time::{Duration, Instant};
 
+const TRANSITION_PRELUDE: &str = include_str!("transition_prelude.glsl");
+const MIN_RENDER_SCALE: f32 = 0.1;
+
+pub const CROSSFADE_TRANSITION: &str = include_str!("crossfade.glsl");
+const OUTGOING_PREFIX: &str = "outgoing/";
+/// Compiled programs kept around for switching back to, e.g. between `#define` variants.
+const MAX_VARIANTS: usize = 8;
+
 pub struct ShaderCompileResponse {
     pub duration: Duration,
     pub preparse_result: Option<eyre::Result<PreparseResult>>,
@@ -22,29 +52,79 @@ pub struct ShaderCompileResponse {
 
 pub struct ShaderCompileRequest {
     pub fragment_source: String,
+    /// The file the source came from, to tag log records with.
+    pub shader_path: Option<PathBuf>,
     pub response_sender: Sender<ShaderCompileResponse>,
 }
 
 pub struct Custom3d {
     shader_frame: Arc<Mutex<ShaderFrame>>,
-    init_time: Instant,
+    pub clock: Clock,
+    pub beat_clock: BeatClock,
+    /// Smoothed bass/mid/treble levels of the first audio-fed sampler slot.
+    pub audio_bands: [f32; 3],
+    pub keyboard: Keyboard,
+    /// Whether the shader viewport has keyboard focus (as of the previous frame).
+    pub has_keyboard_focus: bool,
+    /// Whether viewport input drives the camera in `UniformsValues`.
+    pub camera_enabled: bool,
+    /// Uniforms driven by panning and zooming the viewport.
+    pub viewport_targets: Vec<ViewportTarget>,
+    /// Draggable handles for point-widget uniforms.
+    pub point_gizmos: PointGizmos,
     shader_compile_request: Option<ShaderCompileRequest>,
-    pub mouse_x: f32,
-    pub mouse_y: f32,
-    pub mouse_down: bool,
-    pub mouse_down_seconds: f32,
-    pub frame: u64,
-    last_mouse_down_time: Instant,
+    shader_prepare_request: Option<ShaderCompileRequest>,
+    swap_prepared_request: bool,
+    /// The transition from the previous shader after a playlist switch.
+    pub transition: Option<Transition>,
+    sound_render_request: Option<SoundRenderRequest>,
+    thumbnail_render_request: Option<ThumbnailRenderRequest>,
+    pub mouse: Mouse,
+    /// Fraction of the viewport's resolution to render at, upscaled to fill it.
+    pub render_scale: f32,
+    /// Where the shader was shown last frame.
+    pub rect: egui::Rect,
+    /// Mode for shaders without `#pragma mode`, detected if `None`.
+    pub prelude_mode: Option<PreludeMode>,
 }
 
 struct DrawInfo {
-    mouse_x: f32,
-    mouse_y: f32,
-    mouse_down_seconds: f32,
+    mouse: [f32; 4],
+    mouse_wheel: [f32; 2],
+    mouse_buttons: i32,
     curr_time: f32,
+    time_delta: f32,
+    bpm: f32,
+    beat: f32,
+    bar: f32,
+    audio_bands: [f32; 3],
     frame: u64,
     fps: f32,
     uniforms_values: UniformsValues,
+    data_images: NamedDataImages,
+    warp: WarpSettings,
+    render_scale: f32,
+    transition: Option<TransitionDraw>,
+}
+
+/// The outgoing shader's inputs while transitioning to the current one.
+struct TransitionDraw {
+    progress: f32,
+    source: String,
+    uniforms_values: UniformsValues,
+    textures: Textures,
+    data_images: NamedDataImages,
+}
+
+/// A playlist transition in progress, timed by the clock.
+pub struct Transition {
+    pub start_time: f64,
+    pub duration: f64,
+    /// `vec4 transition(vec2 uv)` in gl-transitions.com style.
+    pub source: String,
+    pub uniforms_values: UniformsValues,
+    pub textures: Textures,
+    pub preparse_result: Option<PreparseResult>,
 }
 
 impl Custom3d {
@@ -53,84 +133,178 @@ impl Custom3d {
         Some(Self {
             shader_frame: Arc::new(Mutex::new(ShaderFrame::new(gl)?)),
             shader_compile_request: None,
-            mouse_x: 0.0,
-            mouse_y: 0.0,
-            mouse_down: false,
-            mouse_down_seconds: 0.0,
-            frame: 0,
-            init_time: Instant::now(),
-            last_mouse_down_time: Instant::now(),
+            shader_prepare_request: None,
+            swap_prepared_request: false,
+            transition: None,
+            sound_render_request: None,
+            thumbnail_render_request: None,
+            mouse: Mouse::default(),
+            clock: Clock::default(),
+            beat_clock: BeatClock::default(),
+            audio_bands: [0.0; 3],
+            keyboard: Keyboard::default(),
+            has_keyboard_focus: false,
+            camera_enabled: false,
+            viewport_targets: Vec::new(),
+            point_gizmos: PointGizmos::default(),
+            render_scale: 1.0,
+            rect: egui::Rect::NOTHING,
+            prelude_mode: None,
         })
     }
 
-    pub(crate) fn reset(&mut self) {
-        self.init_time = Instant::now();
-        self.frame = 0;
-    }
-
     pub(crate) fn request_shader_compile(
         &mut self,
         fragment_source: String,
+        shader_path: Option<PathBuf>,
         response_sender: Sender<ShaderCompileResponse>,
     ) {
         self.shader_compile_request = Some(ShaderCompileRequest {
             fragment_source,
+            shader_path,
             response_sender,
         });
     }
 
+    /// Compile a shader in the background, to be switched to by `request_swap_prepared`.
+    pub(crate) fn request_shader_prepare(
+        &mut self,
+        fragment_source: String,
+        shader_path: Option<PathBuf>,
+        response_sender: Sender<ShaderCompileResponse>,
+    ) {
+        self.shader_prepare_request = Some(ShaderCompileRequest {
+            fragment_source,
+            shader_path,
+            response_sender,
+        });
+    }
+
+    /// Switch to the prepared shader, transitioning from the current one.
+    pub(crate) fn request_swap_prepared(&mut self, transition: Transition) {
+        self.swap_prepared_request = true;
+        self.transition = Some(transition);
+    }
+
+    pub(crate) fn request_sound_render(&mut self, request: SoundRenderRequest) {
+        self.sound_render_request = Some(request);
+    }
+
+    pub(crate) fn request_thumbnail_render(&mut self, request: ThumbnailRenderRequest) {
+        self.thumbnail_render_request = Some(request);
+    }
+
+    pub fn update_keyboard(&mut self, ctx: &egui::Context) {
+        self.keyboard.update(ctx, self.has_keyboard_focus);
+    }
+
     pub fn update(
         &mut self,
-        _ctx: &egui::Context,
         ui: &mut Ui,
         fps: f32,
-        uniforms_values: &UniformsValues,
+        uniforms_values: &mut UniformsValues,
+        warp: &mut Warp,
         textures: Textures,
+        data_images: NamedDataImages,
     ) {
+        self.clock.tick();
         let (rect, response) =
             ui.allocate_exact_size(ui.available_size(), egui::Sense::click_and_drag());
-        if let Some(pos) = response.hover_pos() {
-            // TODO: Why do we need the fudge here?!
-            self.mouse_x = pos.x - rect.min.x;
-            self.mouse_y = pos.y - rect.min.y;
-        }
-        let mouse_down = response.is_pointer_button_down_on();
-        if !self.mouse_down && mouse_down {
-            self.last_mouse_down_time = Instant::now();
-        }
-        self.mouse_down = mouse_down;
-        self.mouse_down_seconds = if mouse_down {
-            self.last_mouse_down_time.elapsed().as_secs_f32()
+        self.rect = rect;
+        let render_scale = self.render_scale.clamp(MIN_RENDER_SCALE, 1.0);
+        // Reserve the shader's place under the handles painted below.
+        let callback_slot = ui.painter().add(egui::Shape::Noop);
+        if warp.editing {
+            warp.show_handles(ui, rect);
         } else {
-            0.0
-        };
+            self.point_gizmos
+                .show(ui, rect, uniforms_values, render_scale);
+        }
+        if response.clicked() || response.drag_started() {
+            response.request_focus();
+        }
+        if response.has_focus() {
+            ui.memory_mut(|m| {
+                m.set_focus_lock_filter(
+                    response.id,
+                    egui::EventFilter {
+                        tab: true,
+                        horizontal_arrows: true,
+                        vertical_arrows: true,
+                        escape: true,
+                    },
+                )
+            });
+        }
+        self.has_keyboard_focus = response.has_focus();
+        navigate(ui, &response, rect, &self.viewport_targets, uniforms_values);
+        if self.camera_enabled {
+            let dt = ui.input(|i| i.stable_dt);
+            uniforms_values
+                .camera
+                .get_or_insert_with(Default::default)
+                .handle_input(ui, &response, self.has_keyboard_focus, dt);
+        }
+        self.mouse
+            .update(ui, &response, rect, self.clock.time(), self.camera_enabled);
         let draw_info = DrawInfo {
-            mouse_x: self.mouse_x,
-            mouse_y: self.mouse_y,
-            mouse_down_seconds: self.mouse_down_seconds,
+            // In the pixels of the possibly downscaled render.
+            mouse: self.mouse.uniform().map(|v| v * render_scale),
+            mouse_wheel: self.mouse.wheel.map(|v| v * render_scale),
+            mouse_buttons: self.mouse.buttons,
             curr_time: self.curr_time(),
-            frame: self.frame,
+            time_delta: self.clock.delta(),
+            bpm: self.beat_clock.bpm() as f32,
+            beat: self.beat_clock.beat(self.clock.time()) as f32,
+            bar: self.beat_clock.bar(self.clock.time()) as f32,
+            audio_bands: self.audio_bands,
+            frame: self.clock.frame(),
             fps,
             uniforms_values: uniforms_values.clone(),
+            data_images,
+            warp: warp.settings.clone(),
+            render_scale,
+            transition: self.transition_draw(),
         };
         let shader_compile_request = self.shader_compile_request.take();
-        self.frame += 1;
+        let shader_prepare_request = self.shader_prepare_request.take();
+        let swap_prepared_request = std::mem::take(&mut self.swap_prepared_request);
+        let sound_render_request = self.sound_render_request.take();
+        let thumbnail_render_request = self.thumbnail_render_request.take();
+        let prelude_mode = self.prelude_mode;
         let f = self.shader_frame.clone();
 
         let cb = egui_glow::CallbackFn::new(move |info, painter| {
             let mut fl = f.lock();
+            fl.sync_data_textures(painter.gl(), &draw_info.data_images, "");
+            if let Some(transition) = &draw_info.transition {
+                fl.sync_data_textures(painter.gl(), &transition.data_images, OUTGOING_PREFIX);
+            }
+            if let Some(request) = &shader_prepare_request {
+                let _scope = shader_scope(request.shader_path.as_deref());
+                let t0 = Instant::now();
+                let (prep, layer) =
+                    compile_layer(painter.gl(), &request.fragment_source, prelude_mode);
+                let fr = layer.map(|layer| fl.prepare_shader(painter.gl(), layer));
+                let duration = Instant::now().duration_since(t0);
+                request
+                    .response_sender
+                    .send(ShaderCompileResponse {
+                        duration,
+                        preparse_result: Some(prep),
+                        error: fr.err(),
+                    })
+                    .ok();
+            }
+            if swap_prepared_request {
+                fl.swap_prepared(painter.gl());
+            }
             if let Some(request) = &shader_compile_request {
+                let _scope = shader_scope(request.shader_path.as_deref());
                 let t0 = Instant::now();
-                let prep = preparse_shader(&request.fragment_source);
-                let sampler_uniform_names = prep
-                    .as_ref()
-                    .map(|prep| prep.sampler_uniform_names())
-                    .unwrap_or_default();
-                let fr = fl.set_shader(
-                    painter.gl(),
-                    &request.fragment_source,
-                    sampler_uniform_names,
-                );
+                let (prep, layer) =
+                    fl.compile_variant(painter.gl(), &request.fragment_source, prelude_mode);
+                let fr = layer.map(|layer| fl.set_shader(painter.gl(), layer));
                 let duration = Instant::now().duration_since(t0);
                 request
                     .response_sender
@@ -141,6 +315,13 @@ impl Custom3d {
                     })
                     .ok();
             }
+            if let Some(request) = &sound_render_request {
+                fl.start_sound_render(painter, request);
+            }
+            fl.step_sound_render(painter);
+            if let Some(request) = &thumbnail_render_request {
+                render_thumbnail(painter, request, prelude_mode);
+            }
             fl.paint(painter, &info, &draw_info, &textures);
         });
 
@@ -148,11 +329,32 @@ impl Custom3d {
             rect,
             callback: Arc::new(cb),
         };
-        ui.painter().add(callback);
+        ui.painter().set(callback_slot, callback);
+    }
+
+    /// Advance the transition, dropping it once it's done.
+    fn transition_draw(&mut self) -> Option<TransitionDraw> {
+        let transition = self.transition.as_ref()?;
+        let elapsed = self.clock.time() - transition.start_time;
+        if !(0.0..transition.duration).contains(&elapsed) {
+            self.transition = None;
+            return None;
+        }
+        Some(TransitionDraw {
+            progress: (elapsed / transition.duration) as f32,
+            source: transition.source.clone(),
+            uniforms_values: transition.uniforms_values.clone(),
+            textures: transition.textures.clone(),
+            data_images: transition
+                .preparse_result
+                .as_ref()
+                .map(|ppr| bake_ramp_images(ppr, &transition.uniforms_values))
+                .unwrap_or_default(),
+        })
     }
 
-    pub fn curr_time(&mut self) -> f32 {
-        self.init_time.elapsed().as_secs_f32()
+    pub fn curr_time(&self) -> f32 {
+        self.clock.time() as f32
     }
 
     pub fn exit(&mut self, gl: Option<&glow::Context>) {
@@ -162,10 +364,135 @@ impl Custom3d {
     }
 }
 
+/// A compiled shader program and the sampler uniforms bound to texture slots.
+#[derive(Clone)]
+struct Layer {
+    program: glow::Program,
+    mode: PreludeMode,
+    sampler_uniform_names: Vec<String>,
+    /// Samplers fed with the previous frame.
+    feedback_uniform_names: Vec<String>,
+    /// ISF passes, the last drawn to the output; empty for a single pass.
+    passes: Vec<RenderPass>,
+}
+
+impl Layer {
+    fn new(program: glow::Program, mode: PreludeMode, ppr: Option<&PreparseResult>) -> Self {
+        Self {
+            program,
+            mode,
+            sampler_uniform_names: ppr
+                .map(|ppr| ppr.sampler_uniform_names())
+                .unwrap_or_default(),
+            feedback_uniform_names: ppr
+                .map(|ppr| ppr.sourced_sampler_names(UniformSource::Feedback))
+                .unwrap_or_default(),
+            passes: Vec::new(),
+        }
+    }
+}
+
+/// Compile a shader and find its uniforms, from the program where the preparser fails.
+fn compile_layer(
+    gl: &glow::Context,
+    fragment_source: &str,
+    default_mode: Option<PreludeMode>,
+) -> (eyre::Result<PreparseResult>, eyre::Result<Layer>) {
+    let mode = PreludeMode::detect(fragment_source, default_mode);
+    let expanded = mode.expand_source(fragment_source);
+    let prep = preparse_shader(&expanded).map(|mut ppr| {
+        mode.adapt_preparse(fragment_source, &mut ppr);
+        ppr
+    });
+    let program = match compile_program(gl, &expanded, mode) {
+        Ok(program) => program,
+        Err(e) => {
+            if let Err(prep_error) = &prep {
+                log::warn!(
+                    "Couldn't preparse the shader, which may be why it doesn't compile: {}",
+                    prep_error
+                );
+            }
+            return (prep, Err(e));
+        }
+    };
+    let (uniforms, unsupported) = program_uniforms(gl, program, mode);
+    let prep = match prep {
+        Ok(mut ppr) => {
+            cross_check(&mut ppr, &uniforms);
+            ppr
+        }
+        Err(e) => {
+            log::warn!(
+                "Couldn't preparse the shader, so its uniforms come from the compiled program \
+                 and pragmas are ignored: {}",
+                e
+            );
+            for description in unsupported {
+                log::warn!("Unsupported uniform {}", description);
+            }
+            let mut ppr = fallback_preparse(&expanded, uniforms);
+            mode.adapt_preparse(fragment_source, &mut ppr);
+            ppr
+        }
+    };
+    let mut layer = Layer::new(program, mode, Some(&prep));
+    if mode == PreludeMode::Isf {
+        layer.passes = isf::passes(fragment_source);
+    }
+    (Ok(prep), Ok(layer))
+}
+
+/// Copy what `layer` just drew offscreen for it to sample next frame.
+fn keep_feedback(
+    feedback: &mut Option<eyre::Result<RenderTarget>>,
+    layer: &Layer,
+    gl: &glow::Context,
+    width: i32,
+    height: i32,
+) {
+    if !layer.feedback_uniform_names.is_empty()
+        && let Some(Ok(feedback)) = feedback
+    {
+        feedback.copy_from_bound(gl, width, height);
+    }
+}
+
+/// What a layer is drawn with besides the shared clock and input state.
+struct LayerInputs<'a> {
+    uniforms_values: &'a UniformsValues,
+    textures: &'a Textures,
+    data_images: &'a NamedDataImages,
+    /// Key prefix in `data_textures`, keeping the outgoing layer's data textures apart.
+    data_prefix: &'a str,
+    /// Textures of ISF pass targets by name.
+    pass_textures: &'a [(String, glow::Texture)],
+    /// The ISF pass being drawn, `None` for the last.
+    pass_index: Option<usize>,
+}
+
 struct ShaderFrame {
-    program: Option<glow::Program>,
+    current: Option<Layer>,
+    /// Compiled ahead of a playlist switch.
+    prepared: Option<Layer>,
+    /// The previous program, drawn while transitioning away from it.
+    outgoing: Option<Layer>,
     vertex_array: glow::VertexArray,
-    sampler_uniform_names: Vec<String>,
+    data_textures: HashMap<String, (glow::Texture, DataImage)>,
+    /// Created when warping is first enabled.
+    warp_pass: Option<eyre::Result<WarpPass>>,
+    /// Offscreen targets for the outgoing and incoming layers, created for the first transition.
+    layer_targets: Option<eyre::Result<[RenderTarget; 2]>>,
+    /// Transition program and the source it was compiled from.
+    transition_program: Option<(String, eyre::Result<glow::Program>)>,
+    /// The current layer's previous frame, for feedback samplers.
+    feedback: Option<eyre::Result<RenderTarget>>,
+    /// Recently compiled shaders by source hash, oldest first.
+    variants: VecDeque<(u64, Layer, PreparseResult)>,
+    /// ISF pass targets by name, the one last drawn into first.
+    pass_targets: HashMap<String, [RenderTarget; 2]>,
+    /// The sound render in progress.
+    sound_render: Option<SoundRender>,
 }
 
 #[allow(unsafe_code)] // we need unsafe code to use glow
@@ -179,37 +506,219 @@ impl ShaderFrame {
                 .expect("Cannot create vertex array");
 
             Some(Self {
-                program: None,
+                current: None,
+                prepared: None,
+                outgoing: None,
                 vertex_array,
-                sampler_uniform_names: Vec::new(),
+                data_textures: HashMap::default(),
+                warp_pass: None,
+                layer_targets: None,
+                transition_program: None,
+                feedback: None,
+                variants: VecDeque::new(),
+                pass_targets: HashMap::default(),
+                sound_render: None,
             })
         }
     }
 
-    fn set_shader(
+    /// Compile a shader, or reuse the program if the same source was compiled recently.
+    fn compile_variant(
         &mut self,
         gl: &glow::Context,
         fragment_source: &str,
-        sampler_uniform_names: Vec<String>,
-    ) -> eyre::Result<()> {
-        let program = compile_program(gl, fragment_source)?;
-        self.program = Some(program);
-        self.sampler_uniform_names = sampler_uniform_names;
-        Ok(())
+        default_mode: Option<PreludeMode>,
+    ) -> (eyre::Result<PreparseResult>, eyre::Result<Layer>) {
+        let mut hasher = DefaultHasher::new();
+        fragment_source.hash(&mut hasher);
+        default_mode.hash(&mut hasher);
+        let hash = hasher.finish();
+        if let Some(index) = self.variants.iter().position(|(h, _, _)| *h == hash) {
+            let variant = self.variants.remove(index).expect("variant exists");
+            let result = (Ok(variant.2.clone()), Ok(variant.1.clone()));
+            self.variants.push_back(variant);
+            return result;
+        }
+        let (prep, layer) = compile_layer(gl, fragment_source, default_mode);
+        if let (Ok(ppr), Ok(layer)) = (&prep, &layer) {
+            self.variants.push_back((hash, layer.clone(), ppr.clone()));
+            if self.variants.len() > MAX_VARIANTS
+                && let Some((_, evicted, _)) = self.variants.pop_front()
+            {
+                self.release(gl, evicted.program);
+            }
+        }
+        (prep, layer)
+    }
+
+    /// Delete `program` unless a layer or the variant cache still has it.
+    fn release(&self, gl: &glow::Context, program: glow::Program) {
+        use glow::HasContext as _;
+        let in_use = [&self.current, &self.prepared, &self.outgoing]
+            .into_iter()
+            .flatten()
+            .chain(self.variants.iter().map(|(_, layer, _)| layer))
+            .any(|layer| layer.program == program);
+        if !in_use {
+            unsafe { gl.delete_program(program) };
+        }
+    }
+
+    fn set_shader(&mut self, gl: &glow::Context, layer: Layer) {
+        if let Some(old) = self.current.replace(layer) {
+            self.release(gl, old.program);
+        }
+        self.clear_feedback(gl);
+        self.clear_pass_targets(gl);
+    }
+
+    /// Keep a layer to swap in later with `swap_prepared`.
+    fn prepare_shader(&mut self, gl: &glow::Context, layer: Layer) {
+        if let Some(old) = self.prepared.replace(layer) {
+            self.release(gl, old.program);
+        }
+    }
+
+    /// Make the prepared shader current, keeping the current one around to transition from.
+    fn swap_prepared(&mut self, gl: &glow::Context) {
+        let Some(prepared) = self.prepared.take() else {
+            return;
+        };
+        let previous = std::mem::replace(&mut self.outgoing, self.current.replace(prepared));
+        if let Some(previous) = previous {
+            self.release(gl, previous.program);
+        }
+        self.clear_feedback(gl);
+        self.clear_pass_targets(gl);
+    }
+
+    /// Start a new shader's feedback from black rather than another shader's last frame.
+    fn clear_feedback(&mut self, gl: &glow::Context) {
+        if let Some(Ok(feedback)) = &mut self.feedback {
+            feedback.clear(gl);
+        }
+    }
+
+    /// Start a new shader's passes from scratch, in targets named after its own.
+    fn clear_pass_targets(&mut self, gl: &glow::Context) {
+        for (_, targets) in self.pass_targets.drain() {
+            for target in targets {
+                target.destroy(gl);
+            }
+        }
+    }
+
+    /// Upload data images whose contents changed since the last frame.
+    fn sync_data_textures(
+        &mut self,
+        gl: &glow::Context,
+        data_images: &NamedDataImages,
+        prefix: &str,
+    ) {
+        for (name, image) in data_images {
+            let key = format!("{}{}", prefix, name);
+            match self.data_textures.get_mut(&key) {
+                Some((texture, uploaded)) => {
+                    if uploaded != image {
+                        upload_data_texture(gl, *texture, image);
+                        *uploaded = image.clone();
+                    }
+                }
+                None => match create_data_texture(gl) {
+                    Ok(texture) => {
+                        upload_data_texture(gl, texture, image);
+                        self.data_textures.insert(key, (texture, image.clone()));
+                    }
+                    Err(e) => {
+                        log::error!("Error creating data texture for {}: {:?}", name, e);
+                    }
+                },
+            }
+        }
+    }
+
+    /// Compile the transition shader if its source changed; `None` if it doesn't compile.
+    fn transition_program(&mut self, gl: &glow::Context, source: &str) -> Option<glow::Program> {
+        use glow::HasContext as _;
+        if self.transition_program.as_ref().map(|(s, _)| s.as_str()) != Some(source) {
+            let program = compile_program_with_prelude(gl, TRANSITION_PRELUDE, source);
+            match &program {
+                Ok(_) => {}
+                Err(e) => log::error!("Error compiling transition shader: {:?}", e),
+            }
+            if let Some((_, Ok(old))) = self
+                .transition_program
+                .replace((source.to_owned(), program))
+            {
+                unsafe { gl.delete_program(old) };
+            }
+        }
+        match &self.transition_program {
+            Some((_, Ok(program))) => Some(*program),
+            _ => None,
+        }
+    }
+
+    /// Start rendering `request`, replacing any sound render in progress.
+    fn start_sound_render(&mut self, painter: &Painter, request: &SoundRenderRequest) {
+        if let Some(previous) = self.sound_render.take() {
+            previous.destroy(painter.gl());
+        }
+        self.sound_render = SoundRender::start(painter, request);
+    }
+
+    fn step_sound_render(&mut self, painter: &Painter) {
+        if let Some(sound_render) = &mut self.sound_render
+            && sound_render.step(painter)
+        {
+            sound_render.destroy(painter.gl());
+            self.sound_render = None;
+        }
     }
 
     fn destroy(&self, gl: &glow::Context) {
         use glow::HasContext as _;
         unsafe {
-            if let Some(program) = self.program {
+            let mut programs: Vec<glow::Program> = [&self.current, &self.prepared, &self.outgoing]
+                .into_iter()
+                .flatten()
+                .chain(self.variants.iter().map(|(_, layer, _)| layer))
+                .map(|layer| layer.program)
+                .collect();
+            programs.sort();
+            programs.dedup();
+            for program in programs {
                 gl.delete_program(program);
             }
+            for (texture, _) in self.data_textures.values() {
+                gl.delete_texture(*texture);
+            }
+            if let Some(Ok(warp_pass)) = &self.warp_pass {
+                warp_pass.destroy(gl);
+            }
+            if let Some(Ok(targets)) = &self.layer_targets {
+                for target in targets {
+                    target.destroy(gl);
+                }
+            }
+            if let Some((_, Ok(program))) = &self.transition_program {
+                gl.delete_program(*program);
+            }
+            if let Some(Ok(feedback)) = &self.feedback {
+                feedback.destroy(gl);
+            }
+            if let Some(sound_render) = &self.sound_render {
+                sound_render.destroy(gl);
+            }
+            for target in self.pass_targets.values().flatten() {
+                target.destroy(gl);
+            }
             gl.delete_vertex_array(self.vertex_array);
         }
     }
 
     fn paint(
-        &self,
+        &mut self,
         painter: &Painter,
         pci: &PaintCallbackInfo,
         info: &DrawInfo,
@@ -217,79 +726,396 @@ impl ShaderFrame {
     ) {
         use glow::HasContext as _;
         let gl = painter.gl();
-        if let Some(program) = self.program {
-            unsafe {
-                // Egui will have configured the viewport already,
-                // so we don't do that.
+        if info.transition.is_none()
+            && let Some(outgoing) = self.outgoing.take()
+        {
+            self.release(gl, outgoing.program);
+        }
+        if self.current.is_none() {
+            return;
+        }
+        // Egui will have configured the viewport already,
+        // so we don't do that.
+        let view = pci.viewport_in_pixels();
+        let scaled = |size: i32| ((size as f32 * info.render_scale).round() as i32).max(1);
+        let (scaled_width, scaled_height) = (scaled(view.width_px), scaled(view.height_px));
 
-                gl.use_program(Some(program));
+        let warping = info.warp.mode != WarpMode::Off;
+        let downscaling = (scaled_width, scaled_height) != (view.width_px, view.height_px);
+        let feeding_back = self
+            .current
+            .as_ref()
+            .is_some_and(|layer| !layer.feedback_uniform_names.is_empty());
+        if feeding_back && self.feedback.is_none() {
+            let feedback = RenderTarget::new(gl);
+            if let Err(e) = &feedback {
+                log::error!("Error setting up feedback target: {:?}", e);
+            }
+            self.feedback = Some(feedback);
+        }
+        // Feedback is copied from an offscreen render of exactly the shader's size.
+        let offscreen = warping || downscaling || feeding_back;
+        if offscreen && self.warp_pass.is_none() {
+            let warp_pass = WarpPass::new(gl);
+            if let Err(e) = &warp_pass {
+                log::error!("Error setting up output warp: {:?}", e);
+            }
+            self.warp_pass = Some(warp_pass);
+        }
+        // Warping, downscaling and feedback render into an offscreen texture first.
+        let saved_target = match &mut self.warp_pass {
+            Some(Ok(warp_pass)) if offscreen => {
+                Some(warp_pass.begin(gl, scaled_width, scaled_height))
+            }
+            _ => None,
+        };
+        let (width, height) = if saved_target.is_some() {
+            (scaled_width, scaled_height)
+        } else {
+            (view.width_px, view.height_px)
+        };
+        let vp = if saved_target.is_some() {
+            (0, 0, width, height)
+        } else {
+            (
+                view.left_px,
+                view.from_bottom_px,
+                width + view.left_px,
+                view.from_bottom_px + height,
+            )
+        };
+        let current_inputs = LayerInputs {
+            uniforms_values: &info.uniforms_values,
+            textures,
+            data_images: &info.data_images,
+            data_prefix: "",
+            pass_textures: &[],
+            pass_index: None,
+        };
+        let mut pass_targets = std::mem::take(&mut self.pass_targets);
+        let pass_textures = match &self.current {
+            Some(current) => self.draw_passes(
+                painter,
+                current,
+                &mut pass_targets,
+                (width, height),
+                info,
+                &current_inputs,
+            ),
+            None => Vec::new(),
+        };
+        self.pass_targets = pass_targets;
+        let current_inputs = LayerInputs {
+            pass_textures: &pass_textures,
+            ..current_inputs
+        };
 
-                let view = pci.viewport_in_pixels();
-                let scale = pci.pixels_per_point;
+        let transitioning = info.transition.is_some() && self.outgoing.is_some();
+        if transitioning && self.layer_targets.is_none() {
+            let targets = RenderTarget::new(gl).and_then(|a| Ok([a, RenderTarget::new(gl)?]));
+            if let Err(e) = &targets {
+                log::error!("Error setting up transition targets: {:?}", e);
+            }
+            self.layer_targets = Some(targets);
+        }
+        let transition_program = match &info.transition {
+            Some(transition) if transitioning => self.transition_program(gl, &transition.source),
+            _ => None,
+        };
+        let mut layer_targets = self.layer_targets.take();
+        match (
+            &info.transition,
+            &self.outgoing,
+            &mut layer_targets,
+            transition_program,
+        ) {
+            (
+                Some(transition),
+                Some(outgoing),
+                Some(Ok([from_target, to_target])),
+                Some(transition_program),
+            ) => {
+                let saved_layers = SavedTarget::save(gl);
+                let layer_vp = (0, 0, width, height);
+                from_target.bind(gl, width, height);
+                let outgoing_inputs = LayerInputs {
+                    uniforms_values: &transition.uniforms_values,
+                    textures: &transition.textures,
+                    data_images: &transition.data_images,
+                    data_prefix: OUTGOING_PREFIX,
+                    pass_textures: &[],
+                    pass_index: None,
+                };
+                self.draw_layer(painter, outgoing, layer_vp, info, &outgoing_inputs);
+                to_target.bind(gl, width, height);
+                let current = self.current.as_ref().unwrap_or(outgoing);
+                self.draw_layer(painter, current, layer_vp, info, &current_inputs);
+                keep_feedback(&mut self.feedback, current, gl, width, height);
+                saved_layers.restore(gl);
+                unsafe {
+                    gl.use_program(Some(transition_program));
+                    for (unit, (name, texture)) in
+                        [("u_from", from_target.texture), ("u_to", to_target.texture)]
+                            .into_iter()
+                            .enumerate()
+                    {
+                        gl.active_texture(glow::TEXTURE0 + unit as u32);
+                        gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+                        gl.uniform_1_i32(
+                            gl.get_uniform_location(transition_program, name).as_ref(),
+                            unit as i32,
+                        );
+                    }
+                    gl.uniform_1_f32(
+                        gl.get_uniform_location(transition_program, "progress")
+                            .as_ref(),
+                        transition.progress,
+                    );
+                    gl.uniform_1_f32(
+                        gl.get_uniform_location(transition_program, "ratio")
+                            .as_ref(),
+                        width as f32 / height.max(1) as f32,
+                    );
+                    gl.uniform_4_f32(
+                        gl.get_uniform_location(transition_program, "u_viewport")
+                            .as_ref(),
+                        vp.0 as f32,
+                        vp.1 as f32,
+                        width as f32,
+                        height as f32,
+                    );
+                    gl.bind_vertex_array(Some(self.vertex_array));
+                    gl.draw_arrays(glow::TRIANGLES, 0, 6);
+                }
+            }
+            _ => {
+                if let Some(current) = &self.current {
+                    self.draw_layer(painter, current, vp, info, &current_inputs);
+                    if saved_target.is_some() {
+                        keep_feedback(&mut self.feedback, current, gl, width, height);
+                    }
+                }
+            }
+        }
+        self.layer_targets = layer_targets;
+        if let (Some(saved_target), Some(Ok(warp_pass))) = (saved_target, &self.warp_pass) {
+            if warping {
+                warp_pass.end(gl, saved_target, &info.warp);
+            } else {
+                warp_pass.end(gl, saved_target, &WarpSettings::unwarped());
+            }
+        }
+    }
 
-                let vp = (
-                    view.left_px,
-                    view.from_bottom_px,
-                    view.width_px + view.left_px,
-                    view.from_bottom_px + view.height_px,
-                );
-                let mouse = (
-                    info.mouse_x * scale,
-                    view.height_px as f32 - (info.mouse_y * scale),
-                );
+    /// Draw an ISF layer's passes but the last into their targets, returning the targets'
+    /// textures for the last pass to sample.
+    fn draw_passes(
+        &self,
+        painter: &Painter,
+        layer: &Layer,
+        targets: &mut HashMap<String, [RenderTarget; 2]>,
+        (width, height): (i32, i32),
+        info: &DrawInfo,
+        inputs: &LayerInputs<'_>,
+    ) -> Vec<(String, glow::Texture)> {
+        use glow::HasContext as _;
+        let gl = painter.gl();
+        for name in layer.passes.iter().filter_map(|pass| pass.target.as_ref()) {
+            if !targets.contains_key(name) {
+                match RenderTarget::new(gl).and_then(|a| Ok([a, RenderTarget::new(gl)?])) {
+                    Ok(pair) => {
+                        targets.insert(name.clone(), pair);
+                    }
+                    Err(e) => log::error!("Error setting up pass target {}: {:?}", name, e),
+                }
+            }
+        }
+        let mut textures: Vec<(String, glow::Texture)> = targets
+            .iter()
+            .map(|(name, [front, _])| (name.clone(), front.texture))
+            .collect();
+        if layer.passes.is_empty() {
+            return textures;
+        }
+        let saved = SavedTarget::save(gl);
+        let blend = unsafe { gl.is_enabled(glow::BLEND) };
+        unsafe { gl.disable(glow::BLEND) };
+        let last = layer.passes.len() - 1;
+        for (index, pass) in layer.passes.iter().enumerate() {
+            let Some(name) = &pass.target else {
+                continue;
+            };
+            let Some(pair) = targets.get_mut(name) else {
+                continue;
+            };
+            // Drawn into the back target while the front one holds what the pass drew last.
+            let (pass_width, pass_height) = pass.size(width, height);
+            pair[1].bind(gl, pass_width, pass_height);
+            let pass_inputs = LayerInputs {
+                pass_textures: &textures,
+                pass_index: Some(index),
+                ..*inputs
+            };
+            self.draw_layer(
+                painter,
+                layer,
+                (0, 0, pass_width, pass_height),
+                info,
+                &pass_inputs,
+            );
+            pair.swap(0, 1);
+            // The last pass is drawn again to the output, seeing what this one did.
+            if index != last
+                && let Some(texture) = textures.iter_mut().find(|(n, _)| n == name)
+            {
+                texture.1 = pair[0].texture;
+            }
+        }
+        if blend {
+            unsafe { gl.enable(glow::BLEND) };
+        }
+        saved.restore(gl);
+        textures
+    }
 
-                gl.uniform_3_f32(
-                    gl.get_uniform_location(program, "iResolution").as_ref(),
-                    view.width_px as f32,
-                    view.height_px as f32,
-                    1.0,
-                );
-                gl.uniform_4_f32(
-                    gl.get_uniform_location(program, "iViewport").as_ref(),
-                    vp.0 as f32,
-                    vp.1 as f32,
-                    vp.2 as f32,
-                    vp.3 as f32,
-                );
-                gl.uniform_1_f32(
-                    gl.get_uniform_location(program, "iTime").as_ref(),
-                    info.curr_time,
-                );
+    /// Draw a layer's program over `vp` (left, bottom, right, top in pixels of the bound target).
+    fn draw_layer(
+        &self,
+        painter: &Painter,
+        layer: &Layer,
+        vp: (i32, i32, i32, i32),
+        info: &DrawInfo,
+        inputs: &LayerInputs<'_>,
+    ) {
+        use glow::HasContext as _;
+        let gl = painter.gl();
+        let program = layer.program;
+        unsafe {
+            gl.use_program(Some(program));
+            gl.uniform_3_f32(
+                gl.get_uniform_location(program, "iResolution").as_ref(),
+                (vp.2 - vp.0) as f32,
+                (vp.3 - vp.1) as f32,
+                1.0,
+            );
+            gl.uniform_4_f32(
+                gl.get_uniform_location(program, "iViewport").as_ref(),
+                vp.0 as f32,
+                vp.1 as f32,
+                vp.2 as f32,
+                vp.3 as f32,
+            );
+            gl.uniform_1_f32(
+                gl.get_uniform_location(program, "iTime").as_ref(),
+                info.curr_time,
+            );
+            gl.uniform_1_f32(
+                gl.get_uniform_location(program, "iTimeDelta").as_ref(),
+                info.time_delta,
+            );
+            gl.uniform_1_f32(gl.get_uniform_location(program, "iBPM").as_ref(), info.bpm);
+            gl.uniform_1_f32(
+                gl.get_uniform_location(program, "iBeat").as_ref(),
+                info.beat,
+            );
+            gl.uniform_1_f32(gl.get_uniform_location(program, "iBar").as_ref(), info.bar);
+            gl.uniform_3_f32(
+                gl.get_uniform_location(program, "iAudioBands").as_ref(),
+                info.audio_bands[0],
+                info.audio_bands[1],
+                info.audio_bands[2],
+            );
+            gl.uniform_1_i32(
+                gl.get_uniform_location(program, "iFrame").as_ref(),
+                info.frame as i32,
+            );
+            gl.uniform_1_f32(
+                gl.get_uniform_location(program, "iFrameRate").as_ref(),
+                info.fps,
+            );
+            gl.uniform_4_f32(
+                gl.get_uniform_location(program, "iMouse").as_ref(),
+                info.mouse[0],
+                info.mouse[1],
+                info.mouse[2],
+                info.mouse[3],
+            );
+            gl.uniform_2_f32(
+                gl.get_uniform_location(program, "iMouseWheel").as_ref(),
+                info.mouse_wheel[0],
+                info.mouse_wheel[1],
+            );
+            gl.uniform_1_i32(
+                gl.get_uniform_location(program, "iMouseButtons").as_ref(),
+                info.mouse_buttons,
+            );
+            layer.mode.apply(
+                gl,
+                program,
+                &ModeInputs {
+                    resolution: [(vp.2 - vp.0) as f32, (vp.3 - vp.1) as f32],
+                    time: info.curr_time,
+                    time_delta: info.time_delta,
+                    frame: info.frame as i32,
+                    mouse: [info.mouse[0], info.mouse[1]],
+                    pass_index: inputs
+                        .pass_index
+                        .unwrap_or(layer.passes.len().saturating_sub(1))
+                        as i32,
+                },
+            );
+            for (index, name) in layer.sampler_uniform_names.iter().enumerate() {
+                let maybe_native_texture = inputs
+                    .textures
+                    .get(index)
+                    .and_then(|t| t.handle.clone())
+                    .map(|tex| tex.id())
+                    .and_then(|texture_id| painter.texture(texture_id));
+                gl.active_texture(glow::TEXTURE1 + index as u32);
+                match maybe_native_texture {
+                    Some(texture) => gl.bind_texture(glow::TEXTURE_2D, Some(texture)),
+                    None => gl.bind_texture(glow::TEXTURE_2D, None),
+                }
                 gl.uniform_1_i32(
-                    gl.get_uniform_location(program, "iFrame").as_ref(),
-                    info.frame as i32,
+                    gl.get_uniform_location(program, name).as_ref(),
+                    (index + 1) as i32,
                 );
-                gl.uniform_1_f32(
-                    gl.get_uniform_location(program, "iFrameRate").as_ref(),
-                    info.fps,
+            }
+            let first_data_unit = layer.sampler_uniform_names.len().max(4) + 1;
+            for (index, (name, _)) in inputs.data_images.iter().enumerate() {
+                let unit = first_data_unit + index;
+                gl.active_texture(glow::TEXTURE0 + unit as u32);
+                gl.bind_texture(
+                    glow::TEXTURE_2D,
+                    self.data_textures
+                        .get(&format!("{}{}", inputs.data_prefix, name))
+                        .map(|(texture, _)| *texture),
                 );
-                gl.uniform_4_f32(
-                    gl.get_uniform_location(program, "iMouse").as_ref(),
-                    mouse.0,
-                    mouse.1,
-                    info.mouse_down_seconds,
-                    0.0,
+                gl.uniform_1_i32(gl.get_uniform_location(program, name).as_ref(), unit as i32);
+            }
+            let first_feedback_unit = first_data_unit + inputs.data_images.len();
+            for (index, name) in layer.feedback_uniform_names.iter().enumerate() {
+                let unit = first_feedback_unit + index;
+                gl.active_texture(glow::TEXTURE0 + unit as u32);
+                gl.bind_texture(
+                    glow::TEXTURE_2D,
+                    match &self.feedback {
+                        Some(Ok(feedback)) => Some(feedback.texture),
+                        _ => None,
+                    },
                 );
-                for (index, name) in self.sampler_uniform_names.iter().enumerate() {
-                    let maybe_native_texture = textures
-                        .get(index)
-                        .and_then(|t| t.handle.clone())
-                        .map(|tex| tex.id())
-                        .and_then(|texture_id| painter.texture(texture_id));
-                    gl.active_texture(glow::TEXTURE1 + index as u32);
-                    match maybe_native_texture {
-                        Some(texture) => gl.bind_texture(glow::TEXTURE_2D, Some(texture)),
-                        None => gl.bind_texture(glow::TEXTURE_2D, None),
-                    }
-                    gl.uniform_1_i32(
-                        gl.get_uniform_location(program, name).as_ref(),
-                        (index + 1) as i32,
-                    );
-                }
-                info.uniforms_values.apply(painter, gl, program);
-                gl.bind_vertex_array(Some(self.vertex_array));
-                gl.draw_arrays(glow::TRIANGLES, 0, 6);
+                gl.uniform_1_i32(gl.get_uniform_location(program, name).as_ref(), unit as i32);
+            }
+            let first_pass_unit = first_feedback_unit + layer.feedback_uniform_names.len();
+            for (index, (name, texture)) in inputs.pass_textures.iter().enumerate() {
+                let unit = first_pass_unit + index;
+                gl.active_texture(glow::TEXTURE0 + unit as u32);
+                gl.bind_texture(glow::TEXTURE_2D, Some(*texture));
+                gl.uniform_1_i32(gl.get_uniform_location(program, name).as_ref(), unit as i32);
             }
+            inputs.uniforms_values.apply(painter, gl, program);
+            gl.bind_vertex_array(Some(self.vertex_array));
+            gl.draw_arrays(glow::TRIANGLES, 0, 6);
         }
     }
 }
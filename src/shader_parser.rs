@@ -11,23 +11,81 @@ use std::ops::RangeInclusive;
 #[derive(Clone)]
 pub struct PreparseResult {
     pub(crate) uniforms: Vec<UniformInfo>,
+    pub(crate) functions: Vec<String>,
 }
 
 impl PreparseResult {
+    pub fn has_function(&self, name: &str) -> bool {
+        self.functions.iter().any(|f| f == name)
+    }
+
     pub fn sampler_uniform_names(&self) -> Vec<String> {
         self.uniforms
             .iter()
-            .filter_map(|ui| match &ui.spec {
-                UniformSpec::Sampler2D => Some(ui.name.clone()),
+            .filter_map(|ui| match (&ui.spec, &ui.widget, &ui.source) {
+                (UniformSpec::Sampler2D, UniformWidget::Default, None) => Some(ui.name.clone()),
                 _ => None,
             })
             .collect()
     }
+
+    /// Names of samplers bound to a built-in source via pragma rather than to a slot.
+    pub fn sourced_sampler_names(&self, source: UniformSource) -> Vec<String> {
+        self.uniforms
+            .iter()
+            .filter(|ui| ui.source == Some(source))
+            .map(|ui| ui.name.clone())
+            .collect()
+    }
 }
 
 #[derive(Deserialize, Debug)]
 struct UniformPragmaInfo {
     pub range: Option<[f32; 2]>,
+    pub widget: Option<UniformWidget>,
+    pub source: Option<UniformSource>,
+    pub zoom: Option<String>,
+    pub coords: Option<PointCoords>,
+    pub label: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PointCoords {
+    /// 0..1 across the viewport, like `fragCoord / iResolution.xy`
+    #[default]
+    Normalized,
+    /// Pixels from the viewport's bottom-left corner, like `fragCoord`
+    Pixels,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UniformSource {
+    /// 256x3 Shadertoy keyboard texture
+    Keyboard,
+    /// The shader's own output from the previous frame
+    Feedback,
+    /// An ISF pass target, rendered earlier in the frame
+    #[serde(skip)]
+    Pass,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UniformWidget {
+    #[default]
+    Default,
+    /// `sampler2D` fed by a multi-stop gradient baked to a 256x1 texture
+    Gradient,
+    /// `sampler2D` fed by a spline curve baked to a 256x1 texture
+    Curve,
+    /// `vec2` center (with the float named by `zoom`) or `vec4` rect driven by panning and zooming the viewport
+    Viewport,
+    /// `vec2` dragged as a handle on the viewport, in the pragma's `coords`
+    Point,
+    /// `vec3` or `vec4` edited with a color picker whatever its name
+    Color,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -36,6 +94,13 @@ pub struct UniformInfo {
     pub spec: UniformSpec,
     pub smell: UniformSmell,
     pub range: RangeInclusive<f32>,
+    pub widget: UniformWidget,
+    pub source: Option<UniformSource>,
+    /// Name of the float uniform holding the zoom of a viewport-widget center
+    pub zoom: Option<String>,
+    pub coords: PointCoords,
+    /// Shown instead of the name in the uniforms panel
+    pub label: Option<String>,
 }
 
 struct UniformVisitation {
@@ -119,6 +184,7 @@ impl Vec4UniformSpec {
 struct UniformVisitor {
     uniform_visitations: Vec<UniformVisitation>,
     pragma_infos: HashMap<String, UniformPragmaInfo>,
+    functions: Vec<String>,
 }
 
 impl UniformVisitor {
@@ -131,11 +197,22 @@ impl UniformVisitor {
                     .and_then(|upi| upi.range)
                     .unwrap_or([0.0, 1.0])
                     .map(|f| f);
+                let widget: UniformWidget =
+                    upi.and_then(|upi| upi.widget.clone()).unwrap_or_default();
                 UniformInfo {
                     name: uv.name.clone(),
                     spec: uv.spec.clone(),
-                    smell: uv.smell.clone(),
+                    smell: if widget == UniformWidget::Color {
+                        UniformSmell::Color
+                    } else {
+                        uv.smell.clone()
+                    },
                     range: min..=max,
+                    widget,
+                    source: upi.and_then(|upi| upi.source),
+                    zoom: upi.and_then(|upi| upi.zoom.clone()),
+                    coords: upi.and_then(|upi| upi.coords).unwrap_or_default(),
+                    label: upi.and_then(|upi| upi.label.clone()),
                 }
             })
             .collect()
@@ -159,8 +236,8 @@ impl Visitor for UniformVisitor {
             // eprintln!("{:#?}", declaration);
             let typ = &declaration.ty.ty;
             if let Some(idfr) = &declaration.name {
-                if typ.array_specifier.is_some() {
-                    eprintln!("Array uniforms are not supported yet");
+                if typ.array_specifier.is_some() || declaration.array_specifier.is_some() {
+                    log::warn!("Array uniforms are not supported yet: {}", idfr);
                     return Visit::Parent;
                 }
                 let name = idfr.clone().to_string();
@@ -222,7 +299,7 @@ impl Visitor for UniformVisitor {
                         });
                     }
                     _ => {
-                        eprintln!("Unsupported uniform type for {}: {:?}", name, typ.ty);
+                        log::warn!("Unsupported uniform type for {}: {:?}", name, typ.ty);
                     }
                 }
             }
@@ -230,19 +307,22 @@ impl Visitor for UniformVisitor {
 
         Visit::Parent
     }
-    fn visit_function_definition(&mut self, _: &FunctionDefinition) -> Visit {
+    fn visit_function_definition(&mut self, fd: &FunctionDefinition) -> Visit {
+        self.functions.push(fd.prototype.name.to_string());
         Visit::Parent
     }
     fn visit_preprocessor_pragma(&mut self, pragma: &PreprocessorPragma) -> Visit {
-        if pragma.command.starts_with("@") {
-            if let Some((name, rest)) = pragma.command[1..].split_once(' ') {
-                match serde_json5::from_str::<UniformPragmaInfo>(rest) {
-                    Ok(upi) => {
-                        self.pragma_infos.insert(name.to_string(), upi);
-                    }
-                    Err(e) => {
-                        eprintln!("Error parsing pragma {:?}: {:?}", pragma.command, e);
-                    }
+        // `@define` pragmas are for `defines`.
+        if pragma.command.starts_with("@")
+            && let Some((name, rest)) = pragma.command[1..].split_once(' ')
+            && name != "define"
+        {
+            match serde_json5::from_str::<UniformPragmaInfo>(rest) {
+                Ok(upi) => {
+                    self.pragma_infos.insert(name.to_string(), upi);
+                }
+                Err(e) => {
+                    log::warn!("Error parsing pragma {:?}: {:?}", pragma.command, e);
                 }
             }
         }
@@ -251,29 +331,31 @@ impl Visitor for UniformVisitor {
 }
 
 fn default_vec_from_declaration(decl: &SingleDeclaration) -> Option<Vec<f32>> {
-    if let Some(Initializer::Simple(si)) = &decl.initializer {
-        if let Expr::FunCall(_fi, args) = si.as_ref() {
-            // TODO: check _fi for vec call...
-            let mut vec = Vec::new();
-            for arg in args {
-                match arg {
-                    Expr::IntConst(i) => vec.push(*i as f32),
-                    Expr::FloatConst(f) => vec.push(*f),
-                    _ => {
-                        eprintln!(
-                            "Unsupported initializer call element for {:?}: {:?}",
-                            decl.name, si
-                        );
-                        return None;
-                    }
+    if let Some(Initializer::Simple(si)) = &decl.initializer
+        && let Expr::FunCall(_fi, args) = si.as_ref()
+    {
+        // TODO: check _fi for vec call...
+        let mut vec = Vec::new();
+        for arg in args {
+            match arg {
+                Expr::IntConst(i) => vec.push(*i as f32),
+                Expr::FloatConst(f) => vec.push(*f),
+                _ => {
+                    log::warn!(
+                        "Unsupported initializer call element for {:?}: {:?}",
+                        decl.name,
+                        si
+                    );
+                    return None;
                 }
             }
-            return Some(vec);
         }
+        return Some(vec);
     }
-    eprintln!(
+    log::warn!(
         "Unsupported initializer for {:?}: {:?}",
-        decl.name, decl.initializer
+        decl.name,
+        decl.initializer
     );
     None
 }
@@ -300,9 +382,10 @@ fn default_number_from_declaration(decl: &SingleDeclaration) -> Option<f32> {
             _ => {}
         }
     }
-    eprintln!(
+    log::warn!(
         "Unsupported initializer for {:?}: {:?}",
-        decl.name, decl.initializer
+        decl.name,
+        decl.initializer
     );
     None
 }
@@ -313,5 +396,6 @@ pub fn preparse_shader(source: &str) -> eyre::Result<PreparseResult> {
     stage.visit(&mut visitor);
     Ok(PreparseResult {
         uniforms: visitor.bake(),
+        functions: visitor.functions,
     })
 }
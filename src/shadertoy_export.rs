@@ -0,0 +1,353 @@
+use crate::beat_clock::BeatClock;
+use crate::prelude::{PreludeMode, declared_uniform, mentions_identifier, replace_identifier};
+use crate::shader_parser::{PreparseResult, UniformSource, UniformSpec, UniformWidget};
+use crate::uniforms_values::UniformsValues;
+use crate::viewport_nav::{ViewportState, split_f64};
+use egui::ahash::HashMap;
+
+/// Shadertoy's sampler inputs.
+const CHANNELS: usize = 4;
+
+/// A Shadertoy-style shader as code for Shadertoy's Image tab: uniforms become constants with
+/// their current values, samplers `iChannel0..3` in slot order, and our own builtins defines.
+pub(crate) fn export(
+    source: &str,
+    ppr: &PreparseResult,
+    values: &UniformsValues,
+    beat_clock: &BeatClock,
+    mode: PreludeMode,
+) -> eyre::Result<String> {
+    if mode != PreludeMode::Shadertoy {
+        eyre::bail!(
+            "Only Shadertoy-style shaders can be exported to Shadertoy, not {:?} ones",
+            mode
+        );
+    }
+    let viewport_values = viewport_values(values);
+    // What replaces the uniforms' declarations, by name.
+    let mut replacements: Vec<(&str, String)> = Vec::new();
+    for uniform in &ppr.uniforms {
+        let name = uniform.name.as_str();
+        let current = viewport_values.get(name);
+        let constant = match &uniform.spec {
+            UniformSpec::Int(spec) => format!(
+                "const int {} = {};",
+                name,
+                values
+                    .int_values
+                    .get(name)
+                    .copied()
+                    .unwrap_or(spec.certain_default())
+            ),
+            UniformSpec::Float(spec) => format!(
+                "const float {} = {};",
+                name,
+                glsl_vector(
+                    current
+                        .cloned()
+                        .or_else(|| values.float_values.get(name).map(|v| vec![*v]))
+                        .unwrap_or_else(|| vec![spec.certain_default()])
+                )
+            ),
+            UniformSpec::Vec2(spec) => format!(
+                "const vec2 {} = {};",
+                name,
+                glsl_vector(
+                    current
+                        .cloned()
+                        .or_else(|| values.vec2_values.get(name).map(|v| v.to_vec()))
+                        .unwrap_or_else(|| spec.certain_default().to_vec())
+                )
+            ),
+            UniformSpec::Vec3(spec) => format!(
+                "const vec3 {} = {};",
+                name,
+                glsl_vector(
+                    values
+                        .vec3_values
+                        .get(name)
+                        .map_or(spec.certain_default().to_vec(), |v| v.to_vec())
+                )
+            ),
+            UniformSpec::Vec4(spec) => format!(
+                "const vec4 {} = {};",
+                name,
+                glsl_vector(
+                    current
+                        .cloned()
+                        .or_else(|| values.vec4_values.get(name).map(|v| v.to_vec()))
+                        .unwrap_or_else(|| spec.certain_default().to_vec())
+                )
+            ),
+            // Shadertoy declares the channels.
+            UniformSpec::Sampler2D => String::new(),
+        };
+        replacements.push((name, constant));
+    }
+
+    let mut channels: Vec<(&str, String)> = ppr
+        .sampler_uniform_names()
+        .iter()
+        .enumerate()
+        .filter_map(|(slot, name)| {
+            let uniform = ppr.uniforms.iter().find(|u| &u.name == name)?;
+            Some((uniform.name.as_str(), format!("slot {}", slot + 1)))
+        })
+        .collect();
+    for uniform in &ppr.uniforms {
+        let note = match (&uniform.spec, uniform.source, &uniform.widget) {
+            (UniformSpec::Sampler2D, Some(UniformSource::Keyboard), _) => "Keyboard",
+            (UniformSpec::Sampler2D, Some(UniformSource::Feedback), _) => {
+                "the shader's previous frame, from a Buffer running it"
+            }
+            (UniformSpec::Sampler2D, _, UniformWidget::Gradient | UniformWidget::Curve) => {
+                "a texture of the ramp"
+            }
+            _ => continue,
+        };
+        channels.push((uniform.name.as_str(), note.to_owned()));
+    }
+    if channels.len() > CHANNELS {
+        log::warn!(
+            "Shadertoy has {} channels but the shader samples {}; the rest keep their names",
+            CHANNELS,
+            channels.len()
+        );
+    }
+
+    let mut body = String::new();
+    for line in source.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("#line") || trimmed.starts_with("#version") {
+            continue;
+        }
+        if let Some(rest) = trimmed.strip_prefix("#pragma") {
+            let rest = rest.trim_start();
+            if rest.starts_with('@') || rest.starts_with("mode") {
+                continue;
+            }
+        }
+        if let Some(name) = declared_uniform(line)
+            && let Some((_, replacement)) = replacements.iter().find(|(n, _)| *n == name)
+        {
+            if !replacement.is_empty() {
+                body.push_str(replacement);
+                body.push('\n');
+            }
+            continue;
+        }
+        body.push_str(line);
+        body.push('\n');
+    }
+    let mut header = String::new();
+    for (index, (name, note)) in channels.iter().take(CHANNELS).enumerate() {
+        let channel = format!("iChannel{}", index);
+        header.push_str(&format!("// {}: {} ({})\n", channel, note, name));
+        body = replace_identifier(&body, name, &channel);
+    }
+    for (name, stand_in) in builtin_stand_ins(values, beat_clock) {
+        if mentions_identifier(&body, name) {
+            header.push_str(&format!("#define {} {}\n", name, stand_in));
+        }
+    }
+    if !header.is_empty() {
+        header.push('\n');
+    }
+    Ok(header + &body)
+}
+
+/// The current values of viewport-widget uniforms and the uniforms they drive.
+fn viewport_values(values: &UniformsValues) -> HashMap<String, Vec<f32>> {
+    let mut result = HashMap::default();
+    for (name, state) in &values.viewport_values {
+        let (high, low): (Vec<f32>, Vec<f32>) = match state {
+            ViewportState::CenterZoom {
+                center,
+                zoom,
+                zoom_uniform,
+            } => {
+                if let Some(zoom_uniform) = zoom_uniform {
+                    result.insert(zoom_uniform.clone(), vec![*zoom as f32]);
+                }
+                center.iter().copied().map(split_f64).unzip()
+            }
+            ViewportState::Rect(rect) => rect.iter().copied().map(split_f64).unzip(),
+        };
+        result.insert(name.clone(), high);
+        result.insert(format!("{}_lo", name), low);
+    }
+    result
+}
+
+/// Our builtins that Shadertoy doesn't have, as they are now.
+fn builtin_stand_ins(
+    values: &UniformsValues,
+    beat_clock: &BeatClock,
+) -> Vec<(&'static str, String)> {
+    let camera = values.camera.clone().unwrap_or_default();
+    let beats_per_second = beat_clock.bpm() as f32 / 60.0;
+    vec![
+        ("iViewport", "vec4(0.0, 0.0, iResolution.xy)".to_owned()),
+        ("iBPM", glsl_vector(vec![beat_clock.bpm() as f32])),
+        (
+            "iBeat",
+            format!("(iTime * {})", glsl_vector(vec![beats_per_second])),
+        ),
+        (
+            "iBar",
+            format!(
+                "(iTime * {})",
+                glsl_vector(vec![
+                    beats_per_second / beat_clock.beats_per_bar.max(1) as f32
+                ])
+            ),
+        ),
+        ("iAudioBands", "vec3(0.0)".to_owned()),
+        ("iCameraPos", glsl_vector(camera.position.to_vec())),
+        ("iCameraTarget", glsl_vector(camera.target.to_vec())),
+        (
+            "iCameraMatrix",
+            format!(
+                "mat3({})",
+                camera
+                    .matrix()
+                    .iter()
+                    .map(|v| format!("{:?}", v))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        ),
+        ("iFov", glsl_vector(vec![camera.fov.to_radians()])),
+        ("iMouseWheel", "vec2(0.0)".to_owned()),
+        ("iMouseButtons", "(iMouse.z > 0.0 ? 1 : 0)".to_owned()),
+    ]
+}
+
+/// A float, or a `vecN` of `components`.
+fn glsl_vector(components: Vec<f32>) -> String {
+    let components: Vec<String> = components.iter().map(|v| format!("{:?}", v)).collect();
+    match components.len() {
+        1 => components[0].clone(),
+        n => format!("vec{}({})", n, components.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shader_parser::preparse_shader;
+
+    const SOURCE: &str = "#version 300 es
+#pragma mode shadertoy
+// Fractal
+#pragma @center {widget: \"viewport\", zoom: \"zoom\"}
+uniform vec2 center;
+uniform vec2 center_lo;
+uniform float zoom = 1.0;
+#pragma @brightness {range: [0, 2]}
+uniform float brightness = 1.0;
+uniform int iterations = 64;
+uniform vec3 tint = vec3(1.0, 0.5, 0.25);
+uniform sampler2D palette;
+uniform sampler2D noise;
+#pragma @keys {source: \"keyboard\"}
+uniform sampler2D keys;
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 paletteUv = fragCoord / iResolution.xy;
+    vec3 c = texture(palette, paletteUv).rgb * texture(noise, center + center_lo).r;
+    float pressed = texelFetch(keys, ivec2(32, 0), 0).x;
+    fragColor = vec4(c * tint * brightness * zoom + pressed * float(iterations) + iBeat, 1.0);
+}
+";
+
+    fn export_shadertoy(source: &str, values: &UniformsValues) -> eyre::Result<String> {
+        let ppr = preparse_shader(source).unwrap();
+        export(
+            source,
+            &ppr,
+            values,
+            &BeatClock::default(),
+            PreludeMode::Shadertoy,
+        )
+    }
+
+    #[test]
+    fn bakes_values_and_renames_channels() {
+        let mut values = UniformsValues::default();
+        values.float_values.insert("brightness".into(), 1.5);
+        values.vec3_values.insert("tint".into(), [0.0, 0.5, 1.0]);
+        values.viewport_values.insert(
+            "center".into(),
+            ViewportState::CenterZoom {
+                center: [-0.5, 0.25],
+                zoom: 4.0,
+                zoom_uniform: Some("zoom".into()),
+            },
+        );
+        assert_eq!(
+            export_shadertoy(SOURCE, &values).unwrap(),
+            "// iChannel0: slot 1 (palette)
+// iChannel1: slot 2 (noise)
+// iChannel2: Keyboard (keys)
+#define iBeat (iTime * 2.0)
+
+// Fractal
+const vec2 center = vec2(-0.5, 0.25);
+const vec2 center_lo = vec2(0.0, 0.0);
+const float zoom = 4.0;
+const float brightness = 1.5;
+const int iterations = 64;
+const vec3 tint = vec3(0.0, 0.5, 1.0);
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 paletteUv = fragCoord / iResolution.xy;
+    vec3 c = texture(iChannel0, paletteUv).rgb * texture(iChannel1, center + center_lo).r;
+    float pressed = texelFetch(iChannel2, ivec2(32, 0), 0).x;
+    fragColor = vec4(c * tint * brightness * zoom + pressed * float(iterations) + iBeat, 1.0);
+}
+"
+        );
+    }
+
+    #[test]
+    fn defaults_stand_in_for_unset_values() {
+        let exported = export_shadertoy(SOURCE, &UniformsValues::default()).unwrap();
+        assert!(exported.contains("const float brightness = 1.0;\n"));
+        assert!(exported.contains("const vec3 tint = vec3(1.0, 0.5, 0.25);\n"));
+        assert!(exported.contains("const float zoom = 1.0;\n"));
+    }
+
+    #[test]
+    fn channels_beyond_four_keep_their_names() {
+        let source = "uniform sampler2D a;
+uniform sampler2D b;
+uniform sampler2D c;
+uniform sampler2D d;
+uniform sampler2D e;
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    fragColor = texture(a, fragCoord) + texture(b, fragCoord) + texture(c, fragCoord)
+        + texture(d, fragCoord) + texture(e, fragCoord);
+}
+";
+        let exported = export_shadertoy(source, &UniformsValues::default()).unwrap();
+        assert!(exported.contains("texture(iChannel3, fragCoord) + texture(e, fragCoord)"));
+        assert!(!exported.contains("iChannel4"));
+        assert!(!exported.contains("uniform sampler2D"));
+    }
+
+    #[test]
+    fn only_shadertoy_shaders_export() {
+        let source = "uniform float u_time;\nvoid main() { gl_FragColor = vec4(u_time); }\n";
+        let ppr = preparse_shader(source).unwrap();
+        let result = export(
+            source,
+            &ppr,
+            &UniformsValues::default(),
+            &BeatClock::default(),
+            PreludeMode::GlslCanvas,
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use egui::{Context, Key, KeyboardShortcut, Modifiers};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    ResetTime,
+    Pause,
+    Screenshot,
+    TogglePanels,
+    Presentation,
+    WarpHandles,
+}
+
+impl Action {
+    const ALL: [Action; 6] = [
+        Action::ResetTime,
+        Action::Pause,
+        Action::Screenshot,
+        Action::TogglePanels,
+        Action::Presentation,
+        Action::WarpHandles,
+    ];
+
+    /// The name used in the config file.
+    fn name(self) -> &'static str {
+        match self {
+            Action::ResetTime => "reset_time",
+            Action::Pause => "pause",
+            Action::Screenshot => "screenshot",
+            Action::TogglePanels => "toggle_panels",
+            Action::Presentation => "presentation",
+            Action::WarpHandles => "warp_handles",
+        }
+    }
+
+    fn default_shortcut(self) -> KeyboardShortcut {
+        let key = match self {
+            Action::ResetTime => Key::F5,
+            Action::Pause => Key::F6,
+            Action::Screenshot => Key::F12,
+            Action::TogglePanels => Key::F10,
+            Action::Presentation => Key::F11,
+            Action::WarpHandles => Key::F2,
+        };
+        KeyboardShortcut::new(Modifiers::NONE, key)
+    }
+}
+
+pub struct Shortcuts(HashMap<Action, KeyboardShortcut>);
+
+impl Shortcuts {
+    /// The default shortcuts, overridden by `keys` from the config.
+    pub fn new(keys: &HashMap<String, String>) -> Self {
+        let mut shortcuts: HashMap<Action, KeyboardShortcut> = Action::ALL
+            .iter()
+            .map(|action| (*action, action.default_shortcut()))
+            .collect();
+        for (name, text) in keys {
+            let Some(action) = Action::ALL.iter().find(|action| action.name() == name) else {
+                log::warn!("Unknown action in config keys: {:?}", name);
+                continue;
+            };
+            match parse_shortcut(text) {
+                Some(shortcut) => {
+                    shortcuts.insert(*action, shortcut);
+                }
+                None => log::warn!("Bad shortcut for {}: {:?}", name, text),
+            }
+        }
+        Self(shortcuts)
+    }
+
+    /// Whether the action's shortcut was pressed this frame; never while typing into a field.
+    pub fn pressed(&self, ctx: &Context, action: Action) -> bool {
+        if ctx.wants_keyboard_input() {
+            return false;
+        }
+        let shortcut = self.0[&action];
+        ctx.input_mut(|i| i.consume_shortcut(&shortcut))
+    }
+
+    pub fn format(&self, ctx: &Context, action: Action) -> String {
+        ctx.format_shortcut(&self.0[&action])
+    }
+}
+
+/// `"F5"`, `"Ctrl+R"`, `"Shift+Alt+S"` and the like.
+fn parse_shortcut(text: &str) -> Option<KeyboardShortcut> {
+    let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+    let key = Key::from_name(parts.pop()?)?;
+    let mut modifiers = Modifiers::NONE;
+    for part in parts {
+        modifiers |= match part.to_lowercase().as_str() {
+            "ctrl" | "control" => Modifiers::CTRL,
+            "cmd" | "command" => Modifiers::COMMAND,
+            "alt" | "option" => Modifiers::ALT,
+            "shift" => Modifiers::SHIFT,
+            _ => return None,
+        };
+    }
+    Some(KeyboardShortcut::new(modifiers, key))
+}
@@ -0,0 +1,307 @@
+#![allow(clippy::undocumented_unsafe_blocks)]
+
+use crate::gl::compile_program_with_prelude;
+use crate::prelude::PreludeMode;
+use crate::uniforms_values::UniformsValues;
+use eframe::egui_glow::Painter;
+use eframe::glow;
+use eframe::glow::HasContext as _;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+const SOUND_PRELUDE: &str = concat!(
+    include_str!("builtin_uniforms.glsl"),
+    include_str!("sound_prelude.glsl")
+);
+
+/// Samples are rendered in square blocks of this size, like Shadertoy does.
+const BLOCK_WIDTH: usize = 512;
+const BLOCK_SAMPLES: usize = BLOCK_WIDTH * BLOCK_WIDTH;
+/// Bytes of a block read back as RGBA floats.
+const BLOCK_BYTES: usize = BLOCK_SAMPLES * 4 * size_of::<f32>();
+
+pub const SAMPLE_RATES: [u32; 4] = [22050, 44100, 48000, 96000];
+
+#[derive(Clone)]
+pub struct SoundRenderRequest {
+    /// The source with its defines set, as compiled for the image.
+    pub fragment_source: String,
+    /// Mode for a `main` that could be either, as for the image.
+    pub default_mode: Option<PreludeMode>,
+    pub uniforms_values: UniformsValues,
+    pub sample_rate: u32,
+    pub duration: f32,
+    pub output_path: PathBuf,
+    pub response_sender: Sender<SoundRenderUpdate>,
+}
+
+pub struct SoundRenderResponse {
+    pub duration: Duration,
+    pub result: eyre::Result<PathBuf>,
+}
+
+pub enum SoundRenderUpdate {
+    /// Fraction of the samples rendered so far.
+    Progress(f32),
+    Done(SoundRenderResponse),
+}
+
+/// A render of a shader's `mainSound` to a stereo 16-bit WAV, a block per frame.
+///
+/// Each block is read back into a pixel buffer without waiting for the GPU, and collected on the
+/// next frame, by when the GPU has usually finished it.
+pub(crate) struct SoundRender {
+    request: SoundRenderRequest,
+    started: Instant,
+    program: glow::Program,
+    vertex_array: glow::VertexArray,
+    texture: glow::Texture,
+    framebuffer: glow::Framebuffer,
+    pack_buffer: glow::Buffer,
+    /// How many samples of the block being read back are wanted.
+    pending: Option<usize>,
+    total_samples: usize,
+    block_offset: usize,
+    /// Interleaved stereo samples rendered so far.
+    samples: Vec<f32>,
+}
+
+#[allow(unsafe_code)]
+impl SoundRender {
+    /// Set up the render; failures are reported to the requester.
+    pub(crate) fn start(painter: &Painter, request: &SoundRenderRequest) -> Option<Self> {
+        let started = Instant::now();
+        match Self::new(painter.gl(), request, started) {
+            Ok(render) => Some(render),
+            Err(e) => {
+                send_done(request, started, Err(e));
+                None
+            }
+        }
+    }
+
+    fn new(
+        gl: &glow::Context,
+        request: &SoundRenderRequest,
+        started: Instant,
+    ) -> eyre::Result<Self> {
+        let total_samples =
+            (request.duration.max(0.0) * request.sample_rate as f32).ceil() as usize;
+        let mode = PreludeMode::detect(&request.fragment_source, request.default_mode);
+        if mode != PreludeMode::Shadertoy {
+            eyre::bail!(
+                "Only Shadertoy-style shaders have a mainSound, not {:?} ones",
+                mode
+            );
+        }
+        let source = mode.adapt_source(&mode.expand_source(&request.fragment_source));
+        let program = compile_program_with_prelude(gl, SOUND_PRELUDE, &source)?;
+        unsafe {
+            let old_framebuffer = gl.get_parameter_framebuffer(glow::FRAMEBUFFER_BINDING);
+            let vertex_array = gl.create_vertex_array().map_err(|e| eyre::eyre!(e))?;
+            let texture = gl.create_texture().map_err(|e| eyre::eyre!(e))?;
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                glow::RGBA32F as i32,
+                BLOCK_WIDTH as i32,
+                BLOCK_WIDTH as i32,
+                0,
+                glow::RGBA,
+                glow::FLOAT,
+                glow::PixelUnpackData::Slice(None),
+            );
+            let framebuffer = gl.create_framebuffer().map_err(|e| eyre::eyre!(e))?;
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+            gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                Some(texture),
+                0,
+            );
+            let complete =
+                gl.check_framebuffer_status(glow::FRAMEBUFFER) == glow::FRAMEBUFFER_COMPLETE;
+            gl.bind_framebuffer(glow::FRAMEBUFFER, old_framebuffer);
+            let pack_buffer = gl.create_buffer().map_err(|e| eyre::eyre!(e))?;
+            gl.bind_buffer(glow::PIXEL_PACK_BUFFER, Some(pack_buffer));
+            gl.buffer_data_size(
+                glow::PIXEL_PACK_BUFFER,
+                BLOCK_BYTES as i32,
+                glow::STREAM_READ,
+            );
+            gl.bind_buffer(glow::PIXEL_PACK_BUFFER, None);
+            let render = Self {
+                request: request.clone(),
+                started,
+                program,
+                vertex_array,
+                texture,
+                framebuffer,
+                pack_buffer,
+                pending: None,
+                total_samples,
+                block_offset: 0,
+                samples: Vec::with_capacity(total_samples * 2),
+            };
+            if !complete {
+                render.destroy(gl);
+                return Err(eyre::eyre!(
+                    "Float render targets are not supported by this GL implementation"
+                ));
+            }
+            Ok(render)
+        }
+    }
+
+    /// Collect the block read back last, render the next, and write the WAV after the last;
+    /// returns whether it's done.
+    pub(crate) fn step(&mut self, painter: &Painter) -> bool {
+        if let Some(count) = self.pending.take() {
+            self.collect_block(painter.gl(), count);
+        }
+        if self.block_offset < self.total_samples {
+            self.render_block(painter);
+            self.request
+                .response_sender
+                .send(SoundRenderUpdate::Progress(
+                    self.samples.len() as f32 / 2.0 / self.total_samples as f32,
+                ))
+                .ok();
+            return false;
+        }
+        let result = write_wav(
+            &self.request.output_path,
+            self.request.sample_rate,
+            &self.samples,
+        )
+        .map(|()| self.request.output_path.clone());
+        send_done(&self.request, self.started, result);
+        true
+    }
+
+    /// Render the block at `block_offset` and start reading it back into `pack_buffer`.
+    fn render_block(&mut self, painter: &Painter) {
+        let gl = painter.gl();
+        let program = self.program;
+        let sample_rate = self.request.sample_rate;
+        unsafe {
+            let old_framebuffer = gl.get_parameter_framebuffer(glow::FRAMEBUFFER_BINDING);
+            let mut old_viewport = [0; 4];
+            gl.get_parameter_i32_slice(glow::VIEWPORT, &mut old_viewport);
+
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer));
+            gl.disable(glow::BLEND);
+            gl.disable(glow::SCISSOR_TEST);
+            gl.viewport(0, 0, BLOCK_WIDTH as i32, BLOCK_WIDTH as i32);
+            gl.use_program(Some(program));
+            self.request.uniforms_values.apply(painter, gl, program);
+            gl.bind_vertex_array(Some(self.vertex_array));
+            gl.uniform_1_f32(
+                gl.get_uniform_location(program, "iSampleRate").as_ref(),
+                sample_rate as f32,
+            );
+            gl.uniform_1_f32(
+                gl.get_uniform_location(program, "iBlockOffset").as_ref(),
+                self.block_offset as f32 / sample_rate as f32,
+            );
+            gl.uniform_1_i32(
+                gl.get_uniform_location(program, "iBlockSampleOffset")
+                    .as_ref(),
+                self.block_offset as i32,
+            );
+            gl.uniform_1_i32(
+                gl.get_uniform_location(program, "iBlockWidth").as_ref(),
+                BLOCK_WIDTH as i32,
+            );
+            gl.draw_arrays(glow::TRIANGLES, 0, 6);
+            gl.bind_buffer(glow::PIXEL_PACK_BUFFER, Some(self.pack_buffer));
+            gl.read_pixels(
+                0,
+                0,
+                BLOCK_WIDTH as i32,
+                BLOCK_WIDTH as i32,
+                glow::RGBA,
+                glow::FLOAT,
+                glow::PixelPackData::BufferOffset(0),
+            );
+            gl.bind_buffer(glow::PIXEL_PACK_BUFFER, None);
+
+            gl.bind_framebuffer(glow::FRAMEBUFFER, old_framebuffer);
+            gl.viewport(
+                old_viewport[0],
+                old_viewport[1],
+                old_viewport[2],
+                old_viewport[3],
+            );
+            let count = (self.total_samples - self.block_offset).min(BLOCK_SAMPLES);
+            self.pending = Some(count);
+            self.block_offset += BLOCK_SAMPLES;
+        }
+    }
+
+    /// Append the first `count` stereo samples of the block in `pack_buffer`.
+    fn collect_block(&mut self, gl: &glow::Context, count: usize) {
+        unsafe {
+            gl.bind_buffer(glow::PIXEL_PACK_BUFFER, Some(self.pack_buffer));
+            let data = gl.map_buffer_range(
+                glow::PIXEL_PACK_BUFFER,
+                0,
+                BLOCK_BYTES as i32,
+                glow::MAP_READ_BIT,
+            );
+            if data.is_null() {
+                log::error!("Couldn't map the sound block for reading");
+                self.samples.resize(self.samples.len() + count * 2, 0.0);
+            } else {
+                let block = std::slice::from_raw_parts(data, BLOCK_BYTES);
+                for texel in block.chunks_exact(4 * size_of::<f32>()).take(count) {
+                    for channel in texel.chunks_exact(size_of::<f32>()).take(2) {
+                        self.samples.push(f32::from_ne_bytes([
+                            channel[0], channel[1], channel[2], channel[3],
+                        ]));
+                    }
+                }
+                gl.unmap_buffer(glow::PIXEL_PACK_BUFFER);
+            }
+            gl.bind_buffer(glow::PIXEL_PACK_BUFFER, None);
+        }
+    }
+
+    pub(crate) fn destroy(&self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_buffer(self.pack_buffer);
+            gl.delete_vertex_array(self.vertex_array);
+            gl.delete_framebuffer(self.framebuffer);
+            gl.delete_texture(self.texture);
+            gl.delete_program(self.program);
+        }
+    }
+}
+
+fn send_done(request: &SoundRenderRequest, started: Instant, result: eyre::Result<PathBuf>) {
+    request
+        .response_sender
+        .send(SoundRenderUpdate::Done(SoundRenderResponse {
+            duration: started.elapsed(),
+            result,
+        }))
+        .ok();
+}
+
+fn write_wav(path: &Path, sample_rate: u32, samples: &[f32]) -> eyre::Result<()> {
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for sample in samples {
+        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
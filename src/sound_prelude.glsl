@@ -0,0 +1,16 @@
+uniform float iSampleRate;
+uniform float iBlockOffset;
+uniform int iBlockSampleOffset;
+uniform int iBlockWidth;
+
+out vec4 sound_out_sample;
+
+vec2 mainSound(int samp, float time);
+
+void main() {
+    ivec2 texel = ivec2(gl_FragCoord.xy);
+    int blockSample = texel.y * iBlockWidth + texel.x;
+    int samp = iBlockSampleOffset + blockSample;
+    vec2 value = mainSound(samp, iBlockOffset + float(blockSample) / iSampleRate);
+    sound_out_sample = vec4(value, 0.0, 1.0);
+}
@@ -0,0 +1,152 @@
+use crate::config::RecordingConfig;
+use crate::shader_parser::PreparseResult;
+use crate::sound::{SAMPLE_RATES, SoundRenderResponse, SoundRenderUpdate};
+use egui::{Context, DragValue, RichText};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+pub struct SoundRenderSettings {
+    pub sample_rate: u32,
+    pub duration: f32,
+    pub output_path: PathBuf,
+}
+
+pub struct SoundWindow {
+    pub open: bool,
+    sample_rate: u32,
+    duration: f32,
+    output_path: String,
+    /// Where output paths are suggested, instead of next to the shader.
+    output_dir: Option<PathBuf>,
+    pending: bool,
+    /// Fraction of the pending render done.
+    progress: f32,
+    pub(crate) inbox: mpsc::Receiver<SoundRenderUpdate>,
+    pub(crate) outbox: mpsc::Sender<SoundRenderUpdate>,
+    last_response: Option<SoundRenderResponse>,
+}
+
+impl Default for SoundWindow {
+    fn default() -> Self {
+        let (outbox, inbox) = mpsc::channel();
+        Self {
+            open: false,
+            sample_rate: 44100,
+            duration: 10.0,
+            output_path: String::new(),
+            output_dir: None,
+            pending: false,
+            progress: 0.0,
+            inbox,
+            outbox,
+            last_response: None,
+        }
+    }
+}
+
+impl SoundWindow {
+    pub fn new(recording: &RecordingConfig) -> Self {
+        let defaults = Self::default();
+        Self {
+            sample_rate: recording.sample_rate.unwrap_or(defaults.sample_rate),
+            duration: recording.duration.unwrap_or(defaults.duration),
+            output_dir: recording.sound_dir.clone(),
+            ..defaults
+        }
+    }
+
+    /// Returns settings for a new render when the user asked for one.
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        shader_path: Option<&Path>,
+        ppr: Option<&PreparseResult>,
+    ) -> Option<SoundRenderSettings> {
+        while let Ok(update) = self.inbox.try_recv() {
+            match update {
+                SoundRenderUpdate::Progress(progress) => self.progress = progress,
+                SoundRenderUpdate::Done(response) => {
+                    self.pending = false;
+                    self.last_response = Some(response);
+                }
+            }
+        }
+        if self.pending {
+            // Renders advance a few blocks per frame.
+            ctx.request_repaint();
+        }
+        if self.output_path.is_empty()
+            && let Some(shader_path) = shader_path
+        {
+            let path = shader_path.with_extension("wav");
+            let path = match (&self.output_dir, path.file_name()) {
+                (Some(dir), Some(name)) => dir.join(name),
+                _ => path,
+            };
+            self.output_path = path.to_string_lossy().to_string();
+        }
+        let has_main_sound = ppr.is_some_and(|ppr| ppr.has_function("mainSound"));
+        let mut settings = None;
+        egui::Window::new("Render sound")
+            .open(&mut self.open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if !has_main_sound {
+                    ui.label(
+                        "The current shader doesn't define `vec2 mainSound(int samp, float time)`.",
+                    );
+                }
+                egui::ComboBox::new("sound_sample_rate", "Sample rate")
+                    .selected_text(format!("{} Hz", self.sample_rate))
+                    .show_ui(ui, |ui| {
+                        for rate in SAMPLE_RATES {
+                            ui.selectable_value(
+                                &mut self.sample_rate,
+                                rate,
+                                format!("{} Hz", rate),
+                            );
+                        }
+                    });
+                ui.add(
+                    DragValue::new(&mut self.duration)
+                        .range(0.1..=600.0)
+                        .speed(0.1)
+                        .prefix("Duration: ")
+                        .suffix(" s"),
+                );
+                ui.horizontal(|ui| {
+                    ui.label("Output");
+                    ui.text_edit_singleline(&mut self.output_path);
+                });
+                let can_render = has_main_sound && !self.pending && !self.output_path.is_empty();
+                if ui
+                    .add_enabled(can_render, egui::Button::new("Render"))
+                    .clicked()
+                {
+                    self.pending = true;
+                    self.progress = 0.0;
+                    settings = Some(SoundRenderSettings {
+                        sample_rate: self.sample_rate,
+                        duration: self.duration,
+                        output_path: PathBuf::from(&self.output_path),
+                    });
+                }
+                if self.pending {
+                    ui.add(egui::ProgressBar::new(self.progress).show_percentage());
+                }
+                match &self.last_response {
+                    Some(SoundRenderResponse {
+                        duration,
+                        result: Ok(path),
+                    }) => {
+                        ui.label(format!("Wrote {} in {:?}", path.display(), duration));
+                    }
+                    Some(SoundRenderResponse { result: Err(e), .. }) => {
+                        ui.label(RichText::new(e.to_string()).color(egui::Color32::RED));
+                    }
+                    None => {}
+                }
+            });
+        settings
+    }
+}
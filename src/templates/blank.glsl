@@ -0,0 +1,5 @@
+void mainImage(out vec4 out_color, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy;
+    vec3 col = 0.5 + 0.5 * cos(iTime + uv.xyx + vec3(0.0, 2.0, 4.0));
+    out_color = vec4(col, 1.0);
+}
@@ -0,0 +1,19 @@
+// `previous` holds what this shader drew last frame.
+#pragma @previous {source:"feedback"}
+uniform sampler2D previous;
+#pragma @decay {range:[0.8, 1]}
+uniform float decay = 0.97;
+
+void mainImage(out vec4 out_color, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy;
+    // Drift the last frame outwards and fade it.
+    vec2 from = 0.5 + (uv - 0.5) * 0.99;
+    vec3 col = texture(previous, from).rgb * decay;
+    if (iFrame == 0) col = vec3(0.0);
+
+    vec2 p = (2.0 * fragCoord - iResolution.xy) / iResolution.y;
+    vec2 brush = 0.5 * vec2(cos(iTime), sin(iTime * 1.3));
+    if (iMouse.z > 0.0) brush = (2.0 * iMouse.xy - iResolution.xy) / iResolution.y;
+    col += (0.5 + 0.5 * cos(iTime + vec3(0.0, 2.0, 4.0))) * smoothstep(0.05, 0.0, length(p - brush));
+    out_color = vec4(col, 1.0);
+}
@@ -0,0 +1,43 @@
+#pragma @steps {range:[16, 256]}
+uniform int steps = 96;
+#pragma @radius {range:[0.1, 2]}
+uniform float radius = 1.0;
+
+float map(vec3 p) {
+    float sphere = length(p) - radius;
+    float ground = p.y + 1.0;
+    return min(sphere, ground);
+}
+
+vec3 normal(vec3 p) {
+    vec2 e = vec2(1e-3, 0.0);
+    return normalize(vec3(
+        map(p + e.xyy) - map(p - e.xyy),
+        map(p + e.yxy) - map(p - e.yxy),
+        map(p + e.yyx) - map(p - e.yyx)
+    ));
+}
+
+void mainImage(out vec4 out_color, in vec2 fragCoord) {
+    vec2 p = (2.0 * fragCoord - iResolution.xy) / iResolution.y;
+    vec3 ro = vec3(3.0 * sin(iTime * 0.3), 1.0, 3.0 * cos(iTime * 0.3));
+    vec3 forward = normalize(-ro);
+    vec3 right = normalize(cross(forward, vec3(0.0, 1.0, 0.0)));
+    vec3 up = cross(right, forward);
+    vec3 rd = normalize(p.x * right + p.y * up + 1.5 * forward);
+
+    float t = 0.0;
+    for (int i = 0; i < steps && t < 50.0; i++) {
+        float d = map(ro + rd * t);
+        if (d < 1e-3) break;
+        t += d;
+    }
+
+    vec3 col = vec3(0.6, 0.7, 0.9) - rd.y * 0.3;
+    if (t < 50.0) {
+        vec3 n = normal(ro + rd * t);
+        vec3 light = normalize(vec3(0.6, 0.8, 0.4));
+        col = vec3(0.8, 0.6, 0.4) * (0.2 + 0.8 * max(dot(n, light), 0.0));
+    }
+    out_color = vec4(pow(col, vec3(0.4545)), 1.0);
+}
@@ -0,0 +1,20 @@
+#pragma @radius {range:[0, 1]}
+uniform float radius = 0.5;
+#pragma @center {widget:"point"}
+uniform vec2 center = vec2(0.5, 0.5);
+
+float sdCircle(vec2 p, float r) {
+    return length(p) - r;
+}
+
+void mainImage(out vec4 out_color, in vec2 fragCoord) {
+    vec2 p = (2.0 * fragCoord - iResolution.xy) / iResolution.y;
+    vec2 c = (2.0 * center * iResolution.xy - iResolution.xy) / iResolution.y;
+    float d = sdCircle(p - c, radius);
+
+    vec3 col = (d > 0.0) ? vec3(0.9, 0.6, 0.3) : vec3(0.65, 0.85, 1.0);
+    col *= 1.0 - exp(-6.0 * abs(d));
+    col *= 0.8 + 0.2 * cos(150.0 * d);
+    col = mix(col, vec3(1.0), 1.0 - smoothstep(0.0, 0.01, abs(d)));
+    out_color = vec4(col, 1.0);
+}
@@ -1,8 +1,47 @@
+use crate::audio::AudioTrack;
 use egui::TextureHandle;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 #[derive(Clone, Default)]
 pub struct WrappedTexture {
     pub handle: Option<TextureHandle>,
+    /// File the slot was loaded from, to reload it when it changes.
+    pub path: Option<PathBuf>,
+    /// When set, the slot is fed with the track's spectrum/waveform instead of `handle`.
+    pub audio: Option<Arc<AudioTrack>>,
+    /// When set, the slot is fed with the keyboard state texture.
+    pub keyboard: bool,
 }
 
 pub type Textures = [WrappedTexture; 4];
+
+/// Raw RGBA8 pixels uploaded as a linear (non-sRGB) GL texture,
+/// for data that shaders sample as values rather than as images.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+    /// Sample texels as they are rather than interpolated, for per-texel data like key states.
+    pub nearest: bool,
+}
+
+impl DataImage {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+            nearest: false,
+        }
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, rgba: [u8; 4]) {
+        let offset = (y * self.width + x) * 4;
+        self.pixels[offset..offset + 4].copy_from_slice(&rgba);
+    }
+}
+
+/// Data images bound to sampler uniforms by name.
+pub type NamedDataImages = Vec<(String, DataImage)>;
@@ -0,0 +1,121 @@
+#![allow(clippy::undocumented_unsafe_blocks)]
+
+use crate::gl::compile_program;
+use crate::prelude::{ModeInputs, PreludeMode};
+use crate::render_target::{RenderTarget, SavedTarget};
+use eframe::egui_glow::Painter;
+use eframe::glow;
+use eframe::glow::HasContext as _;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+
+pub const THUMBNAIL_SIZE: [usize; 2] = [160, 90];
+
+/// Shaders are captured at this `iTime`, past the first frame's blank state many of them have.
+const THUMBNAIL_TIME: f32 = 2.0;
+
+pub struct ThumbnailRenderRequest {
+    pub path: PathBuf,
+    pub hash: u64,
+    pub fragment_source: String,
+    pub response_sender: Sender<ThumbnailRenderResponse>,
+}
+
+pub struct ThumbnailRenderResponse {
+    pub path: PathBuf,
+    pub hash: u64,
+    /// Top-down RGBA8 pixels of `THUMBNAIL_SIZE`, or the compile error.
+    pub result: eyre::Result<Vec<u8>>,
+}
+
+pub(crate) fn render_thumbnail(
+    painter: &Painter,
+    request: &ThumbnailRenderRequest,
+    default_mode: Option<PreludeMode>,
+) {
+    request
+        .response_sender
+        .send(ThumbnailRenderResponse {
+            path: request.path.clone(),
+            hash: request.hash,
+            result: render_pixels(painter, &request.fragment_source, default_mode),
+        })
+        .ok();
+}
+
+#[allow(unsafe_code)]
+fn render_pixels(
+    painter: &Painter,
+    fragment_source: &str,
+    default_mode: Option<PreludeMode>,
+) -> eyre::Result<Vec<u8>> {
+    let gl = painter.gl();
+    let [width, height] = THUMBNAIL_SIZE;
+    let mode = PreludeMode::detect(fragment_source, default_mode);
+    let program = compile_program(gl, &mode.expand_source(fragment_source), mode)?;
+    let mut target = match RenderTarget::new(gl) {
+        Ok(target) => target,
+        Err(e) => {
+            unsafe { gl.delete_program(program) };
+            return Err(e);
+        }
+    };
+    unsafe {
+        let saved = SavedTarget::save(gl);
+        let vertex_array = gl
+            .create_vertex_array()
+            .expect("Cannot create vertex array");
+        target.bind(gl, width as i32, height as i32);
+        gl.disable(glow::BLEND);
+        gl.use_program(Some(program));
+        gl.uniform_3_f32(
+            gl.get_uniform_location(program, "iResolution").as_ref(),
+            width as f32,
+            height as f32,
+            1.0,
+        );
+        gl.uniform_4_f32(
+            gl.get_uniform_location(program, "iViewport").as_ref(),
+            0.0,
+            0.0,
+            width as f32,
+            height as f32,
+        );
+        gl.uniform_1_f32(
+            gl.get_uniform_location(program, "iTime").as_ref(),
+            THUMBNAIL_TIME,
+        );
+        mode.apply(
+            gl,
+            program,
+            &ModeInputs {
+                resolution: [width as f32, height as f32],
+                time: THUMBNAIL_TIME,
+                time_delta: 0.0,
+                frame: 0,
+                mouse: [0.0, 0.0],
+                pass_index: 0,
+            },
+        );
+        gl.bind_vertex_array(Some(vertex_array));
+        gl.draw_arrays(glow::TRIANGLES, 0, 6);
+        let mut pixels = vec![0u8; width * height * 4];
+        gl.read_pixels(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            glow::RGBA,
+            glow::UNSIGNED_BYTE,
+            glow::PixelPackData::Slice(Some(&mut pixels)),
+        );
+        gl.enable(glow::BLEND);
+        saved.restore(gl);
+        gl.delete_vertex_array(vertex_array);
+        target.destroy(gl);
+        gl.delete_program(program);
+        // GL rows run bottom-up.
+        let row = width * 4;
+        Ok(pixels.chunks_exact(row).rev().flatten().copied().collect())
+    }
+}
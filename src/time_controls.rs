@@ -0,0 +1,159 @@
+use crate::beat_clock::BeatClock;
+use crate::clock::Clock;
+use egui::{DragValue, Slider, Ui};
+
+pub fn time_controls(clock: &mut Clock, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        if ui
+            .button("⏮")
+            .on_hover_text("Step one frame back")
+            .clicked()
+        {
+            clock.step(-1);
+        }
+        let play_label = if clock.playing { "⏸" } else { "▶" };
+        if ui.button(play_label).on_hover_text("Play/pause").clicked() {
+            clock.playing = !clock.playing;
+        }
+        if ui
+            .button("⏭")
+            .on_hover_text("Step one frame forward")
+            .clicked()
+        {
+            clock.step(1);
+        }
+        if ui.button("Reset time").clicked() {
+            clock.reset();
+        }
+
+        let scrub_range = if clock.looping {
+            clock.loop_range[0]..=clock.loop_range[1]
+        } else {
+            0.0..=((clock.time() / 60.0).floor() + 1.0) * 60.0
+        };
+        let mut time = clock.time();
+        ui.spacing_mut().slider_width = 300.0;
+        if ui
+            .add(Slider::new(&mut time, scrub_range).text("s"))
+            .changed()
+        {
+            clock.seek(time);
+        }
+
+        ui.separator();
+        ui.add_enabled(
+            !clock.fixed_timestep,
+            DragValue::new(&mut clock.speed)
+                .range(0.0..=16.0)
+                .speed(0.01)
+                .prefix("×"),
+        )
+        .on_hover_text("Playback speed");
+
+        ui.separator();
+        if ui.checkbox(&mut clock.looping, "Loop").changed() && clock.looping {
+            clock.seek(clock.time().clamp(clock.loop_range[0], clock.loop_range[1]));
+        }
+        let now = clock.time();
+        let [a, b] = &mut clock.loop_range;
+        ui.add(
+            DragValue::new(&mut *a)
+                .range(0.0..=f64::MAX)
+                .speed(0.05)
+                .prefix("A: "),
+        );
+        if ui
+            .small_button("set")
+            .on_hover_text("Set A to current time")
+            .clicked()
+        {
+            *a = now;
+        }
+        ui.add(
+            DragValue::new(&mut *b)
+                .range(*a..=f64::MAX)
+                .speed(0.05)
+                .prefix("B: "),
+        );
+        if ui
+            .small_button("set")
+            .on_hover_text("Set B to current time")
+            .clicked()
+        {
+            *b = now;
+        }
+
+        ui.separator();
+        ui.checkbox(&mut clock.fixed_timestep, "Fixed timestep")
+            .on_hover_text("Advance time by exactly 1/fps per frame");
+        ui.add(
+            DragValue::new(&mut clock.fixed_fps)
+                .range(1.0..=240.0)
+                .speed(0.1)
+                .suffix(" fps"),
+        );
+    });
+}
+
+pub fn beat_controls(beat_clock: &mut BeatClock, time: f64, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        let mut bpm = beat_clock.bpm();
+        if ui
+            .add(
+                DragValue::new(&mut bpm)
+                    .range(1.0..=999.0)
+                    .speed(0.1)
+                    .max_decimals(2)
+                    .suffix(" BPM"),
+            )
+            .changed()
+        {
+            beat_clock.set_bpm(bpm, time);
+        }
+        if ui.button("Tap").on_hover_text("Tap tempo").clicked() {
+            beat_clock.tap(time);
+        }
+        if ui
+            .button("◀")
+            .on_hover_text("Nudge beats earlier")
+            .clicked()
+        {
+            beat_clock.nudge(-0.01);
+        }
+        if ui.button("▶").on_hover_text("Nudge beats later").clicked() {
+            beat_clock.nudge(0.01);
+        }
+        if ui
+            .button("Resync")
+            .on_hover_text("Make now the downbeat of a bar")
+            .clicked()
+        {
+            beat_clock.resync(time);
+        }
+        ui.add(
+            DragValue::new(&mut beat_clock.beats_per_bar)
+                .range(1..=16)
+                .suffix("/4"),
+        )
+        .on_hover_text("Beats per bar");
+
+        let beat = beat_clock.beat(time);
+        let beats_per_bar = beat_clock.beats_per_bar.max(1) as i64;
+        let beat_in_bar = beat.floor() as i64;
+        ui.label(format!(
+            "Bar {} beat {}",
+            beat_in_bar.div_euclid(beats_per_bar) + 1,
+            beat_in_bar.rem_euclid(beats_per_bar) + 1,
+        ));
+        let flash = 1.0 - beat.rem_euclid(1.0) as f32;
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+        ui.painter().circle_filled(
+            rect.center(),
+            6.0,
+            ui.visuals()
+                .selection
+                .bg_fill
+                .gamma_multiply(flash.powf(2.0)),
+        );
+    });
+}
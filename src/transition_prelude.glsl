@@ -0,0 +1,25 @@
+precision highp float;
+
+// gl-transitions.com conventions
+uniform sampler2D u_from;
+uniform sampler2D u_to;
+uniform float progress;
+uniform float ratio;
+uniform vec4 u_viewport;
+
+out vec4 transition_out_color;
+
+vec4 getFromColor(vec2 uv) {
+    return texture(u_from, uv);
+}
+
+vec4 getToColor(vec2 uv) {
+    return texture(u_to, uv);
+}
+
+vec4 transition(vec2 uv);
+
+void main() {
+    vec2 uv = (gl_FragCoord.xy - u_viewport.xy) / u_viewport.zw;
+    transition_out_color = vec4(transition(uv).rgb, 1.0);
+}
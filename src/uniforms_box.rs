@@ -1,14 +1,44 @@
-use crate::shader_parser::{PreparseResult, UniformSmell, UniformSpec};
+use crate::defines::DefineInfo;
+use crate::point_gizmos::PointGizmos;
+use crate::ramp_editor::{curve_editor, gradient_editor};
+use crate::shader_parser::{PreparseResult, UniformSmell, UniformSpec, UniformWidget};
 use crate::uniforms_values::UniformsValues;
-use egui::{Color32, Rgba, SliderClamping, Ui};
+use crate::viewport_nav::ViewportState;
+use egui::{Color32, Id, Rgba, SliderClamping, Ui};
 use std::ops::RangeInclusive;
 
-pub fn uniforms_box(uv: &mut UniformsValues, ppr: &PreparseResult, ui: &mut Ui) {
-    if ui.button("clear").clicked() {
-        uv.clear();
-    }
+pub fn uniforms_box(
+    uv: &mut UniformsValues,
+    ppr: &PreparseResult,
+    gizmos: &mut PointGizmos,
+    ui: &mut Ui,
+) {
+    ui.horizontal(|ui| {
+        if ui.button("clear").clicked() {
+            uv.clear();
+        }
+        if !gizmos.targets.is_empty() {
+            ui.checkbox(&mut gizmos.show_all, "all handles")
+                .on_hover_text("Show every point's handle, not just the selected one");
+        }
+    });
+    // Driven by the viewport widgets: their zoom and the low parts of their values.
+    let driven_uniforms: Vec<String> = ppr
+        .uniforms
+        .iter()
+        .filter(|u| u.widget == UniformWidget::Viewport)
+        .flat_map(|u| u.zoom.clone().into_iter().chain([format!("{}_lo", u.name)]))
+        .collect();
     for u in &ppr.uniforms {
         if matches!(u.spec, UniformSpec::Sampler2D) {
+            ramp_box(uv, &u.name, &u.widget, ui);
+            continue;
+        }
+        if u.widget == UniformWidget::Viewport {
+            viewport_box(uv, &u.name, ui);
+            continue;
+        }
+        if driven_uniforms.contains(&u.name) {
             continue;
         }
         let labels = match u.smell {
@@ -17,8 +47,22 @@ pub fn uniforms_box(uv: &mut UniformsValues, ppr: &PreparseResult, ui: &mut Ui)
         };
         ui.group(|ui| {
             let name = &u.name;
+            let label = u.label.as_ref().unwrap_or(name);
             ui.horizontal(|ui| {
-                ui.label(name.clone());
+                if u.widget == UniformWidget::Point {
+                    let selected = gizmos.selected.as_ref() == Some(name);
+                    if ui
+                        .selectable_label(selected, label.clone())
+                        .on_hover_text("Select to show its handle in the viewport")
+                        .clicked()
+                    {
+                        gizmos.selected = (!selected).then(|| name.clone());
+                    }
+                } else if u.label.is_some() {
+                    ui.label(label.clone()).on_hover_text(name.clone());
+                } else {
+                    ui.label(name.clone());
+                }
                 if ui
                     .button("reset")
                     .on_hover_text("Reset to default")
@@ -138,6 +182,109 @@ pub fn uniforms_box(uv: &mut UniformsValues, ppr: &PreparseResult, ui: &mut Ui)
     }
 }
 
+/// Switches for the shader's `#define`s; changing one recompiles the shader.
+pub fn defines_box(uv: &mut UniformsValues, defines: &[DefineInfo], ui: &mut Ui) {
+    if defines.is_empty() {
+        return;
+    }
+    ui.group(|ui| {
+        ui.horizontal(|ui| {
+            ui.label("Defines");
+            if ui
+                .button("reset")
+                .on_hover_text("Reset to the values in the file")
+                .clicked()
+            {
+                uv.define_values.clear();
+            }
+        });
+        for define in defines {
+            let current = uv
+                .define_values
+                .get(&define.name)
+                .unwrap_or(&define.default)
+                .clone();
+            let mut value = current.clone();
+            if define.values.is_empty() {
+                let mut on = value.is_some();
+                ui.checkbox(&mut on, &define.name);
+                if on != current.is_some() {
+                    value = on.then(String::new);
+                }
+            } else {
+                egui::ComboBox::new(("define", &define.name), &define.name)
+                    .selected_text(value.as_deref().unwrap_or("(undefined)"))
+                    .show_ui(ui, |ui| {
+                        for choice in &define.values {
+                            ui.selectable_value(&mut value, Some(choice.clone()), choice);
+                        }
+                    });
+            }
+            if value != current {
+                uv.define_values.insert(define.name.clone(), value);
+            }
+        }
+    });
+}
+
+fn viewport_box(uv: &mut UniformsValues, name: &str, ui: &mut Ui) {
+    ui.group(|ui| {
+        ui.horizontal(|ui| {
+            ui.label(name);
+            if ui.button("reset").on_hover_text("Reset view").clicked() {
+                uv.viewport_values.remove(name);
+            }
+        });
+        match uv.viewport_values.get(name) {
+            Some(ViewportState::CenterZoom { center, zoom, .. }) => {
+                ui.label(format!("center: {:.6e} {:.6e}", center[0], center[1]));
+                ui.label(format!("zoom: {:.3e}", zoom));
+            }
+            Some(ViewportState::Rect(rect)) => {
+                ui.label(format!("min: {:.6e} {:.6e}", rect[0], rect[1]));
+                ui.label(format!("max: {:.6e} {:.6e}", rect[2], rect[3]));
+            }
+            None => {}
+        }
+        ui.weak("Right/middle-drag to pan, scroll to zoom");
+    });
+}
+
+fn ramp_box(uv: &mut UniformsValues, name: &str, widget: &UniformWidget, ui: &mut Ui) {
+    if !matches!(widget, UniformWidget::Gradient | UniformWidget::Curve) {
+        return;
+    }
+    ui.group(|ui| {
+        ui.horizontal(|ui| {
+            ui.label(name);
+            if ui
+                .button("reset")
+                .on_hover_text("Reset to default")
+                .clicked()
+            {
+                uv.gradient_values.remove(name);
+                uv.curve_values.remove(name);
+            }
+        });
+        let id = Id::new("ramp").with(name);
+        match widget {
+            UniformWidget::Gradient => {
+                let mut gradient = uv.gradient_values.get(name).cloned().unwrap_or_default();
+                if gradient_editor(ui, id, &mut gradient) {
+                    uv.set_gradient_value(name, gradient);
+                }
+            }
+            UniformWidget::Curve => {
+                let mut curve = uv.curve_values.get(name).cloned().unwrap_or_default();
+                if curve_editor(ui, id, &mut curve) {
+                    uv.set_curve_value(name, curve);
+                }
+            }
+            _ => {}
+        }
+    });
+}
+
 fn single_component_slider(
     ui: &mut Ui,
     current: f32,
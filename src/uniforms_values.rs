@@ -1,18 +1,30 @@
 #![allow(clippy::undocumented_unsafe_blocks)]
 
+use crate::camera::CameraState;
+use crate::defines::DefineValues;
+use crate::ramps::{Curve, Gradient};
+use crate::viewport_nav::{ViewportState, split_f64};
 use eframe::egui_glow;
 use eframe::egui_glow::Painter;
 use eframe::glow::NativeProgram;
 use egui::ahash::HashMap;
 use egui_glow::glow;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct UniformsValues {
     pub int_values: HashMap<String, i32>,
     pub float_values: HashMap<String, f32>,
     pub vec2_values: HashMap<String, [f32; 2]>,
     pub vec3_values: HashMap<String, [f32; 3]>,
     pub vec4_values: HashMap<String, [f32; 4]>,
+    pub gradient_values: HashMap<String, Gradient>,
+    pub curve_values: HashMap<String, Curve>,
+    pub camera: Option<CameraState>,
+    pub viewport_values: HashMap<String, ViewportState>,
+    /// Overrides of the shader's switchable `#define`s; changing these recompiles it.
+    pub define_values: DefineValues,
 }
 
 impl UniformsValues {
@@ -31,12 +43,23 @@ impl UniformsValues {
     pub fn set_vec4_value(&mut self, name: &str, value: [f32; 4]) {
         self.vec4_values.insert(name.to_owned(), value);
     }
+    pub fn set_gradient_value(&mut self, name: &str, value: Gradient) {
+        self.gradient_values.insert(name.to_owned(), value);
+    }
+    pub fn set_curve_value(&mut self, name: &str, value: Curve) {
+        self.curve_values.insert(name.to_owned(), value);
+    }
     pub fn clear(&mut self) {
         self.int_values.clear();
         self.float_values.clear();
         self.vec2_values.clear();
         self.vec3_values.clear();
         self.vec4_values.clear();
+        self.gradient_values.clear();
+        self.curve_values.clear();
+        self.camera = None;
+        self.viewport_values.clear();
+        self.define_values.clear();
     }
     pub(crate) fn apply(&self, _painter: &Painter, gl: &glow::Context, program: NativeProgram) {
         use glow::HasContext as _;
@@ -57,6 +80,72 @@ impl UniformsValues {
             for (name, &[a, b, c, d]) in &self.vec4_values {
                 gl.uniform_4_f32(gl.get_uniform_location(program, name).as_ref(), a, b, c, d);
             }
+            for (name, state) in &self.viewport_values {
+                let lo_name = format!("{}_lo", name);
+                match state {
+                    ViewportState::CenterZoom {
+                        center,
+                        zoom,
+                        zoom_uniform,
+                    } => {
+                        let (x, x_lo) = split_f64(center[0]);
+                        let (y, y_lo) = split_f64(center[1]);
+                        gl.uniform_2_f32(gl.get_uniform_location(program, name).as_ref(), x, y);
+                        gl.uniform_2_f32(
+                            gl.get_uniform_location(program, &lo_name).as_ref(),
+                            x_lo,
+                            y_lo,
+                        );
+                        if let Some(zoom_uniform) = zoom_uniform {
+                            gl.uniform_1_f32(
+                                gl.get_uniform_location(program, zoom_uniform).as_ref(),
+                                *zoom as f32,
+                            );
+                        }
+                    }
+                    ViewportState::Rect(rect) => {
+                        let [(a, a_lo), (b, b_lo), (c, c_lo), (d, d_lo)] = rect.map(split_f64);
+                        gl.uniform_4_f32(
+                            gl.get_uniform_location(program, name).as_ref(),
+                            a,
+                            b,
+                            c,
+                            d,
+                        );
+                        gl.uniform_4_f32(
+                            gl.get_uniform_location(program, &lo_name).as_ref(),
+                            a_lo,
+                            b_lo,
+                            c_lo,
+                            d_lo,
+                        );
+                    }
+                }
+            }
+            let camera = self.camera.clone().unwrap_or_default();
+            let [x, y, z] = camera.position;
+            gl.uniform_3_f32(
+                gl.get_uniform_location(program, "iCameraPos").as_ref(),
+                x,
+                y,
+                z,
+            );
+            let [x, y, z] = camera.target;
+            gl.uniform_3_f32(
+                gl.get_uniform_location(program, "iCameraTarget").as_ref(),
+                x,
+                y,
+                z,
+            );
+            gl.uniform_matrix_3_f32_slice(
+                gl.get_uniform_location(program, "iCameraMatrix").as_ref(),
+                false,
+                &camera.matrix(),
+            );
+            gl.uniform_1_f32(
+                gl.get_uniform_location(program, "iFov").as_ref(),
+                camera.fov.to_radians(),
+            );
         }
     }
 }
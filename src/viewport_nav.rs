@@ -0,0 +1,170 @@
+use crate::shader_parser::{PreparseResult, UniformSpec, UniformWidget};
+use crate::uniforms_values::UniformsValues;
+use egui::{PointerButton, Rect, Response, Ui, Vec2};
+use serde::{Deserialize, Serialize};
+
+const ZOOM_SPEED: f64 = 0.002;
+
+/// Pan/zoom state of a viewport-widget uniform, kept in double precision.
+///
+/// A `vec2` center maps the viewport's height to `2 / zoom` world units around the center;
+/// a `vec4` rect holds `(min.x, min.y, max.x, max.y)`.
+/// The low-order float parts are written to `<name>_lo` for shaders doing deep zooms.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ViewportState {
+    CenterZoom {
+        center: [f64; 2],
+        zoom: f64,
+        zoom_uniform: Option<String>,
+    },
+    Rect([f64; 4]),
+}
+
+impl ViewportState {
+    /// Pan by a pointer movement in points, for a viewport of `size` points.
+    fn pan(&mut self, delta: Vec2, size: Vec2) {
+        let (dx, dy) = (delta.x as f64, delta.y as f64);
+        match self {
+            ViewportState::CenterZoom { center, zoom, .. } => {
+                let per_point = 2.0 / (*zoom * size.y as f64);
+                center[0] -= dx * per_point;
+                center[1] += dy * per_point;
+            }
+            ViewportState::Rect(rect) => {
+                let per_point_x = (rect[2] - rect[0]) / size.x as f64;
+                let per_point_y = (rect[3] - rect[1]) / size.y as f64;
+                rect[0] -= dx * per_point_x;
+                rect[2] -= dx * per_point_x;
+                rect[1] += dy * per_point_y;
+                rect[3] += dy * per_point_y;
+            }
+        }
+    }
+
+    /// Zoom by `factor` keeping the world point under `pos` (relative to the viewport's bottom-left) fixed.
+    fn zoom_at(&mut self, factor: f64, pos: Vec2, size: Vec2) {
+        match self {
+            ViewportState::CenterZoom { center, zoom, .. } => {
+                let per_point = 2.0 / (*zoom * size.y as f64);
+                let offset = [(pos.x - size.x * 0.5) as f64, (pos.y - size.y * 0.5) as f64];
+                *zoom *= factor;
+                for axis in 0..2 {
+                    center[axis] += offset[axis] * per_point * (1.0 - 1.0 / factor);
+                }
+            }
+            ViewportState::Rect(rect) => {
+                let fx = pos.x as f64 / size.x as f64;
+                let fy = pos.y as f64 / size.y as f64;
+                let wx = rect[0] + (rect[2] - rect[0]) * fx;
+                let wy = rect[1] + (rect[3] - rect[1]) * fy;
+                rect[0] = wx - (wx - rect[0]) / factor;
+                rect[2] = wx + (rect[2] - wx) / factor;
+                rect[1] = wy - (wy - rect[1]) / factor;
+                rect[3] = wy + (rect[3] - wy) / factor;
+            }
+        }
+    }
+}
+
+/// A uniform controlled by viewport navigation, with the state to start from.
+#[derive(Clone, Debug)]
+pub struct ViewportTarget {
+    pub name: String,
+    pub initial: ViewportState,
+}
+
+pub fn viewport_targets(ppr: &PreparseResult) -> Vec<ViewportTarget> {
+    let float_default = |name: &str| {
+        ppr.uniforms.iter().find_map(|u| match &u.spec {
+            UniformSpec::Float(f) if u.name == name => Some(f.certain_default() as f64),
+            _ => None,
+        })
+    };
+    ppr.uniforms
+        .iter()
+        .filter(|u| u.widget == UniformWidget::Viewport)
+        .filter_map(|u| {
+            let initial = match &u.spec {
+                UniformSpec::Vec2(spec) => ViewportState::CenterZoom {
+                    center: spec.certain_default().map(|c| c as f64),
+                    zoom: u
+                        .zoom
+                        .as_deref()
+                        .and_then(float_default)
+                        .filter(|z| *z > 0.0)
+                        .unwrap_or(1.0),
+                    zoom_uniform: u.zoom.clone(),
+                },
+                UniformSpec::Vec4(spec) => {
+                    let rect = spec.default.unwrap_or([-1.0, -1.0, 1.0, 1.0]);
+                    ViewportState::Rect(rect.map(|c| c as f64))
+                }
+                _ => {
+                    log::warn!(
+                        "Viewport widget on {} needs a vec2 center or a vec4 rect",
+                        u.name
+                    );
+                    return None;
+                }
+            };
+            Some(ViewportTarget {
+                name: u.name.clone(),
+                initial,
+            })
+        })
+        .collect()
+}
+
+/// Pan with the secondary or middle button and zoom with the scroll wheel,
+/// leaving the primary button to `iMouse`.
+pub fn navigate(
+    ui: &Ui,
+    response: &Response,
+    rect: Rect,
+    targets: &[ViewportTarget],
+    uv: &mut UniformsValues,
+) {
+    if targets.is_empty() {
+        return;
+    }
+    let size = rect.size();
+    let pan = if response.dragged_by(PointerButton::Secondary)
+        || response.dragged_by(PointerButton::Middle)
+    {
+        Some(response.drag_delta())
+    } else {
+        None
+    };
+    let zoom = match response.hover_pos() {
+        Some(pos) => {
+            let scroll = ui.input(|i| i.smooth_scroll_delta.y) as f64;
+            (scroll != 0.0).then(|| {
+                (
+                    (scroll * ZOOM_SPEED).exp(),
+                    Vec2::new(pos.x - rect.left(), rect.bottom() - pos.y),
+                )
+            })
+        }
+        None => None,
+    };
+    if pan.is_none() && zoom.is_none() {
+        return;
+    }
+    for target in targets {
+        let state = uv
+            .viewport_values
+            .entry(target.name.clone())
+            .or_insert_with(|| target.initial.clone());
+        if let Some(delta) = pan {
+            state.pan(delta, size);
+        }
+        if let Some((factor, pos)) = zoom {
+            state.zoom_at(factor, pos, size);
+        }
+    }
+}
+
+pub(crate) fn split_f64(value: f64) -> (f32, f32) {
+    let hi = value as f32;
+    (hi, (value - hi as f64) as f32)
+}
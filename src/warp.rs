@@ -0,0 +1,333 @@
+use egui::{Color32, CursorIcon, Id, Pos2, Rect, Sense, Stroke, Ui, Vec2};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const HANDLE_RADIUS: f32 = 7.0;
+const MAX_MESH_CELLS: usize = 16;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum WarpMode {
+    #[default]
+    Off,
+    /// Perspective-correct keystone through four corner handles.
+    CornerPin,
+    /// Free-form grid of handles.
+    Mesh,
+}
+
+/// Output-stage warp, with points normalized to the viewport and y pointing up.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WarpSettings {
+    pub mode: WarpMode,
+    /// Bottom-left, bottom-right, top-right, top-left.
+    pub corners: [[f32; 2]; 4],
+    /// Mesh cells across and up.
+    pub mesh_size: [usize; 2],
+    /// Row-major from the bottom-left, `(mesh_size[0] + 1) * (mesh_size[1] + 1)` points.
+    pub mesh: Vec<[f32; 2]>,
+    /// Soft edge widths at the left, right, bottom and top, as fractions of the output.
+    pub edge_blend: [f32; 4],
+    pub blend_gamma: f32,
+}
+
+impl Default for WarpSettings {
+    fn default() -> Self {
+        Self {
+            mode: WarpMode::Off,
+            corners: [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+            mesh_size: [4, 4],
+            mesh: Vec::new(),
+            edge_blend: [0.0; 4],
+            blend_gamma: 2.2,
+        }
+    }
+}
+
+impl WarpSettings {
+    /// Draws the source as is, filling the output.
+    pub(crate) fn unwarped() -> Self {
+        Self {
+            mode: WarpMode::CornerPin,
+            ..Default::default()
+        }
+    }
+
+    /// Mesh points, falling back to a grid spanning the corner pin if the mesh doesn't match its size.
+    fn mesh_points(&self) -> Vec<[f32; 2]> {
+        let [columns, rows] = self.mesh_size;
+        if self.mesh.len() == (columns + 1) * (rows + 1) {
+            return self.mesh.clone();
+        }
+        let [bl, br, tr, tl] = self.corners;
+        let lerp =
+            |a: [f32; 2], b: [f32; 2], t: f32| [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t];
+        (0..=rows)
+            .flat_map(|row| {
+                (0..=columns).map(move |column| {
+                    let (u, v) = (column as f32 / columns as f32, row as f32 / rows as f32);
+                    lerp(lerp(bl, br, u), lerp(tl, tr, u), v)
+                })
+            })
+            .collect()
+    }
+
+    fn handles(&self) -> Vec<[f32; 2]> {
+        match self.mode {
+            WarpMode::Off => Vec::new(),
+            WarpMode::CornerPin => self.corners.to_vec(),
+            WarpMode::Mesh => self.mesh_points(),
+        }
+    }
+
+    fn set_handle(&mut self, index: usize, point: [f32; 2]) {
+        match self.mode {
+            WarpMode::Off => {}
+            WarpMode::CornerPin => self.corners[index] = point,
+            WarpMode::Mesh => {
+                self.mesh = self.mesh_points();
+                self.mesh[index] = point;
+            }
+        }
+    }
+
+    /// Triangles as `[x, y, u*q, v*q, q]` vertices, positions normalized to the viewport.
+    pub(crate) fn vertices(&self) -> Vec<f32> {
+        let mut vertices = Vec::new();
+        let mut quad = |points: [[f32; 2]; 4], uvs: [[f32; 2]; 4], qs: [f32; 4]| {
+            for i in [0, 1, 2, 0, 2, 3] {
+                let q = qs[i];
+                vertices.extend_from_slice(&[
+                    points[i][0],
+                    points[i][1],
+                    uvs[i][0] * q,
+                    uvs[i][1] * q,
+                    q,
+                ]);
+            }
+        };
+        let unit = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+        match self.mode {
+            WarpMode::Off => {}
+            WarpMode::CornerPin => quad(self.corners, unit, projective_weights(self.corners)),
+            WarpMode::Mesh => {
+                let [columns, rows] = self.mesh_size;
+                let points = self.mesh_points();
+                let at = |column: usize, row: usize| points[row * (columns + 1) + column];
+                for row in 0..rows {
+                    for column in 0..columns {
+                        let uv = |c: usize, r: usize| {
+                            [c as f32 / columns as f32, r as f32 / rows as f32]
+                        };
+                        quad(
+                            [
+                                at(column, row),
+                                at(column + 1, row),
+                                at(column + 1, row + 1),
+                                at(column, row + 1),
+                            ],
+                            [
+                                uv(column, row),
+                                uv(column + 1, row),
+                                uv(column + 1, row + 1),
+                                uv(column, row + 1),
+                            ],
+                            [1.0; 4],
+                        );
+                    }
+                }
+            }
+        }
+        vertices
+    }
+}
+
+/// Per-corner `q` weights that make texture coordinates interpolate perspective-correctly
+/// across a quad split along its bl-tr diagonal, from where the diagonals cross.
+fn projective_weights(corners: [[f32; 2]; 4]) -> [f32; 4] {
+    let [p0, p1, p2, p3] = corners;
+    let d1 = [p2[0] - p0[0], p2[1] - p0[1]];
+    let d2 = [p3[0] - p1[0], p3[1] - p1[1]];
+    let denominator = d1[0] * d2[1] - d1[1] * d2[0];
+    if denominator.abs() < 1e-9 {
+        return [1.0; 4];
+    }
+    let t = ((p1[0] - p0[0]) * d2[1] - (p1[1] - p0[1]) * d2[0]) / denominator;
+    let s = ((p1[0] - p0[0]) * d1[1] - (p1[1] - p0[1]) * d1[0]) / denominator;
+    if !(0.0..=1.0).contains(&t) || !(0.0..=1.0).contains(&s) {
+        // Not convex; there's no sensible perspective, so fall back to affine.
+        return [1.0; 4];
+    }
+    let length = |v: [f32; 2]| (v[0] * v[0] + v[1] * v[1]).sqrt();
+    let l1 = length(d1);
+    let l2 = length(d2);
+    // Distances from each corner to the diagonals' intersection.
+    let d = [t * l1, s * l2, (1.0 - t) * l1, (1.0 - s) * l2];
+    if d.iter().any(|d| *d <= 0.0) {
+        return [1.0; 4];
+    }
+    [
+        (d[0] + d[2]) / d[2],
+        (d[1] + d[3]) / d[3],
+        (d[2] + d[0]) / d[0],
+        (d[3] + d[1]) / d[1],
+    ]
+}
+
+/// Warp settings backed by a config file, with on-viewport handle editing.
+pub struct Warp {
+    pub settings: WarpSettings,
+    /// Show draggable handles over the output.
+    pub editing: bool,
+    pub open: bool,
+    path: PathBuf,
+    saved: WarpSettings,
+}
+
+impl Warp {
+    pub fn load(path: &Path) -> Self {
+        let settings = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json5::from_str(&text).unwrap_or_else(|e| {
+                log::error!("Error parsing warp settings {:?}: {}", path, e);
+                WarpSettings::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => WarpSettings::default(),
+            Err(e) => {
+                log::error!("Error reading warp settings {:?}: {}", path, e);
+                WarpSettings::default()
+            }
+        };
+        Self {
+            saved: settings.clone(),
+            settings,
+            editing: false,
+            open: false,
+            path: path.to_path_buf(),
+        }
+    }
+
+    pub fn save_if_changed(&mut self) {
+        if self.settings == self.saved {
+            return;
+        }
+        let result = serde_json5::to_string(&self.settings)
+            .map_err(|e| eyre::eyre!(e))
+            .and_then(|text| Ok(std::fs::write(&self.path, text)?));
+        if let Err(e) = result {
+            log::error!("Error saving warp settings {:?}: {}", self.path, e);
+        }
+        self.saved = self.settings.clone();
+    }
+
+    /// Drag and draw the handles over the output at `rect`.
+    pub fn show_handles(&mut self, ui: &Ui, rect: Rect) {
+        if !self.editing {
+            return;
+        }
+        let to_screen = |p: [f32; 2]| {
+            Pos2::new(
+                rect.left() + p[0] * rect.width(),
+                rect.bottom() - p[1] * rect.height(),
+            )
+        };
+        let painter = ui.painter_at(rect);
+        let handles = self.settings.handles();
+        if self.settings.mode == WarpMode::CornerPin {
+            let outline: Vec<Pos2> = handles.iter().map(|p| to_screen(*p)).collect();
+            painter.add(egui::Shape::closed_line(
+                outline,
+                Stroke::new(1.0, Color32::from_white_alpha(128)),
+            ));
+        }
+        for (index, handle) in handles.iter().enumerate() {
+            let center = to_screen(*handle);
+            let response = ui
+                .interact(
+                    Rect::from_center_size(center, Vec2::splat(HANDLE_RADIUS * 3.0)),
+                    Id::new("warp_handle").with(index),
+                    Sense::drag(),
+                )
+                .on_hover_cursor(CursorIcon::Grab);
+            let mut center = center;
+            if response.dragged() {
+                center = (center + response.drag_delta()).clamp(rect.min, rect.max);
+                self.settings.set_handle(
+                    index,
+                    [
+                        (center.x - rect.left()) / rect.width(),
+                        (rect.bottom() - center.y) / rect.height(),
+                    ],
+                );
+            }
+            let active = response.hovered() || response.dragged();
+            painter.circle(
+                center,
+                HANDLE_RADIUS,
+                if active {
+                    Color32::from_rgba_unmultiplied(255, 200, 0, 160)
+                } else {
+                    Color32::from_black_alpha(96)
+                },
+                Stroke::new(2.0, Color32::from_rgb(255, 200, 0)),
+            );
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let mut open = self.open;
+        egui::Window::new("Warp")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let settings = &mut self.settings;
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut settings.mode, WarpMode::Off, "Off");
+                    ui.radio_value(&mut settings.mode, WarpMode::CornerPin, "Corner pin");
+                    ui.radio_value(&mut settings.mode, WarpMode::Mesh, "Mesh");
+                });
+                ui.checkbox(&mut self.editing, "Edit handles")
+                    .on_hover_text("The warp handles shortcut (F2 by default) toggles this, also in presentation mode");
+                if settings.mode == WarpMode::Mesh {
+                    ui.horizontal(|ui| {
+                        let mut size = settings.mesh_size;
+                        ui.label("Grid");
+                        ui.add(egui::DragValue::new(&mut size[0]).range(1..=MAX_MESH_CELLS));
+                        ui.label("×");
+                        ui.add(egui::DragValue::new(&mut size[1]).range(1..=MAX_MESH_CELLS));
+                        if size != settings.mesh_size {
+                            settings.mesh_size = size;
+                            settings.mesh.clear();
+                        }
+                    });
+                }
+                ui.label("Edge blend");
+                for (index, label) in ["left", "right", "bottom", "top"].iter().enumerate() {
+                    ui.add(
+                        egui::Slider::new(&mut settings.edge_blend[index], 0.0..=0.5).text(*label),
+                    );
+                }
+                ui.add(egui::Slider::new(&mut settings.blend_gamma, 1.0..=3.0).text("gamma"));
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Reset")
+                        .on_hover_text("Reset corners and mesh")
+                        .clicked()
+                    {
+                        let defaults = WarpSettings::default();
+                        settings.corners = defaults.corners;
+                        settings.mesh.clear();
+                    }
+                    if settings.mode == WarpMode::Mesh
+                        && ui
+                            .button("Mesh from corners")
+                            .on_hover_text("Lay the mesh out over the corner pin")
+                            .clicked()
+                    {
+                        settings.mesh.clear();
+                    }
+                });
+                ui.weak(format!("Saved to {}", self.path.display()));
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,20 @@
+precision highp float;
+
+uniform sampler2D u_source;
+// Blend widths at the left, right, bottom and top edges, in source UV units.
+uniform vec4 u_edge_blend;
+uniform float u_blend_gamma;
+
+in vec3 v_uvq;
+out vec4 out_color;
+
+float edge(float x, float width) {
+    return width > 0.0 ? pow(smoothstep(0.0, 1.0, x / width), 1.0 / u_blend_gamma) : 1.0;
+}
+
+void main() {
+    vec2 uv = v_uvq.xy / v_uvq.z;
+    float blend = edge(uv.x, u_edge_blend.x) * edge(1.0 - uv.x, u_edge_blend.y)
+        * edge(uv.y, u_edge_blend.z) * edge(1.0 - uv.y, u_edge_blend.w);
+    out_color = vec4(texture(u_source, uv).rgb * blend, 1.0);
+}
@@ -0,0 +1,114 @@
+#![allow(clippy::undocumented_unsafe_blocks)]
+
+use crate::gl::compile_program_with_vertex_shader;
+use crate::render_target::{RenderTarget, SavedTarget};
+use crate::warp::WarpSettings;
+use eframe::glow;
+use eframe::glow::HasContext as _;
+
+const WARP_VERTEX_SHADER: &str = include_str!("warp_vertex.glsl");
+const WARP_FRAGMENT_SHADER: &str = include_str!("warp_fragment.glsl");
+
+/// Offscreen target the shader renders into, and the mesh that draws it warped.
+pub(crate) struct WarpPass {
+    program: glow::Program,
+    vertex_array: glow::VertexArray,
+    buffer: glow::Buffer,
+    target: RenderTarget,
+}
+
+#[allow(unsafe_code)]
+impl WarpPass {
+    pub(crate) fn new(gl: &glow::Context) -> eyre::Result<Self> {
+        let program =
+            compile_program_with_vertex_shader(gl, WARP_VERTEX_SHADER, WARP_FRAGMENT_SHADER, "")?;
+        unsafe {
+            let vertex_array = gl.create_vertex_array().map_err(|e| eyre::eyre!(e))?;
+            let buffer = gl.create_buffer().map_err(|e| eyre::eyre!(e))?;
+            gl.bind_vertex_array(Some(vertex_array));
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+            let stride = 5 * size_of::<f32>() as i32;
+            for (name, size, offset) in [("a_position", 2, 0), ("a_uvq", 3, 2)] {
+                if let Some(location) = gl.get_attrib_location(program, name) {
+                    gl.enable_vertex_attrib_array(location);
+                    gl.vertex_attrib_pointer_f32(
+                        location,
+                        size,
+                        glow::FLOAT,
+                        false,
+                        stride,
+                        offset * size_of::<f32>() as i32,
+                    );
+                }
+            }
+            gl.bind_vertex_array(None);
+            gl.bind_buffer(glow::ARRAY_BUFFER, None);
+
+            Ok(Self {
+                program,
+                vertex_array,
+                buffer,
+                target: RenderTarget::new(gl)?,
+            })
+        }
+    }
+
+    /// Redirect drawing into the offscreen texture, sized `width`×`height`.
+    pub(crate) fn begin(&mut self, gl: &glow::Context, width: i32, height: i32) -> SavedTarget {
+        let saved = SavedTarget::save(gl);
+        self.target.bind(gl, width, height);
+        saved
+    }
+
+    /// Restore egui's target and draw the offscreen texture through the warp.
+    pub(crate) fn end(&self, gl: &glow::Context, saved: SavedTarget, settings: &WarpSettings) {
+        unsafe {
+            saved.restore(gl);
+            // Only the callback's clip rect is cleared, since egui scissors it.
+            gl.clear_color(0.0, 0.0, 0.0, 1.0);
+            gl.clear(glow::COLOR_BUFFER_BIT);
+
+            let vertices = settings.vertices();
+            let bytes: Vec<u8> = vertices.iter().flat_map(|v| v.to_ne_bytes()).collect();
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.buffer));
+            gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, &bytes, glow::STREAM_DRAW);
+            gl.bind_buffer(glow::ARRAY_BUFFER, None);
+
+            gl.use_program(Some(self.program));
+            gl.active_texture(glow::TEXTURE0);
+            gl.bind_texture(glow::TEXTURE_2D, Some(self.target.texture));
+            gl.uniform_1_i32(
+                gl.get_uniform_location(self.program, "u_source").as_ref(),
+                0,
+            );
+            let [left, right, bottom, top] = settings.edge_blend;
+            gl.uniform_4_f32(
+                gl.get_uniform_location(self.program, "u_edge_blend")
+                    .as_ref(),
+                left,
+                right,
+                bottom,
+                top,
+            );
+            gl.uniform_1_f32(
+                gl.get_uniform_location(self.program, "u_blend_gamma")
+                    .as_ref(),
+                settings.blend_gamma.max(0.1),
+            );
+            gl.disable(glow::BLEND);
+            gl.bind_vertex_array(Some(self.vertex_array));
+            gl.draw_arrays(glow::TRIANGLES, 0, (vertices.len() / 5) as i32);
+            gl.bind_vertex_array(None);
+            gl.enable(glow::BLEND);
+        }
+    }
+
+    pub(crate) fn destroy(&self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_program(self.program);
+            gl.delete_vertex_array(self.vertex_array);
+            gl.delete_buffer(self.buffer);
+            self.target.destroy(gl);
+        }
+    }
+}
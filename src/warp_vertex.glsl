@@ -0,0 +1,7 @@
+in vec2 a_position;
+in vec3 a_uvq;
+out vec3 v_uvq;
+void main() {
+    v_uvq = a_uvq;
+    gl_Position = vec4(a_position * 2.0 - 1.0, 0.0, 1.0);
+}